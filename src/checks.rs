@@ -0,0 +1,215 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Storage sections whose entries each claim a filesystem path
+const PATH_SECTIONS: &[&str] = &["files", "directories", "links"];
+
+/// Ensure every absolute path is claimed by at most one entry across
+/// `storage.files`, `storage.directories` and `storage.links`, reporting all
+/// conflicts at once.
+pub fn verify_paths_unique(config: &Value) -> anyhow::Result<()> {
+    let mut claims: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for section in PATH_SECTIONS {
+        let entries = config
+            .pointer(&format!("/storage/{}", section))
+            .and_then(|v| v.as_array());
+        for (index, entry) in entries.into_iter().flatten().enumerate() {
+            if let Some(path) = entry.get("path").and_then(|p| p.as_str()) {
+                let normalized = path.trim_end_matches('/');
+                claims
+                    .entry(normalized.to_string())
+                    .or_default()
+                    .push(format!("storage.{}[{}]", section, index));
+            }
+        }
+    }
+
+    let conflicts: Vec<String> = claims
+        .iter()
+        .filter(|(_, owners)| owners.len() > 1)
+        .map(|(path, owners)| format!("{} is claimed by {}", path, owners.join(", ")))
+        .collect();
+
+    if !conflicts.is_empty() {
+        anyhow::bail!("Conflicting storage paths:\n  {}", conflicts.join("\n  "));
+    }
+
+    Ok(())
+}
+
+/// Top-level sections defined by every 3.x spec
+const BASE_SECTIONS: &[&str] = &["ignition", "passwd", "storage", "systemd"];
+
+/// Ensure every top-level key of `config` is a section its spec version
+/// defines, reporting all unknown keys at once
+pub fn verify_known_sections(config: &Value) -> anyhow::Result<()> {
+    let version = config
+        .pointer("/ignition/version")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let version = crate::version_range::parse_version(version)?;
+    // kernelArguments was added in spec 3.3
+    let known = |key: &str| {
+        BASE_SECTIONS.contains(&key) || (key == "kernelArguments" && version >= (3, 3, 0))
+    };
+
+    let unknown: Vec<&str> = config
+        .as_object()
+        .into_iter()
+        .flat_map(|map| map.keys())
+        .map(String::as_str)
+        .filter(|key| !known(key))
+        .collect();
+
+    if !unknown.is_empty() {
+        anyhow::bail!(
+            "Unknown top-level section(s) for spec version {}.{}.{}: {}",
+            version.0,
+            version.1,
+            version.2,
+            unknown.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Highest mode Ignition accepts (permission and setuid/setgid/sticky bits)
+pub const MAX_MODE: i64 = 0o7777;
+
+/// Ensure every `mode` in `storage.files` and `storage.directories` lies in
+/// `0..=0o7777`, reporting all offenders at once.
+pub fn verify_modes(config: &Value) -> anyhow::Result<()> {
+    let mut invalid = Vec::new();
+
+    for section in ["files", "directories"] {
+        let entries = config
+            .pointer(&format!("/storage/{}", section))
+            .and_then(|v| v.as_array());
+        for (index, entry) in entries.into_iter().flatten().enumerate() {
+            let Some(mode) = entry.get("mode").filter(|m| !m.is_null()) else {
+                continue;
+            };
+            if mode.as_i64().is_some_and(|m| (0..=MAX_MODE).contains(&m)) {
+                continue;
+            }
+            let path = entry.get("path").and_then(|p| p.as_str()).unwrap_or("");
+            invalid.push(format!(
+                "storage.{}[{}] ({}): mode {} is outside 0..=0o7777",
+                section, index, path, mode
+            ));
+        }
+    }
+
+    if !invalid.is_empty() {
+        anyhow::bail!("Invalid modes:\n  {}", invalid.join("\n  "));
+    }
+
+    Ok(())
+}
+
+/// Ensure `relative`, the output location derived from `ignition_path`,
+/// stays below the directory it is joined onto: no `..`, root or prefix
+/// components
+pub fn ensure_contained(relative: &str, ignition_path: &str) -> anyhow::Result<()> {
+    let escapes = Path::new(relative)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if escapes {
+        anyhow::bail!(
+            "Refusing to extract path outside the output directory: {}",
+            ignition_path
+        );
+    }
+    Ok(())
+}
+
+/// Refuse to write to `path` when something other than a regular file (a
+/// FIFO, socket, device node, directory or symlink) already exists there,
+/// since writing to it could block, have side effects or land elsewhere
+pub fn ensure_regular_target(path: &Path) -> anyhow::Result<()> {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if metadata.is_file() {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "Refusing to write over existing {}: {}",
+        special_file_kind(&metadata.file_type()),
+        path.display()
+    )
+}
+
+#[cfg(unix)]
+fn special_file_kind(file_type: &fs::FileType) -> &'static str {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_fifo() {
+        "named pipe"
+    } else if file_type.is_socket() {
+        "socket"
+    } else if file_type.is_block_device() || file_type.is_char_device() {
+        "device node"
+    } else if file_type.is_dir() {
+        "directory"
+    } else if file_type.is_symlink() {
+        "symlink"
+    } else {
+        "special file"
+    }
+}
+
+#[cfg(not(unix))]
+fn special_file_kind(file_type: &fs::FileType) -> &'static str {
+    if file_type.is_dir() {
+        "directory"
+    } else if file_type.is_symlink() {
+        "symlink"
+    } else {
+        "special file"
+    }
+}
+
+/// Refuse to read `relative` under `root` when it, or any directory between
+/// it and `root`, is a symlink
+pub fn ensure_no_symlinks(root: &Path, relative: &str) -> anyhow::Result<()> {
+    if let Some(symlink) = first_symlink(root, Path::new(relative)) {
+        anyhow::bail!(
+            "Refusing to follow symlink {} (use --follow-symlinks allow)",
+            symlink.display()
+        );
+    }
+    Ok(())
+}
+
+/// Refuse to create `relative` under `root` when it, or any directory
+/// between it and `root`, is a symlink the write would follow, possibly out
+/// of `root`
+pub fn ensure_no_symlinks_below(root: &Path, relative: &Path) -> anyhow::Result<()> {
+    if let Some(symlink) = first_symlink(root, relative) {
+        anyhow::bail!("Refusing to write through symlink {}", symlink.display());
+    }
+    Ok(())
+}
+
+/// The first existing symlink on the way from `root` down to `relative`,
+/// which may start with `/`
+fn first_symlink(root: &Path, relative: &Path) -> Option<PathBuf> {
+    let mut current = root.to_path_buf();
+    for component in relative.components() {
+        match component {
+            Component::RootDir | Component::Prefix(_) | Component::CurDir => continue,
+            _ => current.push(component),
+        }
+        let is_symlink = fs::symlink_metadata(&current)
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false);
+        if is_symlink {
+            return Some(current);
+        }
+    }
+    None
+}
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::verification::inline_content;
+
+/// Entry kinds compared by [`diff_configs`]: label, array pointer and key field
+const SECTIONS: &[(&str, &str, &str)] = &[
+    ("file", "/storage/files", "path"),
+    ("directory", "/storage/directories", "path"),
+    ("link", "/storage/links", "path"),
+    ("unit", "/systemd/units", "name"),
+    ("user", "/passwd/users", "name"),
+    ("group", "/passwd/groups", "name"),
+];
+
+/// The logical content of a file resource, independent of its encoding
+#[derive(Debug, PartialEq)]
+enum Resource {
+    Inline(Vec<u8>),
+    Remote(String),
+}
+
+/// An entry reduced to what it means on the target system
+#[derive(Debug, PartialEq)]
+struct Normalized {
+    fields: Value,
+    content: Vec<Resource>,
+}
+
+/// Compare two configs semantically, returning one line per added, removed
+/// or changed file, directory, link, unit, user or group, e.g.
+/// `file /etc/foo changed: 12 -> 40 bytes`. Sources are compared by their
+/// decoded content, so configs that differ only in base64, percent-encoding
+/// or compression compare equal.
+pub fn diff_configs(left: &Value, right: &Value) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+
+    for (label, pointer, key) in SECTIONS {
+        let left_entries = entries(left, pointer, key)?;
+        let mut right_entries = entries(right, pointer, key)?;
+
+        for (name, left_entry) in left_entries {
+            match right_entries.remove(&name) {
+                None => lines.push(format!("{} {} removed", label, name)),
+                Some(right_entry) if right_entry != left_entry => {
+                    lines.push(format!(
+                        "{} {} changed: {}",
+                        label,
+                        name,
+                        describe_change(&left_entry, &right_entry)
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+        for name in right_entries.keys() {
+            lines.push(format!("{} {} added", label, name));
+        }
+    }
+
+    Ok(lines)
+}
+
+fn entries(config: &Value, pointer: &str, key: &str) -> Result<BTreeMap<String, Normalized>> {
+    let mut entries = BTreeMap::new();
+    let array = config.pointer(pointer).and_then(|v| v.as_array());
+    for entry in array.into_iter().flatten() {
+        let Some(name) = entry.get(key).and_then(|k| k.as_str()) else {
+            continue;
+        };
+        entries.insert(name.to_string(), normalize(entry)?);
+    }
+    Ok(entries)
+}
+
+fn normalize(entry: &Value) -> Result<Normalized> {
+    let mut fields = entry.clone();
+    let mut content = Vec::new();
+
+    if let Some(map) = fields.as_object_mut() {
+        let resources = map
+            .remove("contents")
+            .into_iter()
+            .filter(|c| !c.is_null())
+            .chain(
+                map.remove("append")
+                    .and_then(|a| a.as_array().cloned())
+                    .into_iter()
+                    .flatten(),
+            );
+        for resource in resources {
+            content.push(match inline_content(&resource)? {
+                Some(bytes) => Resource::Inline(bytes),
+                None => Resource::Remote(
+                    resource
+                        .get("source")
+                        .and_then(|s| s.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                ),
+            });
+        }
+    }
+    strip_nulls(&mut fields);
+
+    Ok(Normalized { fields, content })
+}
+
+/// Drop null and empty fields, which mean "unset" in every spec version
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.values_mut().for_each(strip_nulls);
+            map.retain(|_, v| match v {
+                Value::Null => false,
+                Value::Array(arr) => !arr.is_empty(),
+                Value::Object(obj) => !obj.is_empty(),
+                _ => true,
+            });
+        }
+        Value::Array(arr) => arr.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+fn describe_change(left: &Normalized, right: &Normalized) -> String {
+    let mut changes = Vec::new();
+
+    if left.content != right.content {
+        let (left_size, right_size) = (inline_size(&left.content), inline_size(&right.content));
+        if left_size != right_size {
+            changes.push(format!("{} -> {} bytes", left_size, right_size));
+        } else {
+            changes.push(format!("content ({} bytes)", right_size));
+        }
+    }
+
+    let empty = serde_json::Map::new();
+    let left_fields = left.fields.as_object().unwrap_or(&empty);
+    let right_fields = right.fields.as_object().unwrap_or(&empty);
+    let mut keys: Vec<&String> = left_fields.keys().chain(right_fields.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        if left_fields.get(key) != right_fields.get(key) {
+            changes.push(key.clone());
+        }
+    }
+
+    changes.join(", ")
+}
+
+fn inline_size(content: &[Resource]) -> usize {
+    content
+        .iter()
+        .map(|r| match r {
+            Resource::Inline(bytes) => bytes.len(),
+            Resource::Remote(_) => 0,
+        })
+        .sum()
+}
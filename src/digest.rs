@@ -0,0 +1,33 @@
+use sha2::{Digest, Sha256, Sha512};
+
+/// Directory of the decoded tree holding content-addressed files
+pub const BLOBS_DIR: &str = "blobs";
+
+/// Digest used to name content-addressed files. This is independent of the
+/// algorithm of any Ignition `verification.hash`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentDigest {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl ContentDigest {
+    fn name(self) -> &'static str {
+        match self {
+            ContentDigest::Sha256 => "sha256",
+            ContentDigest::Sha512 => "sha512",
+            ContentDigest::Blake3 => "blake3",
+        }
+    }
+
+    /// Relative path of the blob holding `content`, e.g. `blobs/blake3-<hex>`
+    pub fn blob_path(self, content: &[u8]) -> String {
+        let digest = match self {
+            ContentDigest::Sha256 => crate::verification::to_hex(&Sha256::digest(content)),
+            ContentDigest::Sha512 => crate::verification::to_hex(&Sha512::digest(content)),
+            ContentDigest::Blake3 => blake3::hash(content).to_hex().to_string(),
+        };
+        format!("{}/{}-{}", BLOBS_DIR, self.name(), digest)
+    }
+}
@@ -0,0 +1,52 @@
+use serde_json::Value;
+
+/// Render a Containerfile snippet with one `COPY` per extracted
+/// `storage.files` content, mapping the file in the decoded directory to the
+/// absolute path it has in the config.
+///
+/// `decoded` is the config as written to decoded.ign, so sources are
+/// placeholders; the snippet expects the decoded directory as build context.
+/// Appended fragments and remote sources have no single file to copy and are
+/// left out.
+pub fn copy_snippet(decoded: &Value) -> String {
+    let mut snippet = String::from(
+        "# Generated by fcos-ignition-coder; build context is the decoded directory\n",
+    );
+
+    let files = decoded
+        .pointer("/storage/files")
+        .and_then(|f| f.as_array())
+        .into_iter()
+        .flatten();
+
+    for file in files {
+        let target = file.get("path").and_then(|p| p.as_str());
+        let source = file
+            .pointer("/contents/source")
+            .and_then(|s| s.as_str())
+            .and_then(placeholder_file);
+        if let (Some(target), Some(source)) = (target, source) {
+            snippet.push_str(&copy_line(source, target));
+            snippet.push('\n');
+        }
+    }
+
+    snippet
+}
+
+/// The decoded-directory path referenced by a placeholder source
+fn placeholder_file(source: &str) -> Option<&str> {
+    source
+        .split_once(";base64-placeholder,")
+        .map(|(_, file)| file)
+        .filter(|file| !file.is_empty())
+}
+
+/// Paths containing whitespace need the JSON form of `COPY`
+fn copy_line(source: &str, target: &str) -> String {
+    if source.contains(char::is_whitespace) || target.contains(char::is_whitespace) {
+        format!("COPY [{}, {}]", Value::from(source), Value::from(target))
+    } else {
+        format!("COPY {} {}", source, target)
+    }
+}
@@ -0,0 +1,30 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Write `KEY=VALUE` lines in dotenv format, quoting values that need it
+pub fn write(path: &Path, entries: &[(&str, String)]) -> Result<()> {
+    let mut content = String::new();
+    for (key, value) in entries {
+        content.push_str(&format!("{}={}\n", key, quote(value)));
+    }
+    fs::write(path, content)
+        .with_context(|| format!("Failed to write env file: {}", path.display()))
+}
+
+fn quote(value: &str) -> String {
+    let plain = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "._-/:+".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        format!(
+            "\"{}\"",
+            value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        )
+    }
+}
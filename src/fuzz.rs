@@ -0,0 +1,211 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::unified_diff::unified_diff;
+use crate::verbosity::status;
+use crate::{assemble_ignition, disassemble_ignition, AssembleOptions, DisassembleOptions};
+
+/// Run `iterations` round trips (disassemble, assemble, disassemble) over
+/// random configs, failing with the seed of the first config whose content
+/// does not survive. Iteration `i` uses seed `seed + i`. With `diff_output`,
+/// a failing round trip writes a unified diff of the files that changed there.
+pub fn run_fuzz(iterations: usize, seed: u64, diff_output: Option<&Path>) -> Result<()> {
+    for iteration in 0..iterations as u64 {
+        let seed = seed.wrapping_add(iteration);
+        let config = random_config(seed);
+        round_trip(&config, seed, diff_output)
+            .with_context(|| format!("Round trip failed; reproduce with --seed {}", seed))?;
+    }
+    status(
+        false,
+        &format!(
+            "\n{} round trip(s) passed (seeds {}..{})",
+            iterations,
+            seed,
+            seed.wrapping_add(iterations as u64)
+        ),
+    );
+    Ok(())
+}
+
+/// Generate a valid config with random files, modes, append arrays and units
+pub fn random_config(seed: u64) -> Value {
+    let mut rng = fastrand::Rng::with_seed(seed);
+
+    let mut files = Vec::new();
+    for index in 0..rng.usize(0..6) {
+        let mut file = json!({
+            "path": format!("/etc/fuzz/dir{}/file{}", rng.usize(0..3), index),
+        });
+        if let Some(mode) = [None, Some(0o644), Some(0o600), Some(0o755)][rng.usize(0..4)] {
+            file["mode"] = json!(mode);
+        }
+        // Contents and append of the same file would both claim its path in
+        // the decoded tree, so only one of them is generated
+        match rng.usize(0..3) {
+            0 => file["contents"] = json!({ "source": random_source(&mut rng) }),
+            1 => {
+                let append: Vec<Value> = (0..rng.usize(1..4))
+                    .map(|_| json!({ "source": random_source(&mut rng) }))
+                    .collect();
+                file["append"] = Value::Array(append);
+            }
+            _ => {}
+        }
+        files.push(file);
+    }
+
+    let units: Vec<Value> = (0..rng.usize(0..3))
+        .map(|index| {
+            json!({
+                "name": format!("fuzz{}.service", index),
+                "enabled": rng.bool(),
+                "contents": format!(
+                    "[Unit]\nDescription=Fuzz unit {}\n\n[Service]\nExecStart=/bin/true\n",
+                    rng.u32(..)
+                ),
+            })
+        })
+        .collect();
+
+    json!({
+        "ignition": { "version": "3.4.0" },
+        "storage": { "files": files },
+        "systemd": { "units": units },
+    })
+}
+
+/// A data URL holding random text or random bytes (base64)
+fn random_source(rng: &mut fastrand::Rng) -> String {
+    let len = rng.usize(0..64);
+    if rng.bool() {
+        let text: String = (0..len).map(|_| rng.alphanumeric()).collect();
+        format!("data:,{}", text)
+    } else {
+        let bytes: Vec<u8> = (0..len).map(|_| rng.u8(..)).collect();
+        format!(
+            "data:application/octet-stream;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        )
+    }
+}
+
+/// Disassemble, assemble and disassemble again, requiring both decoded
+/// trees to be identical
+fn round_trip(config: &Value, seed: u64, diff_output: Option<&Path>) -> Result<()> {
+    let work_dir = std::env::temp_dir().join(format!(
+        "fcos-ignition-coder-fuzz-{}-{}",
+        std::process::id(),
+        seed
+    ));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)?;
+    }
+    fs::create_dir_all(&work_dir)?;
+
+    let result = (|| {
+        let input_path = work_dir.join("input.ign");
+        let first_dir = work_dir.join("first");
+        let assembled_path = work_dir.join("assembled.ign");
+        let second_dir = work_dir.join("second");
+
+        fs::write(&input_path, serde_json::to_string_pretty(config)?)?;
+        disassemble_ignition(
+            &input_path,
+            &first_dir,
+            &DisassembleOptions::default(),
+            None,
+        )?;
+        assemble_ignition(
+            &assembled_path,
+            &first_dir,
+            &AssembleOptions::default(),
+            None,
+        )?;
+        disassemble_ignition(
+            &assembled_path,
+            &second_dir,
+            &DisassembleOptions::default(),
+            None,
+        )?;
+
+        compare_trees(&first_dir, &second_dir, diff_output)
+    })();
+
+    fs::remove_dir_all(&work_dir)?;
+    result
+}
+
+/// Require two decoded trees to hold the same files with the same content.
+/// On a mismatch, `diff_output` receives a unified diff of every differing
+/// file, a file missing from one tree counting as empty.
+pub fn compare_trees(first: &Path, second: &Path, diff_output: Option<&Path>) -> Result<()> {
+    let first_files = list_files(first, first)?;
+    let second_files = list_files(second, second)?;
+
+    let mut all_files: Vec<&PathBuf> = first_files.iter().chain(&second_files).collect();
+    all_files.sort();
+    all_files.dedup();
+
+    let mut changed = Vec::new();
+    let mut diff = String::new();
+    for relative in all_files {
+        let before = read_if_present(&first.join(relative))?;
+        let after = read_if_present(&second.join(relative))?;
+        if before == after {
+            continue;
+        }
+        changed.push(relative.display().to_string());
+        let (old_label, new_label) = (
+            format!("first/{}", relative.display()),
+            format!("second/{}", relative.display()),
+        );
+        match (std::str::from_utf8(&before), std::str::from_utf8(&after)) {
+            (Ok(old), Ok(new)) => diff.push_str(&unified_diff(&old_label, &new_label, old, new)),
+            _ => diff.push_str(&format!(
+                "Binary files {} and {} differ\n",
+                old_label, new_label
+            )),
+        }
+    }
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(path) = diff_output {
+        fs::write(path, diff)
+            .with_context(|| format!("Failed to write round trip diff: {}", path.display()))?;
+    }
+    if first_files != second_files {
+        anyhow::bail!(
+            "Decoded trees differ in files: {:?} vs {:?}",
+            first_files,
+            second_files
+        );
+    }
+    anyhow::bail!("Content of {} changed", changed.join(", "))
+}
+
+fn read_if_present(path: &Path) -> Result<Vec<u8>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn list_files(root: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(list_files(root, &path)?);
+        } else {
+            files.push(path.strip_prefix(root)?.to_path_buf());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the attributes file written at the root of the decoded directory
+pub const ATTRIBUTES_FILE: &str = ".gitattributes";
+
+/// Media types outside `text/*` whose content is text
+const TEXT_MEDIA_TYPES: &[&str] = &[
+    "application/json",
+    "application/yaml",
+    "application/x-yaml",
+    "application/xml",
+    "application/javascript",
+    "application/toml",
+    "application/x-sh",
+];
+
+/// Whether content of `media_type` should be treated as text by git
+pub fn is_text(media_type: &str) -> bool {
+    let essence = media_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || TEXT_MEDIA_TYPES.contains(&essence.as_str())
+}
+
+/// Write `.gitattributes` at the root of the decoded directory with a `text`
+/// or `binary` line for every extracted file
+pub fn write(output_dir: &Path, extracted: &[(String, String)]) -> Result<PathBuf> {
+    let mut content =
+        String::from("# Generated by fcos-ignition-coder from extracted media types\n");
+    for (path, media_type) in extracted {
+        let attribute = if is_text(media_type) {
+            "text"
+        } else {
+            "binary"
+        };
+        content.push_str(&format!("/{} {}\n", escape_pattern(path), attribute));
+    }
+
+    let path = output_dir.join(ATTRIBUTES_FILE);
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Escape characters gitattributes patterns treat specially
+fn escape_pattern(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' => escaped.push_str("[[:space:]]"),
+            '*' | '?' | '[' | '\\' | '!' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
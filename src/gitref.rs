@@ -0,0 +1,27 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Read `relative` (a path below `dir`) as committed at `rev` in the git
+/// repository containing `dir`, ignoring the working tree
+pub fn read_file(dir: &Path, rev: &str, relative: &str) -> Result<Vec<u8>> {
+    // `<rev>:./<path>` resolves the path against the -C directory rather
+    // than the repository root
+    let object = format!("{}:./{}", rev, relative);
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["show", "--no-textconv", &object])
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to read {} at git ref {}: {}",
+            relative,
+            rev,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,26 @@
+use anyhow::{Context, Result};
+use std::time::Duration;
+
+/// How long a fetch may take in all, from connecting to the last byte
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Fetch `url`, failing on connection errors, non-success statuses, bodies
+/// over `max_size` bytes and fetches taking longer than [`FETCH_TIMEOUT`]
+pub fn fetch(url: &str, max_size: Option<u64>) -> Result<Vec<u8>> {
+    let agent = ureq::Agent::config_builder()
+        .timeout_global(Some(FETCH_TIMEOUT))
+        .build()
+        .new_agent();
+    let mut response = agent
+        .get(url)
+        .call()
+        .with_context(|| format!("Failed to fetch {}", url))?;
+    // ureq refuses a body that reaches its limit, so allow one byte more to
+    // accept a body of exactly `max_size`
+    response
+        .body_mut()
+        .with_config()
+        .limit(max_size.map_or(u64::MAX, |max| max.saturating_add(1)))
+        .read_to_vec()
+        .with_context(|| format!("Failed to read response from {}", url))
+}
@@ -0,0 +1,98 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::sbom::{self, SourceKind};
+
+/// Summary of a config as `info` prints it
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ConfigInfo {
+    pub version: String,
+    pub files: usize,
+    pub links: usize,
+    pub directories: usize,
+    pub units: usize,
+    pub users: usize,
+    pub groups: usize,
+    /// Size of all inline content after decoding and decompression
+    pub decoded_bytes: usize,
+    /// Number of inline sources per compression, `none` for uncompressed ones
+    pub compression: BTreeMap<String, usize>,
+    pub remote_sources: usize,
+    pub has_remote_sources: bool,
+}
+
+impl fmt::Display for ConfigInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let compression = self
+            .compression
+            .iter()
+            .map(|(kind, count)| format!("{}: {}", kind, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let compression = if compression.is_empty() {
+            "-".to_string()
+        } else {
+            compression
+        };
+        let rows = [
+            ("Spec version", self.version.clone()),
+            ("Files", self.files.to_string()),
+            ("Links", self.links.to_string()),
+            ("Directories", self.directories.to_string()),
+            ("Units", self.units.to_string()),
+            ("Users", self.users.to_string()),
+            ("Groups", self.groups.to_string()),
+            ("Decoded size", format!("{} bytes", self.decoded_bytes)),
+            ("Compression", compression),
+            ("Remote sources", self.remote_sources.to_string()),
+        ];
+        for (label, value) in rows {
+            writeln!(f, "{:<16}{}", label, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Summarize `config` from a read-only walk of its sources
+pub fn collect(config: &Value) -> Result<ConfigInfo> {
+    let count = |pointer: &str| {
+        config
+            .pointer(pointer)
+            .and_then(|v| v.as_array())
+            .map_or(0, Vec::len)
+    };
+
+    let mut decoded_bytes = 0;
+    let mut compression = BTreeMap::new();
+    let mut remote_sources = 0;
+    for entry in sbom::collect(config, false)? {
+        match entry.kind {
+            SourceKind::Inline => {
+                decoded_bytes += entry.size.unwrap_or_default();
+                let kind = entry.compression.unwrap_or_else(|| "none".to_string());
+                *compression.entry(kind).or_default() += 1;
+            }
+            SourceKind::Remote => remote_sources += 1,
+        }
+    }
+
+    Ok(ConfigInfo {
+        version: config
+            .pointer("/ignition/version")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        files: count("/storage/files"),
+        links: count("/storage/links"),
+        directories: count("/storage/directories"),
+        units: count("/systemd/units"),
+        users: count("/passwd/users"),
+        groups: count("/passwd/groups"),
+        decoded_bytes,
+        compression,
+        remote_sources,
+        has_remote_sources: remote_sources > 0,
+    })
+}
@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// Indentation of pretty-printed JSON output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+    /// This many spaces per level
+    Spaces(usize),
+    /// One tab per level
+    Tab,
+}
+
+impl Default for Indent {
+    /// Two spaces, as `serde_json::to_string_pretty` writes
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+impl FromStr for Indent {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        if value.eq_ignore_ascii_case("tab") {
+            return Ok(Indent::Tab);
+        }
+        let spaces = value
+            .parse()
+            .with_context(|| format!("Invalid indent '{}': expected a number or 'tab'", value))?;
+        Ok(Indent::Spaces(spaces))
+    }
+}
+
+impl fmt::Display for Indent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Indent::Spaces(spaces) => write!(f, "{}", spaces),
+            Indent::Tab => f.write_str("tab"),
+        }
+    }
+}
+
+/// Top-level sections in the order of the Ignition spec, which is also the
+/// order Butane and the upstream examples write them in
+const TOP_LEVEL_ORDER: &[&str] = &[
+    "ignition",
+    "kernelArguments",
+    "passwd",
+    "storage",
+    "systemd",
+];
+
+/// Keys that lead any nested object: the spec version and the key naming
+/// an entry
+const LEADING_KEYS: &[&str] = &["version", "path", "name"];
+
+/// Serialize `value` as single-line JSON when `compact` is set, otherwise
+/// pretty-printed with `indent`. With `canonical_order`, keys follow
+/// Ignition's order instead of the alphabetical one (see [`Canonical`]).
+pub fn to_json_string(
+    value: &Value,
+    compact: bool,
+    indent: Indent,
+    canonical_order: bool,
+) -> Result<String> {
+    let canonical = Canonical { value, root: true };
+    if compact {
+        return Ok(if canonical_order {
+            serde_json::to_string(&canonical)?
+        } else {
+            serde_json::to_string(value)?
+        });
+    }
+
+    let indent = match indent {
+        Indent::Spaces(spaces) => " ".repeat(spaces),
+        Indent::Tab => "\t".to_string(),
+    };
+    let mut output = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut output, formatter);
+    if canonical_order {
+        canonical.serialize(&mut serializer)?;
+    } else {
+        value.serialize(&mut serializer)?;
+    }
+    Ok(String::from_utf8(output)?)
+}
+
+/// A value serialized with its top-level sections in spec order and the
+/// [`LEADING_KEYS`] first in nested objects; other keys stay alphabetical
+struct Canonical<'a> {
+    value: &'a Value,
+    root: bool,
+}
+
+impl Serialize for Canonical<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self.value {
+            Value::Object(map) => {
+                let order = if self.root {
+                    TOP_LEVEL_ORDER
+                } else {
+                    LEADING_KEYS
+                };
+                let rank = |key: &str| order.iter().position(|k| *k == key).unwrap_or(order.len());
+                let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+                // Stable, so keys of equal rank keep their alphabetical order
+                entries.sort_by_key(|(key, _)| rank(key));
+
+                let mut object = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    object.serialize_entry(key, &Canonical { value, root: false })?;
+                }
+                object.end()
+            }
+            Value::Array(items) => {
+                let mut array = serializer.serialize_seq(Some(items.len()))?;
+                for value in items {
+                    array.serialize_element(&Canonical { value, root: false })?;
+                }
+                array.end()
+            }
+            other => other.serialize(serializer),
+        }
+    }
+}
@@ -0,0 +1,3546 @@
+//! Disassemble Fedora CoreOS Ignition configs into a directory of plain
+//! files and assemble such directories back into configs.
+//!
+//! [`disassemble`] and [`disassemble_ignition`] extract embedded files,
+//! [`assemble_ignition`] embeds them again.
+
+use anyhow::{Context, Result};
+use ignition_config::Config;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod checks;
+mod diff;
+mod digest;
+mod dockerfile;
+mod envfile;
+mod fuzz;
+mod gitattributes;
+mod gitref;
+#[cfg(feature = "http")]
+mod http;
+mod info;
+mod json_format;
+mod links;
+mod listing;
+mod manifest;
+mod merge;
+mod metadata;
+mod modes;
+mod path_filter;
+mod policy;
+mod progress;
+mod references;
+mod remotes;
+mod reproducible;
+mod rootfs;
+mod sbom;
+mod ssh_keys;
+mod streaming;
+mod transform;
+mod tree;
+mod unified_diff;
+mod units;
+mod validate;
+mod verbosity;
+mod verification;
+mod version_range;
+mod warnings;
+mod xattrs;
+
+pub use digest::ContentDigest;
+pub use fuzz::run_fuzz;
+pub use json_format::Indent;
+use manifest::Manifest;
+pub use path_filter::PathGlob;
+pub use policy::OwnershipPolicy;
+pub use progress::{FileEvent, PrintProgress, ProgressSink};
+pub use remotes::RemoteRewrite;
+use streaming::DeferredSource;
+use transform::Direction;
+use verbosity::warn;
+pub use verbosity::{set_verbosity, status, verbosity, Verbosity};
+pub use version_range::VersionRange;
+pub use warnings::WarningKind;
+use warnings::{classify_warnings, report_warnings};
+
+#[cfg(test)]
+mod tests;
+
+/// Upload manifest written next to the `--split-large` copies
+const UPLOAD_MANIFEST_FILE: &str = "upload-manifest.json";
+
+/// Top-level key of decoded.ign recording sections `--keep-going` left unextracted
+const SKIPPED_SECTIONS_KEY: &str = "_skipped_sections";
+
+/// Name of the decoded config disassembly writes unless `--ign-name` says
+/// otherwise
+pub const DECODED_IGN: &str = "decoded.ign";
+
+/// Action to take when the target already exists
+#[derive(clap::ValueEnum, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Action {
+    /// Create new target (fail if it already exists) - default
+    #[default]
+    New,
+    /// Add to or update existing target (merge/overwrite files)
+    Add,
+    /// Replace entire target (remove existing first)
+    Replace,
+    /// Disassembly only: add to an existing target and merge the new config
+    /// into its decoded.ign, entries with the same `path` or `name` merging
+    /// field by field with the new config winning
+    Merge,
+}
+
+/// What to do when an extracted file already exists in the target directory
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverwriteMode {
+    /// Keep the existing file
+    Skip,
+    /// Replace the existing file - default
+    #[default]
+    Overwrite,
+    /// Abort on the first conflicting file
+    Error,
+}
+
+/// Whether assemble reads content files through symlinks
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Fail on symlinked content files - default
+    #[default]
+    Deny,
+    /// Read the file the symlink points to
+    Allow,
+}
+
+/// Line ending handling for text files embedded by assemble
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NewlineMode {
+    /// Embed content exactly as read - default
+    #[default]
+    Preserve,
+    /// Convert CRLF line endings to LF
+    Lf,
+}
+
+/// Order of the `storage.files` entries in an assembled config
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AssembleOrder {
+    /// Original config order recorded in the manifest at disassembly
+    Manifest,
+    /// Sorted by path
+    Sorted,
+    /// Current order of the decoded config - default
+    #[default]
+    Source,
+}
+
+/// Options controlling how an Ignition file is disassembled
+#[derive(Clone, Debug, Default)]
+pub struct DisassembleOptions {
+    /// Serialize decoded.ign in a compact format
+    pub compact: bool,
+    /// Indentation of pretty-printed decoded.ign
+    pub indent: Indent,
+    /// Write decoded.ign keys in Ignition's order instead of alphabetically
+    pub canonical_order: bool,
+    /// Action to take with the target directory
+    pub action: Action,
+    /// How to handle extracted files that already exist
+    pub overwrite_mode: OverwriteMode,
+    /// Warning kinds that abort the run instead of only being reported
+    pub abort_on_warning: Vec<WarningKind>,
+    /// Materialize a filesystem image instead of a decoded tree
+    pub rootfs: bool,
+    /// External program each extracted file's content is piped through
+    pub transform_cmd: Option<PathBuf>,
+    /// Fail when a path is claimed by more than one storage entry
+    pub verify_paths_unique: bool,
+    /// Fail when a file or directory mode is outside `0..=0o7777`
+    pub validate_modes: bool,
+    /// Fail on top-level keys the config's spec version doesn't define
+    pub reject_unknown_sections: bool,
+    /// Fail when an inline source does not match its verification hash
+    pub decode_verification_check: bool,
+    /// Fail on any parse warning and on verification hash mismatches
+    /// instead of only reporting them
+    pub strict: bool,
+    /// Keep extracting other top-level sections when one of them fails
+    pub keep_going: bool,
+    /// Enable every determinism-related behavior (see `--reproducible`)
+    pub reproducible: bool,
+    /// Refuse input configs larger than this many bytes
+    pub max_input_size: Option<u64>,
+    /// Decode base64/gzip systemd unit contents into plain text
+    pub decode_unit_contents: bool,
+    /// Write systemd unit and dropin contents to files under `units/`
+    pub extract_units: bool,
+    /// Group extracted units into `units/<target>.wants/` and the like by
+    /// their `[Install]` section
+    pub split_units_dir: bool,
+    /// Write each user's SSH authorized keys to `passwd/<user>/authorized_keys`
+    pub extract_ssh_keys: bool,
+    /// Materialize `storage.links` as symlinks in the decoded tree
+    pub extract_links: bool,
+    /// Collapse warnings repeated at least this many times into one line
+    pub quiet_warnings: Option<usize>,
+    /// The input is a base64 (optionally gzipped) encoding of the config
+    pub from_base64_file: bool,
+    /// Write a Containerfile `COPY` snippet for the extracted files here
+    pub emit_dockerfile: Option<PathBuf>,
+    /// Write a `.gitattributes` marking extracted files as text or binary
+    pub emit_gitattributes: bool,
+    /// Write summary statistics as a dotenv file here
+    pub emit_env_file: Option<PathBuf>,
+    /// Write an SBOM of every source's path, size, digest and origin here
+    pub emit_sbom: Option<PathBuf>,
+    /// Write the layout of the output directory as nested JSON here
+    pub output_tree_json: Option<PathBuf>,
+    /// Store extracted files content-addressed, named by this digest
+    pub digest: Option<ContentDigest>,
+    /// Store contents shared by several sources once, as a SHA-256 blob
+    pub dedup: bool,
+    /// Decode base64 sources to disk in chunks instead of in memory
+    pub chunked_read: bool,
+    /// Decode and write extracted files on a thread pool
+    pub parallel: bool,
+    /// Skip configs whose spec version falls outside this range
+    pub version_range: Option<VersionRange>,
+    /// Fail unless the config has exactly this spec version
+    pub expected_version: Option<String>,
+    /// Mode and ownership defaults for entries that don't specify them
+    pub ownership_policy: Option<OwnershipPolicy>,
+    /// Base URL rewrites applied to remote sources
+    pub relativize_remote: Vec<RemoteRewrite>,
+    /// Only extract sources whose path matches one of these globs
+    pub include: Vec<PathGlob>,
+    /// Leave sources whose path matches one of these globs inline, even
+    /// when an include matches too
+    pub exclude: Vec<PathGlob>,
+    /// Fail unless exactly this many files are processed
+    pub assert_file_count: Option<usize>,
+    /// Write decoded.ign and the manifest but no content files
+    pub config_only: bool,
+    /// Only treat arrays whose entries all have a source as source lists
+    pub strict_array_detection: bool,
+    /// Tag extracted files with their media type and ignition path as xattrs
+    pub set_xattrs: bool,
+    /// Give extracted files the permission bits their entry's `mode` declares
+    pub apply_mode: bool,
+    /// Configs merged over the input, in order, before disassembly
+    pub input_multiple: Vec<PathBuf>,
+    /// Keep existing manifest and metadata entries, only adding new ones
+    pub no_clobber_manifest: bool,
+    /// File name of the decoded config, [`DECODED_IGN`] when unset
+    pub ign_name: Option<String>,
+    /// Write file modes, ownership, overwrite and compression to metadata.json
+    pub metadata: bool,
+    /// Report the files that would be written without changing anything
+    pub dry_run: bool,
+}
+
+/// Options controlling how an Ignition file is assembled
+#[derive(Clone, Debug, Default)]
+pub struct AssembleOptions {
+    /// Serialize the output in a compact format
+    pub compact: bool,
+    /// Indentation of pretty-printed output
+    pub indent: Indent,
+    /// Write keys in Ignition's order instead of alphabetically
+    pub canonical_order: bool,
+    /// Suppress fields that have default values
+    pub default: bool,
+    /// Fields `default` keeps even at their default value
+    pub keep_keys: Vec<String>,
+    /// Action to take with the target file
+    pub action: Action,
+    /// Warning kinds that abort the run instead of only being reported
+    pub abort_on_warning: Vec<WarningKind>,
+    /// Restore fields the typed parse dropped from the decoded config
+    pub preserve_unknown: bool,
+    /// Move large sources out of the config to a remote location
+    pub split_large: Option<SplitLarge>,
+    /// External program each embedded file's content is piped through
+    pub transform_cmd: Option<PathBuf>,
+    /// Enable every determinism-related behavior (see `--reproducible`)
+    pub reproducible: bool,
+    /// Refuse decoded configs, and sources fetched for `inline_remote`,
+    /// larger than this many bytes
+    pub max_input_size: Option<u64>,
+    /// Order of the `storage.files` entries in the output
+    pub order: AssembleOrder,
+    /// Collapse warnings repeated at least this many times into one line
+    pub quiet_warnings: Option<usize>,
+    /// Skip configs whose spec version falls outside this range
+    pub version_range: Option<VersionRange>,
+    /// Mode and ownership defaults for entries that don't specify them
+    pub ownership_policy: Option<OwnershipPolicy>,
+    /// Base URL rewrites applied to remote sources
+    pub relativize_remote: Vec<RemoteRewrite>,
+    /// Fail unless exactly this many files are processed
+    pub assert_file_count: Option<usize>,
+    /// Only print the decoded config that would be used
+    pub print_config_path: bool,
+    /// Decoded config to prefer over scanning for `*.ign`, [`DECODED_IGN`]
+    /// when unset
+    pub ign_name: Option<String>,
+    /// Drop entries whose content file is missing instead of failing
+    pub allow_missing_files: bool,
+    /// Whether content files may be read through symlinks
+    pub follow_symlinks: SymlinkPolicy,
+    /// Line ending normalization for text content files
+    pub embed_newline: NewlineMode,
+    /// Read content files as committed at this git ref
+    pub content_from_git: Option<String>,
+    /// Write the config to stdout instead of the target file
+    pub stdout: bool,
+    /// End the output with a newline
+    pub newline: bool,
+    /// Check that gzipped content survives re-compression and warn when the
+    /// compressed bytes differ from the original
+    pub verify_gzip_roundtrip: bool,
+    /// Warn about files of the decoded directory the manifest doesn't list
+    pub validate_manifest: bool,
+    /// Fail on any parse warning instead of only reporting it
+    pub strict: bool,
+    /// Fetch `http(s)://` sources and embed them as `data:` URLs
+    pub inline_remote: bool,
+    /// Re-parse the assembled config and refuse to write it if invalid
+    pub validate: bool,
+    /// Encode content files into the output in chunks instead of in memory
+    pub chunked_write: bool,
+    /// Give entries without a `mode` the permission bits of their content file
+    pub mode_from_file: bool,
+    /// Embed every content file in the base64url alphabet instead of
+    /// keeping the alphabet its source used
+    pub base64url: bool,
+    /// Report the files that would be written without changing anything
+    pub dry_run: bool,
+}
+
+/// Sources larger than `threshold` bytes are served from `base_url` instead of
+/// being inlined
+#[derive(Clone, Debug)]
+pub struct SplitLarge {
+    pub threshold: usize,
+    pub base_url: String,
+}
+
+/// A config as `assemble_v3_config` embedded it, before it is written
+struct Assembled {
+    json: String,
+    file_count: usize,
+    externalized: Vec<ExternalizedSource>,
+    /// Content files streamed into `json` when it is written
+    deferred: Vec<DeferredSource>,
+}
+
+/// A source moved out of the config by `--split-large`
+#[derive(Debug, serde::Serialize)]
+struct ExternalizedSource {
+    /// Path of the content file relative to the decoded directory
+    path: String,
+    /// URL the config now references for this content
+    url: String,
+    /// Size of the content in bytes
+    size: usize,
+}
+
+/// Outcome of a disassembly or assembly, for the caller to print
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Number of embedded files extracted or embedded
+    pub file_count: usize,
+    /// Content files moved out of the config: the decoded tree's files on
+    /// disassembly (including those a dry run would write; empty for
+    /// `--rootfs`), the `--split-large` copies on assembly
+    pub extracted_paths: Vec<PathBuf>,
+    /// Every file written, content and sidecars alike; empty for dry runs
+    pub written_paths: Vec<PathBuf>,
+    /// Sources the config fetches at boot, listed in `references.json` on
+    /// disassembly
+    pub remote_count: usize,
+    /// Warnings reported while parsing the config
+    pub warnings: Vec<String>,
+    /// Inline sources whose content doesn't match their verification hash
+    pub hash_mismatches: Vec<String>,
+    /// Why the config was skipped without writing anything
+    pub skipped: Option<String>,
+}
+
+/// Disassemble the config text `config` into `out`
+pub fn disassemble(config: &str, out: &Path, opts: DisassembleOptions) -> Result<Report> {
+    disassemble_content(config, Path::new("<config>"), out, &opts, None)
+}
+
+/// Disassemble the config read from `input_path` (`-` for stdin) into
+/// `output_dir`
+pub fn disassemble_ignition(
+    input_path: &Path,
+    output_dir: &Path,
+    options: &DisassembleOptions,
+    progress: Option<&mut dyn ProgressSink>,
+) -> Result<Report> {
+    let content = read_input(input_path, options)?;
+    disassemble_content(&content, input_path, output_dir, options, progress)
+}
+
+/// Disassemble every config listed in `list_path` (one path per line, `-` for
+/// stdin) into `<config>.decoded` next to it, returning each output directory
+/// with its report
+pub fn disassemble_input_list(
+    list_path: &Path,
+    options: &DisassembleOptions,
+    mut progress: Option<&mut dyn ProgressSink>,
+) -> Result<Vec<(PathBuf, Report)>> {
+    let list = read_config(list_path, None)
+        .with_context(|| format!("Failed to read input list: {}", list_path.display()))?;
+
+    let mut reports = Vec::new();
+    for line in list.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let input_path = Path::new(line);
+        if is_stdin(input_path) {
+            anyhow::bail!("The input list can't name stdin");
+        }
+        let output_dir = input_path.with_extension("decoded");
+        // Reborrow with a shorter trait object lifetime for each config
+        let progress = progress.as_mut().map(|p| &mut **p as &mut dyn ProgressSink);
+        let report = disassemble_ignition(input_path, &output_dir, options, progress)
+            .with_context(|| format!("Failed to disassemble {}", input_path.display()))?;
+        reports.push((output_dir, report));
+    }
+    Ok(reports)
+}
+
+/// Disassemble config text; `input_path` names it in messages and provenance
+fn disassemble_content(
+    content: &str,
+    input_path: &Path,
+    output_dir: &Path,
+    options: &DisassembleOptions,
+    progress: Option<&mut dyn ProgressSink>,
+) -> Result<Report> {
+    // Status goes to stderr when the config is piped in
+    let to_stderr = is_stdin(input_path);
+    let decoded_path = decoded_ign_path(output_dir, options.ign_name.as_deref())?;
+
+    let emits = options.emit_dockerfile.is_some()
+        || options.emit_gitattributes
+        || options.emit_env_file.is_some()
+        || options.emit_sbom.is_some();
+    if options.rootfs && emits {
+        anyhow::bail!("--emit-* options describe a decoded tree and don't apply to --rootfs");
+    }
+
+    let (content, provenance) = if options.input_multiple.is_empty() {
+        (content.to_string(), BTreeMap::new())
+    } else {
+        merge_inputs(input_path, content, options)?
+    };
+
+    if let Some(reason) = version_skip(&content, options.version_range.as_ref(), input_path)? {
+        return Ok(Report {
+            skipped: Some(reason),
+            ..Default::default()
+        });
+    }
+
+    if let Some(expected) = &options.expected_version {
+        let version = version_range::config_version(&content)?;
+        if !version_range::same_version(&version, expected)? {
+            anyhow::bail!(
+                "Config spec version {} does not match expected version {}",
+                version,
+                expected
+            );
+        }
+    }
+
+    // The typed parse drops unknown keys, so check the raw JSON
+    if options.reject_unknown_sections {
+        let raw: serde_json::Value =
+            serde_json::from_str(&content).with_context(|| "Failed to parse Ignition file")?;
+        checks::verify_known_sections(&raw)?;
+    }
+
+    // Parse the Ignition config using ignition-config crate
+    let (mut config, warnings) =
+        Config::parse_str(&content).with_context(|| "Failed to parse Ignition file")?;
+
+    // Print warnings if any, aborting on the selected kinds
+    let warnings = classify_warnings(warnings);
+    let abort_on = abort_on_kinds(&options.abort_on_warning, options.strict);
+    report_warnings(&warnings, abort_on, options.quiet_warnings)?;
+    let warning_lines: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+
+    if options.verify_paths_unique {
+        let json_value =
+            serde_json::to_value(&config).with_context(|| "Failed to serialize config")?;
+        checks::verify_paths_unique(&json_value)?;
+    }
+
+    if options.validate_modes {
+        let json_value =
+            serde_json::to_value(&config).with_context(|| "Failed to serialize config")?;
+        checks::verify_modes(&json_value)?;
+    }
+
+    // Stale or tampered hashes are collected for the summary unless they fail the run
+    let json_value = serde_json::to_value(&config).with_context(|| "Failed to serialize config")?;
+    if options.decode_verification_check || options.strict {
+        verification::verify_inline_hashes(&json_value)?;
+    }
+    let hash_mismatches = verification::inline_hash_mismatches(&json_value);
+
+    if output_dir.exists() && !output_dir.is_dir() {
+        anyhow::bail!(
+            "Target exists and is a file, not a directory: {}",
+            output_dir.display()
+        );
+    }
+
+    // Handle target directory based on action
+    if output_dir.exists() {
+        match options.action {
+            Action::New => {
+                anyhow::bail!(
+                    "Target directory already exists: {}. Use --action replace to overwrite, --action add to add files or --action merge to merge decoded.ign.",
+                    output_dir.display()
+                );
+            }
+            Action::Add | Action::Merge => {
+                // Directory exists, we'll add/overwrite files within it
+                let verb = match (&options.action, options.dry_run) {
+                    (Action::Merge, true) => "Would merge into",
+                    (Action::Merge, false) => "Merging into",
+                    (_, true) => "Would add to",
+                    (_, false) => "Adding to",
+                };
+                status(
+                    to_stderr,
+                    &format!("{} existing directory: {}", verb, output_dir.display()),
+                );
+            }
+            Action::Replace if options.dry_run => {
+                status(
+                    to_stderr,
+                    &format!(
+                        "Would remove and replace existing directory: {}",
+                        output_dir.display()
+                    ),
+                );
+            }
+            Action::Replace => {
+                fs::remove_dir_all(output_dir).with_context(|| {
+                    format!(
+                        "Failed to remove existing target directory: {}",
+                        output_dir.display()
+                    )
+                })?;
+                status(
+                    to_stderr,
+                    &format!("Replaced existing directory: {}", output_dir.display()),
+                );
+            }
+        }
+    } else {
+        // Directory doesn't exist, all actions will create it
+        status(
+            to_stderr,
+            &format!(
+                "{} new directory: {}",
+                if options.dry_run {
+                    "Would create"
+                } else {
+                    "Creating"
+                },
+                output_dir.display()
+            ),
+        );
+    }
+
+    // Create output directory
+    if !options.dry_run {
+        fs::create_dir_all(output_dir).with_context(|| {
+            format!(
+                "Failed to create output directory: {}",
+                output_dir.display()
+            )
+        })?;
+    }
+
+    if options.rootfs {
+        let mut json_value =
+            serde_json::to_value(&config).with_context(|| "Failed to serialize config")?;
+        if let Some(policy) = &options.ownership_policy {
+            policy.apply(&mut json_value);
+        }
+        if options.dry_run {
+            for path in rootfs::plan_rootfs(&json_value, output_dir)? {
+                report_dry_write(&path, options.action == Action::Replace, to_stderr);
+            }
+            return Ok(Report {
+                warnings: warning_lines,
+                hash_mismatches,
+                ..Default::default()
+            });
+        }
+        let file_counter = rootfs::materialize_rootfs(&json_value, output_dir)?;
+        check_file_count(file_counter, options.assert_file_count)?;
+        let mut written_paths = Vec::new();
+        if let Some(tree_path) = &options.output_tree_json {
+            tree::write_tree_json(output_dir, tree_path)?;
+            written_paths.push(tree_path.clone());
+        }
+        return Ok(Report {
+            file_count: file_counter,
+            written_paths,
+            warnings: warning_lines,
+            hash_mismatches,
+            ..Default::default()
+        });
+    }
+
+    // Work with the config based on version
+    let extraction = match &mut config {
+        Config::V3_0(cfg) => disassemble_v3_config(cfg, output_dir, options, to_stderr, progress)?,
+        Config::V3_1(cfg) => disassemble_v3_config(cfg, output_dir, options, to_stderr, progress)?,
+        Config::V3_2(cfg) => disassemble_v3_config(cfg, output_dir, options, to_stderr, progress)?,
+        Config::V3_3(cfg) => disassemble_v3_config(cfg, output_dir, options, to_stderr, progress)?,
+        Config::V3_4(cfg) => disassemble_v3_config(cfg, output_dir, options, to_stderr, progress)?,
+        Config::V3_5(cfg) => disassemble_v3_config(cfg, output_dir, options, to_stderr, progress)?,
+        _ => anyhow::bail!("Unsupported Ignition config version"),
+    };
+    let file_counter = extraction.file_counter;
+    let mut written_paths = extraction.written_paths;
+    check_file_count(file_counter, options.assert_file_count)?;
+    let mut report = Report {
+        file_count: file_counter,
+        extracted_paths: extraction.extracted_paths,
+        remote_count: extraction.remote_count,
+        warnings: warning_lines,
+        hash_mismatches,
+        ..Default::default()
+    };
+
+    if options.dry_run {
+        report_dry_write(&decoded_path, options.action == Action::Replace, to_stderr);
+        for path in [&options.emit_env_file, &options.output_tree_json]
+            .into_iter()
+            .flatten()
+        {
+            report_dry_write(path, false, to_stderr);
+        }
+        return Ok(report);
+    }
+
+    if !provenance.is_empty() {
+        let mut manifest = Manifest::read(output_dir)?.unwrap_or_default();
+        manifest.provenance = provenance;
+        if options.no_clobber_manifest {
+            manifest.write_preserving(output_dir)?;
+        } else {
+            manifest.write(output_dir)?;
+        }
+    }
+
+    // Write the modified Ignition file
+    let decoded_size = extraction.decoded_json.len();
+    fs::write(&decoded_path, extraction.decoded_json).with_context(|| {
+        format!(
+            "Failed to write {}: {}",
+            decoded_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            decoded_path.display()
+        )
+    })?;
+    verbosity::detail(
+        to_stderr,
+        &format!(
+            "Wrote {} ({} bytes, application/json)",
+            decoded_path.display(),
+            decoded_size
+        ),
+    );
+    written_paths.push(decoded_path.clone());
+
+    if let Some(env_path) = &options.emit_env_file {
+        let version = version_range::config_version(&content)?;
+        envfile::write(
+            env_path,
+            &[
+                ("IGNITION_VERSION", version),
+                ("EXTRACTED_FILE_COUNT", file_counter.to_string()),
+                ("WARNING_COUNT", warnings.len().to_string()),
+                ("TOTAL_BYTES", extraction.total_bytes.to_string()),
+            ],
+        )?;
+    }
+
+    if options.reproducible {
+        reproducible::apply_fixed_mtimes(output_dir, &written_paths)?;
+    }
+
+    // Files outside the decoded tree keep their real mtimes
+    for path in [
+        &options.emit_env_file,
+        &options.emit_dockerfile,
+        &options.emit_sbom,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        written_paths.push(path.clone());
+    }
+
+    if let Some(tree_path) = &options.output_tree_json {
+        tree::write_tree_json(output_dir, tree_path)?;
+        written_paths.push(tree_path.clone());
+    }
+
+    report.written_paths = written_paths;
+    Ok(report)
+}
+
+/// Parse a config and report its version, warnings and contents. Parse
+/// errors and out-of-range modes fail; warnings are only printed.
+pub fn validate_ignition(input_path: &Path, reject_unknown_sections: bool) -> Result<()> {
+    let content = read_config(input_path, None)
+        .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
+
+    if reject_unknown_sections {
+        let raw: serde_json::Value =
+            serde_json::from_str(&content).with_context(|| "Failed to parse Ignition file")?;
+        checks::verify_known_sections(&raw)?;
+    }
+
+    let (config, warnings) =
+        Config::parse_str(&content).with_context(|| "Failed to parse Ignition file")?;
+    let warnings = classify_warnings(warnings);
+    report_warnings(&warnings, &[], None)?;
+
+    let json_value = serde_json::to_value(&config).with_context(|| "Failed to serialize config")?;
+    checks::verify_modes(&json_value)?;
+
+    let count = |pointer: &str| {
+        json_value
+            .pointer(pointer)
+            .and_then(|v| v.as_array())
+            .map_or(0, Vec::len)
+    };
+    println!(
+        "{} is a valid Ignition {} config",
+        input_path.display(),
+        version_range::config_version(&content)?
+    );
+    println!(
+        "{} file(s), {} systemd unit(s), {} user(s), {} warning(s)",
+        count("/storage/files"),
+        count("/systemd/units"),
+        count("/passwd/users"),
+        warnings.len()
+    );
+
+    Ok(())
+}
+
+/// Print the path, media type, decoded size and compression of every inline
+/// source, as disassembly would find them
+pub fn list_embedded(input_path: &Path) -> Result<()> {
+    let content = read_config(input_path, None)
+        .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
+
+    let (config, warnings) =
+        Config::parse_str(&content).with_context(|| "Failed to parse Ignition file")?;
+    report_warnings(&classify_warnings(warnings), &[], None)?;
+
+    let json_value = serde_json::to_value(&config).with_context(|| "Failed to serialize config")?;
+    for source in listing::collect(&json_value)? {
+        println!("{}", source);
+    }
+
+    Ok(())
+}
+
+/// Print every remote source of the config at `input_path`, as JSON with `json`
+pub fn list_remotes(input_path: &Path, json: bool) -> Result<()> {
+    let content = read_config(input_path, None)
+        .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
+
+    let (config, warnings) =
+        Config::parse_str(&content).with_context(|| "Failed to parse Ignition file")?;
+    report_warnings(&classify_warnings(warnings), &[], None)?;
+
+    let json_value = serde_json::to_value(&config).with_context(|| "Failed to serialize config")?;
+    let remotes = remotes::collect_remotes(&json_value);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&remotes)?);
+    } else {
+        for remote in &remotes {
+            println!("{}  ({})", remote.url, remote.sections.join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a summary of an Ignition file: its spec version, entry counts,
+/// decoded size, compression and remote sources. Nothing is written.
+pub fn show_info(input_path: &Path, json: bool) -> Result<()> {
+    let content = read_config(input_path, None)
+        .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
+
+    let (config, warnings) =
+        Config::parse_str(&content).with_context(|| "Failed to parse Ignition file")?;
+    report_warnings(&classify_warnings(warnings), &[], None)?;
+
+    let json_value = serde_json::to_value(&config).with_context(|| "Failed to serialize config")?;
+    let info = info::collect(&json_value)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+    } else {
+        print!("{}", info);
+    }
+
+    Ok(())
+}
+
+/// Read a disassembly input, decoding it with `--from-base64-file`
+fn read_input(input_path: &Path, options: &DisassembleOptions) -> Result<String> {
+    let content = read_config(input_path, options.max_input_size)
+        .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
+    if options.from_base64_file {
+        decode_base64_config(&content)
+            .with_context(|| format!("Failed to decode input file: {}", input_path.display()))
+    } else {
+        Ok(content)
+    }
+}
+
+/// Merge the `--input-multiple` configs over `content`, returning the merged
+/// config and the input each `storage.files` path last came from
+fn merge_inputs(
+    input_path: &Path,
+    content: &str,
+    options: &DisassembleOptions,
+) -> Result<(String, BTreeMap<String, String>)> {
+    let parse = |content: &str, path: &Path| -> Result<serde_json::Value> {
+        serde_json::from_str(content)
+            .with_context(|| format!("Failed to parse input file: {}", path.display()))
+    };
+
+    let mut merged = parse(content, input_path)?;
+    let mut provenance = BTreeMap::new();
+    for path in merge::file_paths(&merged) {
+        provenance.insert(path.to_string(), input_path.display().to_string());
+    }
+
+    for overlay_path in &options.input_multiple {
+        let overlay = parse(&read_input(overlay_path, options)?, overlay_path)?;
+        for path in merge::file_paths(&overlay) {
+            provenance.insert(path.to_string(), overlay_path.display().to_string());
+        }
+        merge::merge_configs(&mut merged, &overlay);
+    }
+
+    Ok((serde_json::to_string(&merged)?, provenance))
+}
+
+/// Merge decoded directories into `output`, as Ignition merges a child
+/// config into its parent: each `decoded.ign` is merged over the ones before
+/// it, so entries of `storage`, `systemd` and `passwd` lists with the same
+/// `path` or `name` merge field by field with later inputs winning, and new
+/// entries are appended.
+///
+/// Every file the merged config still references is copied from its input.
+/// Files of the same relative path referenced from several inputs, such as
+/// concatenated `append` fragments, get a `.<n>` suffix after the first.
+/// `metadata.json` sidecars merge with later inputs winning per path; other
+/// sidecars are not carried over.
+pub fn merge_decoded_dirs(inputs: &[PathBuf], output: &Path, dry_run: bool) -> Result<Report> {
+    let decoded_path = output.join(DECODED_IGN);
+    if decoded_path.exists() {
+        anyhow::bail!("Merge target already exists: {}", decoded_path.display());
+    }
+
+    let mut merged: Option<serde_json::Value> = None;
+    let mut file_metadata = metadata::FileMetadata::new();
+    for (index, dir) in inputs.iter().enumerate() {
+        let ignition_file = find_ignition_file(dir, None)?;
+        let content = fs::read_to_string(&ignition_file)
+            .with_context(|| format!("Failed to read {}", ignition_file.display()))?;
+        let mut config: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", ignition_file.display()))?;
+        merge::tag_placeholders(&mut config, index);
+
+        if let Some(sidecar) = metadata::read(dir)? {
+            file_metadata.extend(sidecar);
+        }
+        for sidecar in [links::LINKS_FILE, units::UNIT_ENCODINGS_FILE] {
+            if dir.join(sidecar).exists() {
+                warn(&format!(
+                    "{} of {} is not carried over by merge",
+                    sidecar,
+                    dir.display()
+                ));
+            }
+        }
+
+        match &mut merged {
+            Some(merged) => merge::merge_configs(merged, &config),
+            None => merged = Some(config),
+        }
+    }
+    let Some(mut merged) = merged else {
+        anyhow::bail!("Nothing to merge: no input directories given");
+    };
+
+    // Name the output file of every (input, relative file) still referenced
+    let mut names: BTreeMap<(usize, String), String> = BTreeMap::new();
+    let mut taken = BTreeSet::new();
+    let mut copies = Vec::new();
+    merge::resolve_placeholders(&mut merged, &mut |input, relative| {
+        if let Some(name) = names.get(&(input, relative.to_string())) {
+            return Ok(name.clone());
+        }
+        checks::ensure_contained(relative, relative)?;
+        let mut name = relative.to_string();
+        let mut suffix = 1;
+        while taken.contains(&name) {
+            name = format!("{}.{}", relative, suffix);
+            suffix += 1;
+        }
+        taken.insert(name.clone());
+        names.insert((input, relative.to_string()), name.clone());
+        copies.push((inputs[input].join(relative), output.join(&name)));
+        Ok(name)
+    })?;
+
+    let metadata_path = output.join(metadata::METADATA_FILE);
+    let mut report = Report {
+        file_count: copies.len(),
+        extracted_paths: copies.iter().map(|(_, to)| to.clone()).collect(),
+        ..Default::default()
+    };
+    if dry_run {
+        for (_, to) in &copies {
+            report_dry_write(to, false, false);
+        }
+        if !file_metadata.is_empty() {
+            report_dry_write(&metadata_path, false, false);
+        }
+        report_dry_write(&decoded_path, false, false);
+        return Ok(report);
+    }
+
+    fs::create_dir_all(output)
+        .with_context(|| format!("Failed to create directory: {}", output.display()))?;
+    for (from, to) in copies {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&from, &to)
+            .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+        report.written_paths.push(to);
+    }
+    if !file_metadata.is_empty() {
+        fs::write(
+            &metadata_path,
+            serde_json::to_string_pretty(&file_metadata)?,
+        )
+        .with_context(|| format!("Failed to write {}", metadata_path.display()))?;
+        report.written_paths.push(metadata_path);
+    }
+    fs::write(&decoded_path, serde_json::to_string_pretty(&merged)?)
+        .with_context(|| format!("Failed to write {}", decoded_path.display()))?;
+    report.written_paths.push(decoded_path);
+    Ok(report)
+}
+
+/// Convert the config in `input` to spec version `to_version` (e.g. `3.4`),
+/// writing it to `output`, which must not exist yet.
+///
+/// The typed config is re-parsed with the target version's structure;
+/// fields it has no place for are returned, as dotted paths. Downgrading
+/// fails instead when any field would be lost.
+pub fn convert_ignition(input: &Path, output: &Path, to_version: &str) -> Result<Vec<String>> {
+    if output.exists() {
+        anyhow::bail!("Output file already exists: {}", output.display());
+    }
+    let content = read_config(input, None)
+        .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+    let (config, warnings) =
+        Config::parse_str(&content).with_context(|| "Failed to parse Ignition file")?;
+    report_warnings(&classify_warnings(warnings), &[], None)?;
+
+    let mut json_value =
+        serde_json::to_value(&config).with_context(|| "Failed to serialize config")?;
+    let from_version = json_value["ignition"]["version"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let (major, minor, patch) = version_range::parse_version(to_version)?;
+    let target = format!("{}.{}.{}", major, minor, patch);
+    json_value["ignition"]["version"] = serde_json::Value::String(target.clone());
+
+    let (converted, unused) = Config::parse_str(&serde_json::to_string(&json_value)?)
+        .with_context(|| format!("Failed to convert config to spec version {}", target))?;
+    let dropped: Vec<String> = unused
+        .into_iter()
+        .map(|warning| match warning {
+            ignition_config::Warning::UnusedKey(path) => path.trim_start_matches("$.").to_string(),
+            other => other.to_string(),
+        })
+        .collect();
+    let downgrade = version_range::parse_version(&from_version)? > (major, minor, patch);
+    if downgrade && !dropped.is_empty() {
+        anyhow::bail!(
+            "Converting from {} to {} would lose:\n  {}",
+            from_version,
+            target,
+            dropped.join("\n  ")
+        );
+    }
+    for field in &dropped {
+        warn(&format!(
+            "{} can't be represented in spec version {}",
+            field, target
+        ));
+    }
+
+    let converted =
+        serde_json::to_string_pretty(&converted).with_context(|| "Failed to serialize config")?;
+    fs::write(output, converted)
+        .with_context(|| format!("Failed to write output file: {}", output.display()))?;
+    Ok(dropped)
+}
+
+/// Semantic differences between two config files, one line per added,
+/// removed or changed file, directory, link, unit, user or group. Configs
+/// that differ only in how their sources are encoded compare equal.
+pub fn diff_ignition(left: &Path, right: &Path) -> Result<Vec<String>> {
+    let parse = |path: &Path| -> Result<serde_json::Value> {
+        let content = read_config(path, None)
+            .with_context(|| format!("Failed to read input file: {}", path.display()))?;
+        parse_config_value(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    };
+    diff::diff_configs(&parse(left)?, &parse(right)?)
+}
+
+/// Semantic differences between a local config and the one served at `url`,
+/// refusing either when larger than `max_input_size` bytes
+#[cfg(feature = "http")]
+pub fn diff_remote(
+    local_file: &Path,
+    url: &str,
+    max_input_size: Option<u64>,
+) -> Result<Vec<String>> {
+    let local = read_config(local_file, max_input_size)
+        .with_context(|| format!("Failed to read input file: {}", local_file.display()))?;
+    let remote = String::from_utf8(http::fetch(url, max_input_size)?)
+        .with_context(|| format!("Config served at {} is not UTF-8", url))?;
+
+    let local = parse_config_value(&local)
+        .with_context(|| format!("Failed to parse {}", local_file.display()))?;
+    let remote = parse_config_value(&remote)
+        .with_context(|| format!("Failed to parse config at {}", url))?;
+
+    diff::diff_configs(&remote, &local)
+}
+
+/// Parse a config and return its typed representation as JSON
+fn parse_config_value(content: &str) -> Result<serde_json::Value> {
+    let (config, warnings) = Config::parse_str(content)?;
+    report_warnings(&classify_warnings(warnings), &[], None)?;
+    serde_json::to_value(&config).with_context(|| "Failed to serialize config")
+}
+
+/// Read a config file (or stdin for `-`), refusing it before reading when
+/// its size exceeds `max_size`
+fn read_config(path: &Path, max_size: Option<u64>) -> Result<String> {
+    if is_stdin(path) {
+        return read_capped(std::io::stdin().lock(), max_size);
+    }
+
+    let file = fs::File::open(path)?;
+    if let Some(max_size) = max_size {
+        let size = file.metadata()?.len();
+        if size > max_size {
+            anyhow::bail!(
+                "Input is {} bytes, exceeding the maximum input size of {} bytes",
+                size,
+                max_size
+            );
+        }
+    }
+    read_capped(file, max_size)
+}
+
+/// Whether `path` is the `-` sentinel for standard input
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Read all of `reader`, failing once it yields more than `max_size` bytes
+fn read_capped(reader: impl std::io::Read, max_size: Option<u64>) -> Result<String> {
+    use std::io::Read;
+
+    let Some(max_size) = max_size else {
+        return Ok(std::io::read_to_string(reader)?);
+    };
+
+    // The metadata can understate the size of special files, so cap the read too
+    let mut content = String::new();
+    reader.take(max_size + 1).read_to_string(&mut content)?;
+    if content.len() as u64 > max_size {
+        anyhow::bail!("Input exceeds the maximum input size of {} bytes", max_size);
+    }
+
+    Ok(content)
+}
+
+/// Decode a base64-encoded config, inflating it when the payload is gzip
+fn decode_base64_config(content: &str) -> Result<String> {
+    use base64::Engine;
+    use std::io::Read;
+
+    let compact: String = content
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    let mut bytes = base64::engine::general_purpose::STANDARD
+        .decode(compact)
+        .with_context(|| "Input is not valid base64")?;
+
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut inflated = Vec::new();
+        flate2::read::GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut inflated)
+            .with_context(|| "Failed to decompress gzip payload")?;
+        bytes = inflated;
+    }
+
+    String::from_utf8(bytes).with_context(|| "Decoded config is not valid UTF-8")
+}
+
+fn rewrite_remote_sources(
+    json_value: &mut serde_json::Value,
+    rewrites: &[RemoteRewrite],
+    to_stderr: bool,
+) {
+    if rewrites.is_empty() {
+        return;
+    }
+    let rewritten = remotes::rewrite_remotes(json_value, rewrites);
+    status(
+        to_stderr,
+        &format!("Rewrote {} remote source(s)", rewritten),
+    );
+}
+
+/// Embed every `http(s)://` source of the config for `--inline-remote`,
+/// refusing sources larger than `max_size` bytes
+fn inline_remote_sources(
+    json_value: &mut serde_json::Value,
+    max_size: Option<u64>,
+    to_stderr: bool,
+) -> Result<()> {
+    #[cfg(feature = "http")]
+    let mut fetch = |url: &str| http::fetch(url, max_size);
+    #[cfg(not(feature = "http"))]
+    let _ = max_size;
+    #[cfg(not(feature = "http"))]
+    let mut fetch =
+        |url: &str| -> Result<Vec<u8>> { anyhow::bail!("Fetching {} needs the http feature", url) };
+    let inlined = remotes::inline_remotes(json_value, &mut fetch)?;
+    status(to_stderr, &format!("Inlined {} remote source(s)", inlined));
+    Ok(())
+}
+
+/// Print a file a dry run would write instead of writing it, on stderr when
+/// stdout carries the output config. `cleared` is set when `--action
+/// replace` would remove the file's directory first.
+fn report_dry_write(path: &Path, cleared: bool, to_stderr: bool) {
+    let verb = if !cleared && path.exists() {
+        "overwrite"
+    } else {
+        "write"
+    };
+    status(to_stderr, &format!("Would {} {}", verb, path.display()));
+}
+
+/// Path of the decoded config named `ign_name`, [`DECODED_IGN`] by default,
+/// in `dir`
+fn decoded_ign_path(dir: &Path, ign_name: Option<&str>) -> Result<PathBuf> {
+    let name = ign_name.unwrap_or(DECODED_IGN);
+    if Path::new(name).file_name() != Some(name.as_ref()) {
+        anyhow::bail!("--ign-name must be a plain file name: {}", name);
+    }
+    Ok(dir.join(name))
+}
+
+/// Find the decoded config in the ignition_dir: the one named `ign_name`
+/// (decoded.ign by default), or else the first .ign file by name
+fn find_ignition_file(ignition_dir: &Path, ign_name: Option<&str>) -> Result<PathBuf> {
+    let named = decoded_ign_path(ignition_dir, ign_name)?;
+    if named.is_file() {
+        return Ok(named);
+    }
+
+    let mut candidates = Vec::new();
+    for entry in fs::read_dir(ignition_dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("ign") {
+            candidates.push(path);
+        }
+    }
+    candidates.sort();
+    candidates.into_iter().next().ok_or_else(|| {
+        anyhow::anyhow!(
+            "No .ign file found in ignition_dir: {}",
+            ignition_dir.display()
+        )
+    })
+}
+
+/// Warn about the differences between the files of a decoded directory and
+/// the artifacts its manifest lists
+fn validate_against_manifest(ignition_dir: &Path, ignition_file: &Path) -> Result<()> {
+    let manifest = Manifest::read(ignition_dir)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "--validate-manifest requires a {} in {}",
+            manifest::MANIFEST_FILE,
+            ignition_dir.display()
+        )
+    })?;
+    let config_name = ignition_file
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let sidecars = [
+        config_name.as_str(),
+        manifest::MANIFEST_FILE,
+        metadata::METADATA_FILE,
+        units::UNIT_ENCODINGS_FILE,
+        links::LINKS_FILE,
+        references::REFERENCES_FILE,
+        gitattributes::ATTRIBUTES_FILE,
+    ];
+    for warning in manifest.validate_tree(ignition_dir, &sidecars)? {
+        warn(&warning.to_string());
+    }
+    Ok(())
+}
+
+/// Enforce `--assert-file-count`
+fn check_file_count(actual: usize, expected: Option<usize>) -> Result<()> {
+    match expected {
+        Some(expected) if expected != actual => {
+            anyhow::bail!("Expected {} file(s) but processed {}", expected, actual)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Check a config's spec version against `range`, returning why the config
+/// is skipped when it falls outside
+fn version_skip(
+    content: &str,
+    range: Option<&VersionRange>,
+    path: &Path,
+) -> Result<Option<String>> {
+    let Some(range) = range else {
+        return Ok(None);
+    };
+    let version = version_range::config_version(content)
+        .with_context(|| format!("Failed to detect spec version of {}", path.display()))?;
+    if range.contains(&version)? {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "Skipping {}: spec version {} is outside {}",
+        path.display(),
+        version,
+        range
+    )))
+}
+
+/// Merge `json_value` over the decoded config already at `decoded_path` for
+/// `--action merge`, as [`merge_decoded_dirs`] merges a later directory over
+/// an earlier one. Without an existing config, `json_value` is kept as is.
+fn merge_over_existing_decoded(
+    json_value: &mut serde_json::Value,
+    decoded_path: &Path,
+) -> Result<()> {
+    if !decoded_path.is_file() {
+        return Ok(());
+    }
+    let content = fs::read_to_string(decoded_path).with_context(|| {
+        format!(
+            "Failed to read {}: {}",
+            decoded_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            decoded_path.display()
+        )
+    })?;
+    let mut merged: serde_json::Value = serde_json::from_str(&content).with_context(|| {
+        format!(
+            "Failed to parse {}: {}",
+            decoded_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            decoded_path.display()
+        )
+    })?;
+    merge::merge_configs(&mut merged, json_value);
+    *json_value = merged;
+    Ok(())
+}
+
+// Generic function to handle all v3.x configs (they all have the same structure for our purposes)
+fn disassemble_v3_config<T>(
+    config: &mut T,
+    output_dir: &Path,
+    options: &DisassembleOptions,
+    to_stderr: bool,
+    progress: Option<&mut dyn ProgressSink>,
+) -> Result<Extraction>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    // Serialize to JSON value for manipulation
+    let mut json_value: serde_json::Value =
+        serde_json::to_value(config).with_context(|| "Failed to serialize config")?;
+
+    if let Some(policy) = &options.ownership_policy {
+        policy.apply(&mut json_value);
+    }
+
+    rewrite_remote_sources(&mut json_value, &options.relativize_remote, to_stderr);
+
+    let mut extractor = Extractor::new(output_dir, options, to_stderr, progress);
+    if options.dedup {
+        extractor.shared_digests = shared_digests(&json_value, options.strict_array_detection)?;
+    }
+    if options.parallel {
+        extractor.predecode(&json_value)?;
+    }
+    // Files in a directory --action replace would remove don't count as overwritten
+    let cleared = options.action == Action::Replace;
+
+    let mut manifest = Manifest::from_config(&json_value);
+
+    // The SBOM describes the sources as they are before extraction
+    if let (Some(sbom_path), true) = (&options.emit_sbom, options.dry_run) {
+        report_dry_write(sbom_path, false, to_stderr);
+    } else if let Some(sbom_path) = &options.emit_sbom {
+        sbom::write(&json_value, sbom_path, options.strict_array_detection)?;
+    }
+
+    if options.metadata && options.dry_run {
+        report_dry_write(
+            &output_dir.join(metadata::METADATA_FILE),
+            cleared,
+            to_stderr,
+        );
+    } else if options.metadata {
+        let metadata_path = metadata::write(&json_value, output_dir, options.no_clobber_manifest)?;
+        extractor.written_paths.push(metadata_path);
+    }
+
+    if options.keep_going {
+        let skipped = extract_sections_isolated(&mut json_value, &mut extractor);
+        if !skipped.is_empty() {
+            json_value[SKIPPED_SECTIONS_KEY] = serde_json::Value::Object(skipped);
+        }
+    } else {
+        find_and_replace_source_with_path_update(&mut json_value, "", "", &mut extractor)?;
+    }
+
+    if options.decode_unit_contents {
+        let encodings = units::decode_unit_contents(&mut json_value);
+        let encodings_path = output_dir.join(units::UNIT_ENCODINGS_FILE);
+        if !encodings.is_empty() && options.dry_run {
+            report_dry_write(&encodings_path, cleared, to_stderr);
+        } else if !encodings.is_empty() {
+            fs::write(&encodings_path, serde_json::to_string_pretty(&encodings)?)
+                .with_context(|| format!("Failed to write {}", encodings_path.display()))?;
+            extractor.written_paths.push(encodings_path);
+        }
+    }
+
+    // After decoding, so the files hold plain unit text
+    if options.extract_units {
+        let taken = units::take_unit_contents(&mut json_value, options.split_units_dir);
+        for (key, relative, text) in taken {
+            checks::ensure_contained(&relative, &key)?;
+            let source_name = format!("contents of {}", key);
+            let size = extractor.write_file(
+                &relative,
+                text.into_bytes(),
+                None,
+                "text/plain",
+                &key,
+                &source_name,
+            )?;
+            extractor.artifacts.push(manifest::Artifact {
+                ignition_path: key,
+                field: "contents".to_string(),
+                file: relative,
+                media_type: "text/plain".to_string(),
+                compression: None,
+                size,
+            });
+        }
+    }
+
+    if options.extract_ssh_keys {
+        for (user, keys) in ssh_keys::take_authorized_keys(&mut json_value) {
+            let relative = ssh_keys::keys_file(&user);
+            checks::ensure_contained(&relative, &user)?;
+            let source_name = format!("SSH keys of {}", user);
+            let size = extractor.write_file(
+                &relative,
+                keys.into_bytes(),
+                None,
+                "text/plain",
+                &user,
+                &source_name,
+            )?;
+            extractor.artifacts.push(manifest::Artifact {
+                ignition_path: user,
+                field: "sshAuthorizedKeys".to_string(),
+                file: relative,
+                media_type: "text/plain".to_string(),
+                compression: None,
+                size,
+            });
+        }
+    }
+
+    extractor.flush_writes()?;
+
+    if options.apply_mode {
+        modes::apply(&json_value, output_dir, &extractor.written_paths)?;
+    }
+
+    // Gzip digests and artifacts are only known once the sources have been decoded
+    manifest.gzip_digests = std::mem::take(&mut extractor.gzip_digests);
+    manifest.set_artifacts(std::mem::take(&mut extractor.artifacts));
+    if options.action == Action::Merge {
+        if let Some(existing) = Manifest::read(output_dir)? {
+            manifest.merge_over(existing);
+        }
+    }
+    if options.dry_run {
+        report_dry_write(
+            &output_dir.join(manifest::MANIFEST_FILE),
+            cleared,
+            to_stderr,
+        );
+    } else {
+        let manifest_path = if options.no_clobber_manifest {
+            manifest.write_preserving(output_dir)?
+        } else {
+            manifest.write(output_dir)?
+        };
+        extractor.written_paths.push(manifest_path);
+    }
+
+    // Last, so collisions with every extracted file are known
+    if options.extract_links && !options.config_only {
+        let extracted: Vec<String> = extractor.extracted.iter().map(|(p, _)| p.clone()).collect();
+        let link_metadata = links::extract(
+            &mut json_value,
+            output_dir,
+            &extracted,
+            options.dry_run,
+            to_stderr,
+        )?;
+        if options.dry_run {
+            report_dry_write(&output_dir.join(links::LINKS_FILE), cleared, to_stderr);
+        } else {
+            let links_path = links::write(&link_metadata, output_dir)?;
+            extractor.written_paths.push(links_path);
+        }
+    }
+
+    // The inventory of what the config pulls at boot; decoded.ign keeps the sources
+    let remote_count = extractor.references.len();
+    if remote_count > 0 && options.dry_run {
+        report_dry_write(
+            &output_dir.join(references::REFERENCES_FILE),
+            cleared,
+            to_stderr,
+        );
+    } else if remote_count > 0 {
+        let references_path = references::write(&extractor.references, output_dir)?;
+        extractor.written_paths.push(references_path);
+    }
+
+    if options.emit_gitattributes && options.dry_run {
+        report_dry_write(
+            &output_dir.join(gitattributes::ATTRIBUTES_FILE),
+            cleared,
+            to_stderr,
+        );
+    } else if options.emit_gitattributes {
+        let attributes_path = gitattributes::write(output_dir, &extractor.extracted)?;
+        extractor.written_paths.push(attributes_path);
+    }
+
+    if let (Some(dockerfile_path), true) = (&options.emit_dockerfile, options.dry_run) {
+        report_dry_write(dockerfile_path, false, to_stderr);
+    } else if let Some(dockerfile_path) = &options.emit_dockerfile {
+        fs::write(dockerfile_path, dockerfile::copy_snippet(&json_value)).with_context(|| {
+            format!(
+                "Failed to write Dockerfile snippet: {}",
+                dockerfile_path.display()
+            )
+        })?;
+    }
+
+    if options.action == Action::Merge {
+        let decoded_path = decoded_ign_path(output_dir, options.ign_name.as_deref())?;
+        merge_over_existing_decoded(&mut json_value, &decoded_path)?;
+    }
+
+    let decoded_json = json_format::to_json_string(
+        &json_value,
+        options.compact,
+        options.indent,
+        options.canonical_order,
+    )
+    .with_context(|| "Failed to serialize modified config")?;
+
+    Ok(Extraction {
+        decoded_json,
+        file_counter: extractor.file_counter,
+        written_paths: extractor.written_paths,
+        extracted_paths: extractor
+            .extracted
+            .iter()
+            .map(|(path, _)| output_dir.join(path))
+            .collect(),
+        total_bytes: extractor.total_bytes,
+        remote_count,
+    })
+}
+
+/// Hex SHA-256 of every decoded inline content that more than one source of
+/// `config` has
+fn shared_digests(
+    config: &serde_json::Value,
+    strict_array_detection: bool,
+) -> Result<BTreeSet<String>> {
+    let mut seen = BTreeSet::new();
+    let mut shared = BTreeSet::new();
+    for entry in sbom::collect(config, strict_array_detection)? {
+        if let Some(digest) = entry.sha256 {
+            if !seen.insert(digest.clone()) {
+                shared.insert(digest);
+            }
+        }
+    }
+    Ok(shared)
+}
+
+/// Field of a config object whose string value `find_and_replace_source`
+/// hands to its closure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentField {
+    /// `source` of a file, merge config, certificate, ...
+    Source,
+    /// Plain-text `contents` of a systemd unit or dropin, keyed by its name
+    Contents,
+}
+
+fn find_and_replace_source<F>(value: &mut serde_json::Value, path: &str, func: &mut F) -> Result<()>
+where
+    F: FnMut(&str, &str, Option<&str>, bool, usize, ContentField) -> Result<String>,
+{
+    let mut replaced = false;
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut new_path = path.to_string();
+            if let Some(p) = map.get("path").and_then(|v| v.as_str()) {
+                new_path = p.to_string();
+            }
+            let compression = sibling_compression(map);
+            let name = map.get("name").and_then(|v| v.as_str()).map(str::to_string);
+
+            // Check if this object has both a path and array fields with sources
+            let has_path = map.contains_key("path");
+            let mut found_array_with_sources = false;
+
+            if has_path {
+                // Look for array fields that contain objects with sources
+                for (_key, val) in map.iter() {
+                    if let serde_json::Value::Array(arr) = val {
+                        // Check if any element in the array has a source
+                        if arr.iter().any(|item| {
+                            if let serde_json::Value::Object(obj) = item {
+                                obj.contains_key("source")
+                            } else {
+                                false
+                            }
+                        }) {
+                            found_array_with_sources = true;
+                            break;
+                        }
+                    }
+                }
+            }
+
+            for (key, val) in map.iter_mut() {
+                if key == "source" {
+                    if let Some(s) = val.as_str() {
+                        let new_source = func(
+                            &new_path,
+                            s,
+                            compression.as_deref(),
+                            false,
+                            0,
+                            ContentField::Source,
+                        )?;
+                        replaced = new_source != s;
+                        *val = serde_json::Value::String(new_source);
+                    }
+                } else if key == "contents" && val.is_string() {
+                    // Units and dropins are named rather than pathed
+                    let s = val.as_str().unwrap_or_default();
+                    let key_path = name.as_deref().unwrap_or(&new_path);
+                    *val = serde_json::Value::String(func(
+                        key_path,
+                        s,
+                        None,
+                        false,
+                        0,
+                        ContentField::Contents,
+                    )?);
+                } else if found_array_with_sources && key != "path" {
+                    // This might be an array field with sources
+                    if let serde_json::Value::Array(arr) = val {
+                        // Check if this array contains objects with sources
+                        let has_sources = arr.iter().any(|item| {
+                            if let serde_json::Value::Object(obj) = item {
+                                obj.contains_key("source")
+                            } else {
+                                false
+                            }
+                        });
+
+                        if has_sources {
+                            // Process as array of sources
+                            for (index, item) in arr.iter_mut().enumerate() {
+                                process_array_item_sources(item, &new_path, index, func)?;
+                            }
+                        } else {
+                            // Regular array processing
+                            find_and_replace_source(val, &new_path, func)?;
+                        }
+                    } else {
+                        find_and_replace_source(val, &new_path, func)?;
+                    }
+                } else {
+                    find_and_replace_source(val, &new_path, func)?;
+                }
+            }
+        }
+        serde_json::Value::Array(arr) => {
+            for val in arr.iter_mut() {
+                find_and_replace_source(val, path, func)?;
+            }
+        }
+        _ => (),
+    }
+    if replaced {
+        refresh_verification_hash(value)?;
+    }
+    Ok(())
+}
+
+fn process_array_item_sources<F>(
+    item: &mut serde_json::Value,
+    path: &str,
+    index: usize,
+    func: &mut F,
+) -> Result<()>
+where
+    F: FnMut(&str, &str, Option<&str>, bool, usize, ContentField) -> Result<String>,
+{
+    let mut replaced = false;
+    if let serde_json::Value::Object(map) = item {
+        let compression = sibling_compression(map);
+        for (key, val) in map.iter_mut() {
+            if key == "source" {
+                if let Some(s) = val.as_str() {
+                    let new_source = func(
+                        path,
+                        s,
+                        compression.as_deref(),
+                        true,
+                        index,
+                        ContentField::Source,
+                    )?;
+                    replaced = new_source != s;
+                    *val = serde_json::Value::String(new_source);
+                }
+            } else {
+                // Recursively process nested structures
+                process_nested_sources(val, path, index, func)?;
+            }
+        }
+    }
+    if replaced {
+        refresh_verification_hash(item)?;
+    }
+    Ok(())
+}
+
+fn process_nested_sources<F>(
+    value: &mut serde_json::Value,
+    path: &str,
+    index: usize,
+    func: &mut F,
+) -> Result<()>
+where
+    F: FnMut(&str, &str, Option<&str>, bool, usize, ContentField) -> Result<String>,
+{
+    let mut replaced = false;
+    match value {
+        serde_json::Value::Object(map) => {
+            let compression = sibling_compression(map);
+            for (key, val) in map.iter_mut() {
+                if key == "source" {
+                    if let Some(s) = val.as_str() {
+                        let new_source = func(
+                            path,
+                            s,
+                            compression.as_deref(),
+                            true,
+                            index,
+                            ContentField::Source,
+                        )?;
+                        replaced = new_source != s;
+                        *val = serde_json::Value::String(new_source);
+                    }
+                } else {
+                    process_nested_sources(val, path, index, func)?;
+                }
+            }
+        }
+        serde_json::Value::Array(arr) => {
+            for val in arr.iter_mut() {
+                process_nested_sources(val, path, index, func)?;
+            }
+        }
+        _ => (),
+    }
+    if replaced {
+        refresh_verification_hash(value)?;
+    }
+    Ok(())
+}
+
+/// Recompute the `verification.hash` a resource already has after its
+/// source was re-embedded, with the algorithm it used, so a hand-edited
+/// content file still verifies. Remote sources keep their hash.
+fn refresh_verification_hash(resource: &mut serde_json::Value) -> Result<()> {
+    if verification::existing_hash(resource).is_none() {
+        return Ok(());
+    }
+    // Deferred content is hashed by `refresh_deferred_hashes`
+    let source = resource.get("source").and_then(|s| s.as_str());
+    if source.is_some_and(streaming::is_deferred) {
+        return Ok(());
+    }
+    if let Some(content) = verification::inline_content(resource)? {
+        verification::recompute_hash(resource, &content)?;
+    }
+    Ok(())
+}
+
+/// Recompute the existing `verification.hash` of every resource whose
+/// source is deferred, reading its content file in chunks
+fn refresh_deferred_hashes(
+    value: &mut serde_json::Value,
+    deferred: &[DeferredSource],
+) -> Result<()> {
+    let source = value.get("source").and_then(|s| s.as_str());
+    let file = deferred
+        .iter()
+        .find(|d| Some(d.marker.as_str()) == source)
+        .map(|d| &d.file);
+    if let (Some(file), Some(old_hash)) = (file, verification::existing_hash(value)) {
+        let (algorithm, _) = verification::parse_hash(old_hash)?;
+        let reader =
+            fs::File::open(file).with_context(|| format!("Failed to open {}", file.display()))?;
+        let hash = algorithm.hash_reader(std::io::BufReader::new(reader))?;
+        value["verification"]["hash"] = serde_json::Value::String(hash);
+        return Ok(());
+    }
+
+    match value {
+        serde_json::Value::Object(map) => {
+            for val in map.values_mut() {
+                refresh_deferred_hashes(val, deferred)?;
+            }
+        }
+        serde_json::Value::Array(arr) => {
+            for val in arr.iter_mut() {
+                refresh_deferred_hashes(val, deferred)?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Remove whatever still holds a placeholder after embedding: array entries
+/// (files, append fragments, merge configs, ...) are dropped as a whole, as
+/// are files whose `contents` is unresolved; other resources lose their key.
+/// Returns whether `value` itself must be removed by its parent.
+fn drop_unresolved_placeholders(value: &mut serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Object(map) => {
+            let unresolved = map
+                .get("source")
+                .and_then(|s| s.as_str())
+                .is_some_and(|s| s.contains(";base64-placeholder,"));
+            if unresolved {
+                return true;
+            }
+
+            let mut drop_keys = Vec::new();
+            for (key, val) in map.iter_mut() {
+                // Unit and dropin contents are plain strings; the unit stays
+                if key == "contents"
+                    && val
+                        .as_str()
+                        .is_some_and(|s| s.contains(";base64-placeholder,"))
+                {
+                    drop_keys.push(key.clone());
+                } else if drop_unresolved_placeholders(val) {
+                    if key == "contents" {
+                        return true;
+                    }
+                    drop_keys.push(key.clone());
+                }
+            }
+            for key in drop_keys {
+                map.remove(&key);
+            }
+            false
+        }
+        serde_json::Value::Array(arr) => {
+            arr.retain_mut(|item| !drop_unresolved_placeholders(item));
+            false
+        }
+        _ => false,
+    }
+}
+
+/// Numeric fields for which zero is a meaningful value (root ownership, mode 0)
+const ZERO_SIGNIFICANT_KEYS: &[&str] = &["id", "uid", "gid", "mode"];
+
+/// Fields whose default value still means something: an explicit `false`
+/// or `[]` overrides what a parent config sets when the config is merged
+/// into it, and `enabled: false` disables a unit rather than leaving it be
+const DEFAULT_SIGNIFICANT_KEYS: &[&str] = &["overwrite", "append", "enabled"];
+
+/// The `--default` pass over a whole config, keeping `keep_keys` along with
+/// the fields it always keeps. Whatever it holds, the `ignition` object and
+/// its `version` always survive: a config without them is not a config.
+/// Kept keys under `ignition` come back as they were, not as the pass left
+/// their contents.
+fn remove_config_defaults(config: &mut serde_json::Value, keep_keys: &[String]) {
+    let keep_all = keep_keys.iter().any(|k| k == "ignition");
+    let kept: Vec<(String, serde_json::Value)> = config
+        .get("ignition")
+        .and_then(|ignition| ignition.as_object())
+        .map(|ignition| {
+            ignition
+                .iter()
+                .filter(|(k, _)| keep_all || *k == "version" || keep_keys.contains(k))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let had_ignition = config.get("ignition").is_some();
+    remove_default_values(config, keep_keys);
+    if had_ignition && config.get("ignition").is_none() {
+        config["ignition"] = serde_json::json!({});
+    }
+    for (key, value) in kept {
+        config["ignition"][key] = value;
+    }
+}
+
+fn remove_default_values(value: &mut serde_json::Value, keep_keys: &[String]) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|k, v| {
+                !is_default(v)
+                    || (v.is_number() && ZERO_SIGNIFICANT_KEYS.contains(&k.as_str()))
+                    || (!v.is_null()
+                        && (DEFAULT_SIGNIFICANT_KEYS.contains(&k.as_str())
+                            || keep_keys.contains(k)))
+            });
+            for (_, v) in map.iter_mut() {
+                remove_default_values(v, keep_keys);
+            }
+        }
+        serde_json::Value::Array(arr) => {
+            arr.retain(|v| !is_default(v));
+            for v in arr.iter_mut() {
+                remove_default_values(v, keep_keys);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn is_default(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => true,
+        serde_json::Value::String(s) => s.is_empty(),
+        serde_json::Value::Array(arr) => arr.is_empty(),
+        serde_json::Value::Object(map) => map.is_empty(),
+        serde_json::Value::Bool(b) => !b,
+        serde_json::Value::Number(n) => n.as_u64().unwrap_or(1) == 0,
+    }
+}
+
+/// Warning kinds that abort the run: in strict mode, every kind the parser
+/// reports
+fn abort_on_kinds(selected: &[WarningKind], strict: bool) -> &[WarningKind] {
+    if strict {
+        &[WarningKind::UnknownField]
+    } else {
+        selected
+    }
+}
+
+pub fn assemble_ignition(
+    target_file: &Path,
+    ignition_dir: &Path,
+    options: &AssembleOptions,
+    progress: Option<&mut dyn ProgressSink>,
+) -> Result<Report> {
+    if !ignition_dir.is_dir() {
+        if ignition_dir.exists() {
+            anyhow::bail!(
+                "Ignition directory exists and is a file, not a directory: {}",
+                ignition_dir.display()
+            );
+        }
+        anyhow::bail!(
+            "Ignition directory does not exist: {}",
+            ignition_dir.display()
+        );
+    }
+
+    if options.action == Action::Merge {
+        anyhow::bail!("--action merge only applies to disassembly");
+    }
+
+    if let Some(manifest) = Manifest::read(ignition_dir)? {
+        manifest.check_placeholder_format()?;
+    }
+
+    let ignition_file = find_ignition_file(ignition_dir, options.ign_name.as_deref())?;
+    if options.print_config_path {
+        println!("{}", ignition_file.display());
+        return Ok(Report::default());
+    }
+
+    if options.validate_manifest {
+        validate_against_manifest(ignition_dir, &ignition_file)?;
+    }
+
+    // Read the decoded Ignition file
+    let content = read_config(&ignition_file, options.max_input_size)
+        .with_context(|| format!("Failed to read decoded file: {}", ignition_file.display()))?;
+
+    if let Some(reason) = version_skip(&content, options.version_range.as_ref(), &ignition_file)? {
+        return Ok(Report {
+            skipped: Some(reason),
+            ..Default::default()
+        });
+    }
+
+    // Parse the Ignition config
+    let (config, warnings) =
+        Config::parse_str(&content).with_context(|| "Failed to parse decoded Ignition file")?;
+
+    // Print warnings if any, aborting on the selected kinds before the
+    // target is touched
+    let warnings = classify_warnings(warnings);
+    let abort_on = abort_on_kinds(&options.abort_on_warning, options.strict);
+    report_warnings(&warnings, abort_on, options.quiet_warnings)?;
+    let mut report = Report {
+        warnings: warnings.iter().map(ToString::to_string).collect(),
+        ..Default::default()
+    };
+
+    // Handle target file based on action; stdout has none to check
+    if options.stdout {
+        if options.split_large.is_some() {
+            anyhow::bail!("--split-large needs an output file and can't write to stdout");
+        }
+    } else if target_file.exists() {
+        match options.action {
+            Action::New => {
+                anyhow::bail!(
+                    "Target file already exists: {}. Use --action replace or --action add to overwrite.",
+                    target_file.display()
+                );
+            }
+            Action::Merge => unreachable!("rejected before reading the config"),
+            Action::Add | Action::Replace if options.dry_run => {
+                status(
+                    false,
+                    &format!("Would overwrite existing file: {}", target_file.display()),
+                );
+            }
+            // The old file is only replaced once the new one is complete
+            Action::Add | Action::Replace => {
+                status(
+                    false,
+                    &format!("Overwriting existing file: {}", target_file.display()),
+                );
+            }
+        }
+    } else if options.dry_run {
+        status(
+            false,
+            &format!("Would create new file: {}", target_file.display()),
+        );
+    } else {
+        // File doesn't exist, all actions will create it
+        status(
+            false,
+            &format!("Creating new file: {}", target_file.display()),
+        );
+    }
+
+    // Keep the raw decoded config around to restore fields the parser drops
+    let original = if options.preserve_unknown {
+        let mut original = serde_json::from_str::<serde_json::Value>(&content)
+            .with_context(|| "Failed to parse decoded Ignition file as JSON")?;
+        // The keep-going annotation describes the decoded tree, not the config
+        if let Some(map) = original.as_object_mut() {
+            map.remove(SKIPPED_SECTIONS_KEY);
+        }
+        Some(original)
+    } else {
+        None
+    };
+    let original = original.as_ref();
+
+    // Work with the config based on version
+    let assembled = match config {
+        Config::V3_0(cfg) => assemble_v3_config(&cfg, ignition_dir, original, options, progress)?,
+        Config::V3_1(cfg) => assemble_v3_config(&cfg, ignition_dir, original, options, progress)?,
+        Config::V3_2(cfg) => assemble_v3_config(&cfg, ignition_dir, original, options, progress)?,
+        Config::V3_3(cfg) => assemble_v3_config(&cfg, ignition_dir, original, options, progress)?,
+        Config::V3_4(cfg) => assemble_v3_config(&cfg, ignition_dir, original, options, progress)?,
+        Config::V3_5(cfg) => assemble_v3_config(&cfg, ignition_dir, original, options, progress)?,
+        _ => anyhow::bail!("Unsupported Ignition config version"),
+    };
+    check_file_count(assembled.file_count, options.assert_file_count)?;
+    report.file_count = assembled.file_count;
+    let Assembled {
+        json: mut modified_json,
+        externalized,
+        deferred,
+        ..
+    } = assembled;
+
+    if options.default {
+        let mut json_value: serde_json::Value = serde_json::from_str(&modified_json)?;
+        remove_config_defaults(&mut json_value, &options.keep_keys);
+        modified_json = json_format::to_json_string(
+            &json_value,
+            options.compact,
+            options.indent,
+            options.canonical_order,
+        )?;
+    }
+
+    if options.validate {
+        validate::validate_assembled(&modified_json)?;
+    }
+
+    if options.newline {
+        modified_json.push('\n');
+    }
+
+    let split_dir = target_file.with_extension("split");
+    if options.dry_run {
+        for source in &externalized {
+            let path = split_dir.join(&source.path);
+            report_dry_write(&path, false, options.stdout);
+            report.extracted_paths.push(path);
+        }
+        if !externalized.is_empty() {
+            report_dry_write(&split_dir.join(UPLOAD_MANIFEST_FILE), false, options.stdout);
+        }
+        return Ok(report);
+    }
+
+    if options.stdout {
+        use std::io::Write;
+        let mut stdout = std::io::stdout().lock();
+        streaming::write_with_deferred(&modified_json, &deferred, &mut stdout)
+            .and_then(|()| Ok(stdout.flush()?))
+            .with_context(|| "Failed to write output to stdout")?;
+        return Ok(report);
+    }
+
+    // Write the encoded Ignition file
+    write_output(target_file, &modified_json, &deferred)
+        .with_context(|| format!("Failed to write output file: {}", target_file.display()))?;
+    verbosity::detail(
+        false,
+        &format!(
+            "Wrote {} ({} bytes, application/json)",
+            target_file.display(),
+            fs::metadata(target_file)?.len()
+        ),
+    );
+    report.written_paths.push(target_file.to_path_buf());
+
+    if options.reproducible {
+        reproducible::set_mtime(target_file, reproducible::fixed_mtime())?;
+    }
+
+    if !externalized.is_empty() {
+        let copies = write_split_sources(&externalized, ignition_dir, &split_dir)?;
+        report.extracted_paths.extend(copies.iter().cloned());
+        report.written_paths.extend(copies);
+        report
+            .written_paths
+            .push(split_dir.join(UPLOAD_MANIFEST_FILE));
+    }
+
+    Ok(report)
+}
+
+/// Write the serialized config to `target_file`, streaming deferred content
+/// into it. The config goes to a temporary file next to it first, renamed
+/// over `target_file` once complete, so a failed write keeps any previous
+/// target.
+fn write_output(target_file: &Path, json: &str, deferred: &[DeferredSource]) -> Result<()> {
+    use std::io::Write;
+    let file_name = target_file
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temp_path = target_file.with_file_name(format!(".{}.tmp", file_name));
+    let write = || -> Result<()> {
+        let mut writer = std::io::BufWriter::new(fs::File::create(&temp_path)?);
+        streaming::write_with_deferred(json, deferred, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    };
+    if let Err(e) = write().and_then(|()| Ok(fs::rename(&temp_path, target_file)?)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn assemble_v3_config<T>(
+    config: &T,
+    files_dir: &Path,
+    original: Option<&serde_json::Value>,
+    options: &AssembleOptions,
+    mut progress: Option<&mut dyn ProgressSink>,
+) -> Result<Assembled>
+where
+    T: serde::Serialize,
+{
+    // Serialize to JSON value for manipulation
+    let mut json_value: serde_json::Value =
+        serde_json::to_value(config).with_context(|| "Failed to serialize config")?;
+
+    // The sidecar overrides decoded.ign, and both override policy defaults
+    if let Some(file_metadata) = metadata::read(files_dir)? {
+        metadata::apply(&mut json_value, &file_metadata);
+    }
+
+    // What the tree says about modes overrides policy defaults, but not the
+    // config or its sidecar
+    if options.mode_from_file {
+        modes::read_back(&mut json_value, files_dir)?;
+    }
+
+    // Symlinks in the tree are links once disassembly has extracted them
+    if let Some(link_metadata) = links::read(files_dir)? {
+        links::restore(&mut json_value, files_dir, &link_metadata)?;
+    }
+
+    if let Some(policy) = &options.ownership_policy {
+        policy.apply(&mut json_value);
+    }
+
+    rewrite_remote_sources(&mut json_value, &options.relativize_remote, options.stdout);
+
+    if options.inline_remote {
+        inline_remote_sources(&mut json_value, options.max_input_size, options.stdout)?;
+    }
+
+    // Restore dropped fields before embedding so any sources they hold are embedded too
+    if let Some(original) = original {
+        let mut restored = Vec::new();
+        restore_dropped_fields(original, &mut json_value, "", &mut restored);
+        for pointer in restored {
+            warn(&format!(
+                "preserved field dropped by the Ignition parser: {}",
+                pointer
+            ));
+        }
+    }
+
+    let mut file_counter = 0;
+    let mut externalized = Vec::new();
+    let mut deferred = Vec::new();
+    let mut missing_files = 0;
+    let gzip_digests = if options.verify_gzip_roundtrip {
+        Manifest::read(files_dir)?
+            .map(|manifest| manifest.gzip_digests)
+            .unwrap_or_default()
+    } else {
+        BTreeMap::new()
+    };
+
+    find_and_replace_source(
+        &mut json_value,
+        "",
+        &mut |path, source_str, compression, _is_array, _array_index, field| {
+            if source_str.contains(";base64-placeholder,") {
+                // Extract media type and file path from the placeholder
+                let after_data = source_str.trim_start_matches("data:");
+                let parts: Vec<&str> = after_data.splitn(2, ";base64-placeholder,").collect();
+                // Sources that used base64url are embedded with it again
+                let (media_type, base64url) =
+                    match parts[0].strip_suffix(streaming::BASE64URL_PARAMETER) {
+                        Some(media_type) => (media_type, true),
+                        None => (parts[0], options.base64url),
+                    };
+                let file_path = if parts.len() > 1 && !parts[1].is_empty() {
+                    parts[1]
+                } else {
+                    return Err(anyhow::anyhow!(
+                        "Invalid placeholder format: missing file path"
+                    ));
+                };
+
+                let in_path = files_dir.join(file_path);
+                // Missing files and symlinks are properties of the working tree
+                let from_worktree = options.content_from_git.is_none();
+
+                if from_worktree && options.allow_missing_files && !in_path.exists() {
+                    warn(&format!(
+                        "omitting {} from the output, its file is missing: {}",
+                        path,
+                        in_path.display()
+                    ));
+                    missing_files += 1;
+                    return Ok(source_str.to_string());
+                }
+
+                if from_worktree && options.follow_symlinks == SymlinkPolicy::Deny {
+                    checks::ensure_no_symlinks(files_dir, file_path)?;
+                }
+
+                let split_source = |size: usize| {
+                    let split = options
+                        .split_large
+                        .as_ref()
+                        .filter(|s| size > s.threshold)?;
+                    let url = format!("{}/{}", split.base_url.trim_end_matches('/'), file_path);
+                    Some(ExternalizedSource {
+                        path: file_path.to_string(),
+                        url,
+                        size,
+                    })
+                };
+
+                // Content that needs no processing as a whole is streamed
+                // into the output when it is written
+                let chunked = options.chunked_write
+                    && from_worktree
+                    && field == ContentField::Source
+                    && options.transform_cmd.is_none()
+                    && !options.verify_gzip_roundtrip
+                    && !(options.embed_newline == NewlineMode::Lf
+                        && gitattributes::is_text(media_type));
+
+                let (embedded, size) = if chunked {
+                    let size = fs::metadata(&in_path)
+                        .with_context(|| format!("Failed to read {}", in_path.display()))?
+                        .len() as usize;
+                    if let Some(source) = split_source(size) {
+                        let url = source.url.clone();
+                        externalized.push(source);
+                        return Ok(url);
+                    }
+                    let gzip = match compression.unwrap_or("") {
+                        "" => false,
+                        "gzip" => true,
+                        other => anyhow::bail!("Unsupported compression '{}' for {}", other, path),
+                    };
+                    let source = DeferredSource::new(
+                        media_type,
+                        in_path.clone(),
+                        gzip,
+                        base64url,
+                        deferred.len(),
+                    );
+                    let marker = source.marker.clone();
+                    deferred.push(source);
+                    (marker, size)
+                } else {
+                    let mut file_content = match &options.content_from_git {
+                        Some(rev) => gitref::read_file(files_dir, rev, file_path)?,
+                        None => fs::read(&in_path)?,
+                    };
+
+                    if options.embed_newline == NewlineMode::Lf
+                        && gitattributes::is_text(media_type)
+                    {
+                        file_content = crlf_to_lf(&file_content);
+                    }
+
+                    if field == ContentField::Contents {
+                        // Unit contents go back in as the plain text they were
+                        let text = String::from_utf8(file_content)
+                            .with_context(|| format!("{} is not UTF-8 text", in_path.display()))?;
+                        let size = text.len();
+                        (text, size)
+                    } else {
+                        if let Some(program) = &options.transform_cmd {
+                            file_content = transform::run_transform(
+                                program,
+                                &file_content,
+                                path,
+                                Direction::Encode,
+                            )?;
+                        }
+
+                        if let Some(source) = split_source(file_content.len()) {
+                            let url = source.url.clone();
+                            externalized.push(source);
+                            return Ok(url);
+                        }
+
+                        let gzipped = match compression.unwrap_or("") {
+                            "" => None,
+                            "gzip" => Some(gzip_deterministic(&file_content)?),
+                            other => {
+                                anyhow::bail!("Unsupported compression '{}' for {}", other, path)
+                            }
+                        };
+
+                        if let (Some(gzipped), true) = (&gzipped, options.verify_gzip_roundtrip) {
+                            let original = gzip_digests.get(file_path).map(String::as_str);
+                            if let Some(warning) =
+                                verify_gzip_roundtrip(path, &file_content, gzipped, original)?
+                            {
+                                warn(&warning.to_string());
+                            }
+                        }
+
+                        use base64::Engine;
+                        let encoded = streaming::base64_engine(base64url)
+                            .encode(gzipped.as_deref().unwrap_or(&file_content));
+                        (
+                            format!("data:{};base64,{}", media_type, encoded),
+                            file_content.len(),
+                        )
+                    }
+                };
+
+                file_counter += 1;
+                if let Some(progress) = progress.as_deref_mut() {
+                    progress.on_file_embedded(&FileEvent {
+                        ignition_path: path.to_string(),
+                        file: in_path,
+                        media_type: media_type.to_string(),
+                        size,
+                    });
+                }
+                Ok(embedded)
+            } else {
+                Ok(source_str.to_string())
+            }
+        },
+    )?;
+
+    ssh_keys::restore_authorized_keys(&mut json_value, |relative| {
+        let in_path = files_dir.join(relative);
+        let content = match &options.content_from_git {
+            Some(rev) => gitref::read_file(files_dir, rev, relative)?,
+            None if options.allow_missing_files && !in_path.exists() => {
+                warn(&format!(
+                    "omitting SSH keys from the output, their file is missing: {}",
+                    in_path.display()
+                ));
+                return Ok(None);
+            }
+            None => fs::read(&in_path)
+                .with_context(|| format!("Failed to read {}", in_path.display()))?,
+        };
+        let text = String::from_utf8(content)
+            .with_context(|| format!("{} is not UTF-8 text", in_path.display()))?;
+        Ok(Some(text))
+    })?;
+
+    if missing_files > 0 {
+        drop_unresolved_placeholders(&mut json_value);
+    }
+
+    refresh_deferred_hashes(&mut json_value, &deferred)?;
+
+    reorder_storage_files(&mut json_value, options.order, files_dir)?;
+
+    // Merge and replace references carry their own verification, which must
+    // match the config that was just re-embedded
+    update_config_reference_hashes(&mut json_value)?;
+
+    // Restore unit contents that disassembly decoded from base64/gzip
+    let encodings_path = files_dir.join(units::UNIT_ENCODINGS_FILE);
+    if encodings_path.is_file() {
+        let encodings = serde_json::from_str(&fs::read_to_string(&encodings_path)?)
+            .with_context(|| format!("Failed to parse {}", encodings_path.display()))?;
+        units::reencode_unit_contents(&mut json_value, &encodings)?;
+    }
+
+    let encoded_json = json_format::to_json_string(
+        &json_value,
+        options.compact,
+        options.indent,
+        options.canonical_order,
+    )
+    .with_context(|| "Failed to serialize encoded config")?;
+
+    Ok(Assembled {
+        json: encoded_json,
+        file_count: file_counter,
+        externalized,
+        deferred,
+    })
+}
+
+/// Gzip `content` with a zero mtime and unknown OS byte, so the same input
+/// always produces the same stream
+fn gzip_deterministic(content: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Write;
+    let mut encoder = streaming::gzip_encoder(Vec::new());
+    encoder.write_all(content)?;
+    Ok(encoder.finish()?)
+}
+
+/// Check that `gzipped` inflates back to `content`. Returns a warning when
+/// its bytes don't match `original_digest`, the hash of the gzip stream the
+/// content was extracted from.
+fn verify_gzip_roundtrip(
+    path: &str,
+    content: &[u8],
+    gzipped: &[u8],
+    original_digest: Option<&str>,
+) -> Result<Option<String>> {
+    use std::io::Read;
+    let mut inflated = Vec::new();
+    flate2::read::GzDecoder::new(gzipped)
+        .read_to_end(&mut inflated)
+        .with_context(|| format!("Failed to decompress re-gzipped content for {}", path))?;
+    if inflated != content {
+        anyhow::bail!("Gzip round trip changed the content of {}", path);
+    }
+
+    let Some(original_digest) = original_digest else {
+        return Ok(None);
+    };
+    let (algorithm, _) = verification::parse_hash(original_digest)?;
+    if algorithm.hash(gzipped) == original_digest {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "re-gzipped {} differs byte-wise from the original gzip stream; its decompressed content is unchanged",
+        path
+    )))
+}
+
+/// Replace every CRLF pair with LF, leaving lone CRs in place
+fn crlf_to_lf(content: &[u8]) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(content.len());
+    for (i, &byte) in content.iter().enumerate() {
+        if byte == b'\r' && content.get(i + 1) == Some(&b'\n') {
+            continue;
+        }
+        normalized.push(byte);
+    }
+    normalized
+}
+
+/// Recompute the `verification.hash` of inline `ignition.config.merge` and
+/// `ignition.config.replace` references so edited configs still verify
+fn update_config_reference_hashes(json_value: &mut serde_json::Value) -> Result<()> {
+    let Some(config) = json_value
+        .pointer_mut("/ignition/config")
+        .and_then(|c| c.as_object_mut())
+    else {
+        return Ok(());
+    };
+
+    let mut references: Vec<&mut serde_json::Value> = Vec::new();
+    for (key, val) in config.iter_mut() {
+        match (key.as_str(), val) {
+            ("merge", serde_json::Value::Array(merge)) => references.extend(merge.iter_mut()),
+            ("replace", replace) => references.push(replace),
+            _ => (),
+        }
+    }
+
+    for reference in references {
+        let Some(source) = reference.get("source").and_then(|s| s.as_str()) else {
+            continue;
+        };
+        if !source.starts_with("data:")
+            || streaming::is_deferred(source)
+            || verification::existing_hash(reference).is_none()
+        {
+            continue;
+        }
+        let content = verification::decode_data_url(source)?;
+        verification::recompute_hash(reference, &content)?;
+    }
+
+    Ok(())
+}
+
+/// Reorder `storage.files` according to `order`.
+///
+/// In manifest order, entries the manifest doesn't know about keep their
+/// relative order after the recorded ones.
+fn reorder_storage_files(
+    json_value: &mut serde_json::Value,
+    order: AssembleOrder,
+    files_dir: &Path,
+) -> Result<()> {
+    let Some(files) = json_value
+        .pointer_mut("/storage/files")
+        .and_then(|f| f.as_array_mut())
+    else {
+        return Ok(());
+    };
+
+    let path_of = |entry: &serde_json::Value| {
+        entry
+            .get("path")
+            .and_then(|p| p.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    match order {
+        AssembleOrder::Source => (),
+        AssembleOrder::Sorted => files.sort_by_key(path_of),
+        AssembleOrder::Manifest => {
+            let manifest = Manifest::read(files_dir)?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "--assemble-order manifest requires a {} in {}",
+                    manifest::MANIFEST_FILE,
+                    files_dir.display()
+                )
+            })?;
+            // Stable sort keeps unknown entries in source order after the known ones
+            files.sort_by_key(|entry| {
+                let path = path_of(entry);
+                manifest
+                    .file_order
+                    .iter()
+                    .position(|p| *p == path)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy externalized sources into `split_dir` alongside an upload manifest
+/// describing where each one must be served from.
+fn write_split_sources(
+    externalized: &[ExternalizedSource],
+    files_dir: &Path,
+    split_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let mut copies = Vec::new();
+    for source in externalized {
+        let dest = split_dir.join(&source.path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(files_dir.join(&source.path), &dest)
+            .with_context(|| format!("Failed to copy externalized source: {}", source.path))?;
+        copies.push(dest);
+    }
+
+    let manifest_path = split_dir.join(UPLOAD_MANIFEST_FILE);
+    let manifest = serde_json::to_string_pretty(externalized)?;
+    fs::write(&manifest_path, manifest).with_context(|| {
+        format!(
+            "Failed to write upload manifest: {}",
+            manifest_path.display()
+        )
+    })?;
+
+    Ok(copies)
+}
+
+/// Copy every field present in `original` but missing from `value` back into
+/// `value`, recording the JSON Pointer of each restored field.
+fn restore_dropped_fields(
+    original: &serde_json::Value,
+    value: &mut serde_json::Value,
+    pointer: &str,
+    restored: &mut Vec<String>,
+) {
+    match (original, value) {
+        (serde_json::Value::Object(original_map), serde_json::Value::Object(map)) => {
+            for (key, original_val) in original_map {
+                let child_pointer = pointer_push(pointer, key);
+                match map.get_mut(key) {
+                    Some(val) => {
+                        restore_dropped_fields(original_val, val, &child_pointer, restored)
+                    }
+                    None => {
+                        map.insert(key.clone(), original_val.clone());
+                        restored.push(child_pointer);
+                    }
+                }
+            }
+        }
+        (serde_json::Value::Array(original_arr), serde_json::Value::Array(arr))
+            if original_arr.len() == arr.len() =>
+        {
+            for (index, (original_val, val)) in original_arr.iter().zip(arr.iter_mut()).enumerate()
+            {
+                let child_pointer = pointer_push(pointer, &index.to_string());
+                restore_dropped_fields(original_val, val, &child_pointer, restored);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Append a reference token to a JSON Pointer, escaping it per RFC 6901
+fn pointer_push(pointer: &str, token: &str) -> String {
+    format!(
+        "{}/{}",
+        pointer,
+        token.replace('~', "~0").replace('/', "~1")
+    )
+}
+
+/// Outcome of extracting the sources of a typed config
+struct Extraction {
+    /// decoded.ign content with placeholders in place of the sources
+    decoded_json: String,
+    file_counter: usize,
+    written_paths: Vec<PathBuf>,
+    /// Content files of the decoded tree
+    extracted_paths: Vec<PathBuf>,
+    /// Combined size of the files written
+    total_bytes: u64,
+    /// Number of sources fetched at boot rather than embedded
+    remote_count: usize,
+}
+
+/// Receives every `source` found by the disassembly walk
+trait SourceVisitor {
+    /// Handle `source` found for the ignition `path`, returning the value that
+    /// replaces it. `entry` names the array field and index of sources inside
+    /// a list such as `append`.
+    fn visit(
+        &mut self,
+        source: &str,
+        compression: Option<&str>,
+        path: &str,
+        entry: Option<(&str, usize)>,
+    ) -> Result<String>;
+
+    /// Note a resource whose `source` is fetched at boot, before that source
+    /// is visited
+    fn visit_remote(
+        &mut self,
+        _resource: &serde_json::Map<String, serde_json::Value>,
+        _path: &str,
+        _entry: Option<(&str, usize)>,
+    ) {
+    }
+
+    /// Only treat arrays whose entries all have a source as lists of sources
+    fn strict_array_detection(&self) -> bool {
+        false
+    }
+}
+
+/// State carried through the disassembly walk
+struct Extractor<'a, 'p> {
+    output_dir: &'a Path,
+    options: &'a DisassembleOptions,
+    /// Status goes to stderr when the config is piped in
+    to_stderr: bool,
+    progress: Option<&'p mut dyn ProgressSink>,
+    file_counter: usize,
+    written_paths: Vec<PathBuf>,
+    /// Relative path and media type of every file in the decoded tree
+    extracted: Vec<(String, String)>,
+    /// The source each relative path of the decoded tree was written for
+    claimed: BTreeMap<String, String>,
+    /// Manifest entry of every extracted file
+    artifacts: Vec<manifest::Artifact>,
+    /// Hash of the original compressed bytes of gzipped files
+    gzip_digests: BTreeMap<String, String>,
+    /// Every source fetched at boot rather than embedded
+    references: Vec<references::Reference>,
+    /// Hex SHA-256 of the decoded contents more than one source has
+    shared_digests: BTreeSet<String>,
+    /// Sources decoded ahead of the walk by `--parallel`, by source
+    predecoded: HashMap<String, Vec<u8>>,
+    /// Files `--parallel` writes once the walk is done
+    pending_writes: Vec<PendingWrite>,
+    /// Files that existed before this run wrote them, which a section
+    /// skipped by `--keep-going` must not remove
+    preexisting: BTreeSet<PathBuf>,
+    total_bytes: u64,
+}
+
+impl<'a, 'p> Extractor<'a, 'p> {
+    fn new(
+        output_dir: &'a Path,
+        options: &'a DisassembleOptions,
+        to_stderr: bool,
+        progress: Option<&'p mut dyn ProgressSink>,
+    ) -> Self {
+        Self {
+            output_dir,
+            options,
+            to_stderr,
+            progress,
+            file_counter: 0,
+            written_paths: Vec::new(),
+            extracted: Vec::new(),
+            claimed: BTreeMap::new(),
+            artifacts: Vec::new(),
+            gzip_digests: BTreeMap::new(),
+            references: Vec::new(),
+            shared_digests: BTreeSet::new(),
+            predecoded: HashMap::new(),
+            pending_writes: Vec::new(),
+            preexisting: BTreeSet::new(),
+            total_bytes: 0,
+        }
+    }
+
+    /// Decode an inline `data:` source for the ignition `path`, write it under
+    /// the output directory and return the placeholder that replaces it.
+    ///
+    /// Array entries (`entry` names the field and index) are written to
+    /// `<path>/<index>`.
+    /// Content whose sibling `compression` is `gzip` is written inflated; the
+    /// field stays next to the placeholder so assembly can compress it again.
+    /// Sources that aren't `data:` URLs, and those `--include`/`--exclude`
+    /// leave inline, are returned unchanged.
+    fn extract(
+        &mut self,
+        source_str: &str,
+        compression: Option<&str>,
+        path: &str,
+        entry: Option<(&str, usize)>,
+    ) -> Result<String> {
+        if !source_str.starts_with("data:")
+            || !path_filter::is_selected(path, &self.options.include, &self.options.exclude)
+        {
+            return Ok(source_str.to_string());
+        }
+
+        let gzipped = match compression.unwrap_or("") {
+            "" => false,
+            "gzip" => true,
+            other => anyhow::bail!("Unsupported compression '{}' for {}", other, path),
+        };
+
+        let url = data_url::DataUrl::process(source_str).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to parse data URL for {} ({:?}): {}",
+                path,
+                e,
+                source_snippet(source_str)
+            )
+        })?;
+        // The placeholder keeps the media type as written, since the parser
+        // normalizes it and replaces ones it can't parse with the default
+        let parsed_media_type = url.mime_type().to_string();
+        let media_type = verbatim_media_type(source_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| parsed_media_type.clone());
+
+        // Streamed content is decoded while writing instead of here
+        let streamed_payload = if self.options.chunked_read
+            && !self.options.parallel
+            && self.options.transform_cmd.is_none()
+            && self.options.digest.is_none()
+            && !self.options.dedup
+            && !gzipped
+            && !self.options.config_only
+            && !self.options.dry_run
+            && !path.trim_start_matches('/').is_empty()
+        {
+            streaming::base64_payload(source_str)
+        } else {
+            None
+        };
+        let predecoded = self.predecoded.remove(source_str);
+        let base64url = streaming::base64url_payload(source_str);
+        let mut decoded_content = match (streamed_payload, predecoded) {
+            (Some(_), _) => Vec::new(),
+            (None, Some(content)) => content,
+            (None, None) if base64url.is_some() => {
+                use base64::Engine;
+                streaming::base64_engine(true)
+                    .decode(base64url.unwrap_or_default())
+                    .map_err(|e| {
+                        anyhow::anyhow!(
+                            "Failed to decode base64url body for {} ({}): {}",
+                            path,
+                            e,
+                            source_snippet(source_str)
+                        )
+                    })?
+            }
+            (None, None) => {
+                url.decode_to_vec()
+                    .map_err(|e| {
+                        anyhow::anyhow!(
+                            "Failed to decode base64 body for {} ({:?}): {}",
+                            path,
+                            e,
+                            source_snippet(source_str)
+                        )
+                    })?
+                    .0
+            }
+        };
+
+        let gzip_digest =
+            gzipped.then(|| verification::HashAlgorithm::Sha256.hash(&decoded_content));
+        if gzipped {
+            use std::io::Read;
+            let mut inflated = Vec::new();
+            flate2::read::GzDecoder::new(decoded_content.as_slice())
+                .read_to_end(&mut inflated)
+                .with_context(|| format!("Failed to decompress gzip content for {}", path))?;
+            decoded_content = inflated;
+        }
+
+        // Identical content of several sources, before any transform changes it
+        let shared = self.options.dedup && {
+            use sha2::Digest;
+            let digest = verification::to_hex(&sha2::Sha256::digest(&decoded_content));
+            self.shared_digests.contains(&digest)
+        };
+
+        let relative_path = path.trim_start_matches("/");
+        checks::ensure_contained(relative_path, path)?;
+
+        // Handle empty path by providing a default filename based on the content
+        let effective_path = if relative_path.is_empty() {
+            let mut attempt = 0;
+            let mut name = generated_file_name(&parsed_media_type, &decoded_content, attempt);
+            while self.claimed.contains_key(&name) {
+                attempt += 1;
+                name = generated_file_name(&parsed_media_type, &decoded_content, attempt);
+            }
+            name
+        } else {
+            relative_path.to_string()
+        };
+
+        if let Some(program) = &self.options.transform_cmd {
+            decoded_content =
+                transform::run_transform(program, &decoded_content, path, Direction::Decode)?;
+        }
+
+        // Array entries become indexed files inside a directory named after the path
+        let placeholder_path = match (self.options.digest, entry) {
+            (Some(digest), _) => digest.blob_path(&decoded_content),
+            (None, _) if shared => ContentDigest::Sha256.blob_path(&decoded_content),
+            (None, Some((_, index))) => format!("{}/{}", effective_path, index),
+            (None, None) => effective_path,
+        };
+        let source_name = match entry {
+            Some((field, index)) => format!("{}[{}] of {}", field, index, path),
+            None => format!("source of {}", path),
+        };
+
+        let size = self.write_file(
+            &placeholder_path,
+            decoded_content,
+            streamed_payload,
+            &media_type,
+            path,
+            &source_name,
+        )?;
+        self.artifacts.push(manifest::Artifact {
+            ignition_path: path.to_string(),
+            field: match entry {
+                Some((field, index)) => format!("{}[{}]", field, index),
+                None => "contents".to_string(),
+            },
+            file: placeholder_path.clone(),
+            media_type: media_type.clone(),
+            compression: gzipped.then(|| "gzip".to_string()),
+            size,
+        });
+
+        if let Some(digest) = gzip_digest {
+            self.gzip_digests.insert(placeholder_path.clone(), digest);
+        }
+        self.file_counter += 1;
+
+        // Replace the source with a placeholder holding the relative file
+        // path, and the alphabet when it isn't the standard one
+        let alphabet = if base64url.is_some() {
+            streaming::BASE64URL_PARAMETER
+        } else {
+            ""
+        };
+        Ok(format!(
+            "data:{}{};base64-placeholder,{}",
+            media_type, alphabet, placeholder_path
+        ))
+    }
+
+    /// Write `content` to `relative` in the output directory, honoring the
+    /// overwrite mode, `--config-only` and `--dry-run`. `streamed_payload`
+    /// replaces `content` with base64 decoded while writing.
+    fn write_file(
+        &mut self,
+        relative: &str,
+        content: Vec<u8>,
+        streamed_payload: Option<&str>,
+        media_type: &str,
+        ignition_path: &str,
+        source_name: &str,
+    ) -> Result<usize> {
+        let mut size = match streamed_payload {
+            Some(payload) => streaming::decoded_len(payload),
+            None => content.len(),
+        };
+
+        // An existing blob already holds exactly this content
+        let is_blob = (self.options.digest.is_some() || self.options.dedup)
+            && relative.starts_with(&format!("{}/", digest::BLOBS_DIR));
+        if !is_blob {
+            self.claim(relative, source_name)?;
+        }
+
+        if self.options.config_only {
+            // Only the placeholder is produced, but the content still counts
+            self.total_bytes += content.len() as u64;
+        } else {
+            // Create the output file
+            let file_path = self.output_dir.join(relative);
+            // A dry run still sees the files --action replace would remove
+            let cleared = self.options.dry_run && self.options.action == Action::Replace;
+            // Symlinks left in the tree, such as extracted links, are not written through
+            if let (Some(parent), false) = (Path::new(relative).parent(), cleared) {
+                checks::ensure_no_symlinks_below(self.output_dir, parent)?;
+            }
+            if let (Some(parent), false) = (file_path.parent(), self.options.dry_run) {
+                fs::create_dir_all(parent)?;
+            }
+
+            if !cleared {
+                checks::ensure_regular_target(&file_path)?;
+            }
+            let conflict = !is_blob && !cleared && file_path.exists();
+            if conflict && self.options.overwrite_mode == OverwriteMode::Error {
+                anyhow::bail!(
+                    "Refusing to overwrite existing file: {}",
+                    file_path.display()
+                );
+            }
+
+            if conflict && self.options.overwrite_mode == OverwriteMode::Skip {
+                status(
+                    self.to_stderr,
+                    &format!("Keeping existing file: {}", file_path.display()),
+                );
+            } else if self.options.dry_run {
+                report_dry_write(&file_path, cleared, self.to_stderr);
+                self.total_bytes += content.len() as u64;
+            } else {
+                if file_path.exists() {
+                    self.preexisting.insert(file_path.clone());
+                }
+                let event = FileEvent {
+                    ignition_path: ignition_path.to_string(),
+                    file: file_path,
+                    media_type: media_type.to_string(),
+                    size,
+                };
+                match streamed_payload {
+                    Some(payload) => {
+                        size = streaming::stream_base64_to_file(payload, &event.file)?;
+                    }
+                    // Recorded once the write has happened
+                    None if self.options.parallel => {
+                        self.total_bytes += size as u64;
+                        self.pending_writes.push(PendingWrite { event, content });
+                        return Ok(self.finish_claim(relative, media_type, source_name, size));
+                    }
+                    None => fs::write(&event.file, content)?,
+                }
+
+                self.total_bytes += size as u64;
+
+                if self.options.set_xattrs {
+                    xattrs::set(&event.file, media_type, ignition_path)?;
+                }
+                self.record_written(FileEvent { size, ..event });
+            }
+        }
+
+        Ok(self.finish_claim(relative, media_type, source_name, size))
+    }
+
+    /// Enter `relative` into the decoded tree as written for `source_name`,
+    /// passing `size` through
+    fn finish_claim(
+        &mut self,
+        relative: &str,
+        media_type: &str,
+        source_name: &str,
+        size: usize,
+    ) -> usize {
+        self.extracted
+            .push((relative.to_string(), media_type.to_string()));
+        self.claimed
+            .insert(relative.to_string(), source_name.to_string());
+        size
+    }
+
+    /// Record a file of the decoded tree that has been written
+    fn record_written(&mut self, event: FileEvent) {
+        if let Some(progress) = self.progress.as_deref_mut() {
+            progress.on_file_extracted(&event);
+        }
+        self.written_paths.push(event.file);
+    }
+
+    /// Fail when `relative` is already written for another source, or is a
+    /// file where another source needs a directory or the other way round,
+    /// as with a file's `contents` at `etc/foo` and its `append` entries at
+    /// `etc/foo/<index>`
+    fn claim(&self, relative: &str, source_name: &str) -> Result<()> {
+        let below = format!("{}/", relative);
+        let conflict = self
+            .claimed
+            .get_key_value(relative)
+            .or_else(|| {
+                self.claimed
+                    .range(below.clone()..)
+                    .next()
+                    .filter(|(path, _)| path.starts_with(&below))
+            })
+            .or_else(|| {
+                relative
+                    .match_indices('/')
+                    .find_map(|(end, _)| self.claimed.get_key_value(&relative[..end]))
+            });
+        if let Some((path, other)) = conflict {
+            anyhow::bail!(
+                "{} ({}) collides with {} ({}) in the output directory",
+                source_name,
+                relative,
+                other,
+                path
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Extractor<'_, '_> {
+    /// Decode every inline source of `config` on the thread pool, for the
+    /// walk to pick up instead of decoding them one by one. Sources that fail
+    /// to decode are left for the walk to report.
+    fn predecode(&mut self, config: &serde_json::Value) -> Result<()> {
+        use rayon::prelude::*;
+        let mut collector = InlineSources {
+            options: self.options,
+            sources: BTreeSet::new(),
+        };
+        find_and_replace_source_with_path_update(&mut config.clone(), "", "", &mut collector)?;
+        self.predecoded = collector
+            .sources
+            .into_par_iter()
+            .filter_map(|source| {
+                let content = verification::decode_data_url(&source).ok()?;
+                Some((source, content))
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Write the files `--parallel` held back on the thread pool, recording
+    /// each one that was written and failing with the first error. Blobs
+    /// queued more than once are written once.
+    fn flush_writes(&mut self) -> Result<()> {
+        use rayon::prelude::*;
+        let mut pending = std::mem::take(&mut self.pending_writes);
+        pending.sort_by(|a, b| a.event.file.cmp(&b.event.file));
+        pending.dedup_by(|a, b| a.event.file == b.event.file);
+        let set_xattrs = self.options.set_xattrs;
+        let results: Vec<Result<()>> = pending
+            .par_iter()
+            .map(|write| {
+                let event = &write.event;
+                fs::write(&event.file, &write.content)
+                    .with_context(|| format!("Failed to write {}", event.file.display()))?;
+                if set_xattrs {
+                    xattrs::set(&event.file, &event.media_type, &event.ignition_path)?;
+                }
+                Ok(())
+            })
+            .collect();
+
+        let mut first_error = None;
+        for (write, result) in pending.into_iter().zip(results) {
+            match result {
+                Ok(()) => self.record_written(write.event),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+/// A file `--parallel` writes after the walk, with the event reported once
+/// it is written
+struct PendingWrite {
+    event: FileEvent,
+    content: Vec<u8>,
+}
+
+/// Visitor collecting the distinct inline sources of a config that
+/// `--include` and `--exclude` select
+struct InlineSources<'a> {
+    options: &'a DisassembleOptions,
+    sources: BTreeSet<String>,
+}
+
+impl SourceVisitor for InlineSources<'_> {
+    fn visit(
+        &mut self,
+        source: &str,
+        _compression: Option<&str>,
+        path: &str,
+        _entry: Option<(&str, usize)>,
+    ) -> Result<String> {
+        if source.starts_with("data:")
+            && path_filter::is_selected(path, &self.options.include, &self.options.exclude)
+        {
+            self.sources.insert(source.to_string());
+        }
+        Ok(source.to_string())
+    }
+
+    fn strict_array_detection(&self) -> bool {
+        self.options.strict_array_detection
+    }
+}
+
+impl SourceVisitor for Extractor<'_, '_> {
+    fn visit(
+        &mut self,
+        source: &str,
+        compression: Option<&str>,
+        path: &str,
+        entry: Option<(&str, usize)>,
+    ) -> Result<String> {
+        self.extract(source, compression, path, entry)
+    }
+
+    fn visit_remote(
+        &mut self,
+        resource: &serde_json::Map<String, serde_json::Value>,
+        path: &str,
+        entry: Option<(&str, usize)>,
+    ) {
+        self.references
+            .push(references::reference(resource, path, entry));
+    }
+
+    fn strict_array_detection(&self) -> bool {
+        self.options.strict_array_detection
+    }
+}
+
+/// The media type of a `data:` URL exactly as written, parameters included;
+/// `None` when the URL leaves out the type and the `text/plain` default
+/// applies
+fn verbatim_media_type(source: &str) -> Option<&str> {
+    let header = source.strip_prefix("data:")?.split(',').next()?;
+    let media_type = match header.len().checked_sub(";base64".len()) {
+        Some(at) if header.is_char_boundary(at) && header[at..].eq_ignore_ascii_case(";base64") => {
+            &header[..at]
+        }
+        _ => header,
+    };
+    let essence = media_type.split(';').next().unwrap_or_default().trim();
+    (!essence.is_empty()).then_some(media_type)
+}
+
+/// Name given to an extracted source whose path is empty, derived from its
+/// content so it doesn't depend on the source's position, with an extension
+/// based on the media type. `attempt` is bumped while a name is taken by
+/// other content, or by the same content of another source.
+fn generated_file_name(media_type: &str, content: &[u8], attempt: usize) -> String {
+    use sha2::Digest;
+    let digest = verification::to_hex(&sha2::Sha256::digest(content));
+    let suffix = match attempt {
+        0 => String::new(),
+        n => format!("-{}", n),
+    };
+    format!(
+        "extracted_{}{}.{}",
+        &digest[..GENERATED_NAME_DIGEST_LEN],
+        suffix,
+        media_type_extension(media_type)
+    )
+}
+
+/// Hex digits of the content's SHA-256 in generated file names
+const GENERATED_NAME_DIGEST_LEN: usize = 12;
+
+/// File extension for content of `media_type`: a known one, else the MIME
+/// subtype (`application/wasm` -> `wasm`) when it makes a plausible
+/// extension, else `data`
+fn media_type_extension(media_type: &str) -> String {
+    let essence = media_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let known = match essence.as_str() {
+        "text/plain" => Some("data"),
+        "application/json" => Some("json"),
+        "application/yaml" | "text/yaml" | "application/x-yaml" | "text/x-yaml" => Some("yaml"),
+        "application/xml" | "text/xml" => Some("xml"),
+        "text/html" => Some("html"),
+        "application/javascript" | "text/javascript" => Some("js"),
+        "text/css" => Some("css"),
+        "application/octet-stream" => Some("bin"),
+        "application/x-pem-file" => Some("pem"),
+        "application/x-x509-ca-cert" | "application/pkix-cert" => Some("crt"),
+        "application/pgp-keys" => Some("asc"),
+        "application/gzip" | "application/x-gzip" => Some("gz"),
+        "application/x-tar" => Some("tar"),
+        "application/x-sh" | "text/x-shellscript" => Some("sh"),
+        "application/toml" => Some("toml"),
+        "image/jpeg" => Some("jpg"),
+        "image/svg+xml" => Some("svg"),
+        _ => None,
+    };
+    if let Some(extension) = known {
+        return extension.to_string();
+    }
+
+    // `vnd.example+json` -> `json`, `x-foo` -> `foo`
+    let subtype = essence.split_once('/').map(|(_, s)| s).unwrap_or_default();
+    let subtype = subtype.rsplit('+').next().unwrap_or_default();
+    let subtype = subtype.strip_prefix("x-").unwrap_or(subtype);
+    let plausible =
+        (1..=8).contains(&subtype.len()) && subtype.chars().all(|c| c.is_ascii_alphanumeric());
+    if plausible {
+        subtype.to_string()
+    } else {
+        "data".to_string()
+    }
+}
+
+/// The start of a source, short enough to quote in an error message
+fn source_snippet(source: &str) -> String {
+    const MAX_CHARS: usize = 48;
+    match source.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => format!("{}...", &source[..end]),
+        None => source.to_string(),
+    }
+}
+
+/// Extract each top-level section on its own. A section that fails is put
+/// back as it was, the files it created are removed along with its
+/// references and gzip digests, and its error is returned keyed by section
+/// name. Files it overwrote are left in place.
+fn extract_sections_isolated(
+    json_value: &mut serde_json::Value,
+    extractor: &mut Extractor,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut skipped = serde_json::Map::new();
+    let Some(sections) = json_value.as_object_mut() else {
+        return skipped;
+    };
+
+    for (section, value) in sections.iter_mut() {
+        let original = value.clone();
+        let written_before = extractor.written_paths.len();
+        let extracted_before = extractor.extracted.len();
+        let artifacts_before = extractor.artifacts.len();
+        let counter_before = extractor.file_counter;
+        let bytes_before = extractor.total_bytes;
+        let pending_before = extractor.pending_writes.len();
+        let references_before = extractor.references.len();
+        let gzip_digests_before = extractor.gzip_digests.clone();
+
+        let pointer = pointer_push("", section);
+        if let Err(e) = find_and_replace_source_with_path_update(value, "", &pointer, extractor) {
+            for path in extractor.written_paths.drain(written_before..) {
+                if !extractor.preexisting.contains(&path) {
+                    let _ = fs::remove_file(path);
+                }
+            }
+            for (path, _) in extractor.extracted.drain(extracted_before..) {
+                extractor.claimed.remove(&path);
+            }
+            extractor.artifacts.truncate(artifacts_before);
+            extractor.pending_writes.truncate(pending_before);
+            extractor.references.truncate(references_before);
+            extractor.gzip_digests = gzip_digests_before;
+            extractor.file_counter = counter_before;
+            extractor.total_bytes = bytes_before;
+            *value = original;
+
+            warn(&format!("skipped section {}: {:#}", section, e));
+            skipped.insert(
+                section.clone(),
+                serde_json::Value::String(format!("{:#}", e)),
+            );
+        }
+    }
+
+    skipped
+}
+
+/// Walk `value` handing every source to `visitor`. `path` is the Ignition
+/// path of the enclosing entry and `pointer` the JSON pointer of `value`,
+/// which errors of the sources below it name.
+fn find_and_replace_source_with_path_update<V: SourceVisitor + ?Sized>(
+    value: &mut serde_json::Value,
+    path: &str,
+    pointer: &str,
+    visitor: &mut V,
+) -> Result<()> {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut new_path = path.to_string();
+            if let Some(p) = map.get("path").and_then(|v| v.as_str()) {
+                new_path = p.to_string();
+            }
+            let compression = sibling_compression(map);
+            if references::is_remote(map) {
+                visitor.visit_remote(map, &new_path, None);
+            }
+
+            // Check if this object has both a path and array fields with sources
+            let has_path = map.contains_key("path");
+            let strict = visitor.strict_array_detection();
+            let mut found_array_with_sources = false;
+
+            if has_path {
+                // Look for array fields that contain objects with sources
+                for (key, val) in map.iter() {
+                    if let serde_json::Value::Array(arr) = val {
+                        let with_source = count_sources(arr);
+                        if with_source > 0 && with_source < arr.len() {
+                            warn(&format!(
+                                "{} of {} entries of {} in {} have a source; {}",
+                                with_source,
+                                arr.len(),
+                                key,
+                                new_path,
+                                if strict {
+                                    "walking it as a nested structure"
+                                } else {
+                                    "treating it as a list of sources"
+                                }
+                            ));
+                        }
+                        found_array_with_sources |= is_source_array(arr, strict);
+                    }
+                }
+            }
+
+            for (key, val) in map.iter_mut() {
+                let child = pointer_push(pointer, key);
+                if key == "source" {
+                    if let Some(source_str) = val.as_str() {
+                        let new_source = visitor
+                            .visit(source_str, compression.as_deref(), &new_path, None)
+                            .with_context(|| format!("at {}", child))?;
+                        *val = serde_json::Value::String(new_source);
+                    }
+                } else if found_array_with_sources && key != "path" {
+                    // This might be an array field with sources
+                    if let serde_json::Value::Array(arr) = val {
+                        let has_sources = is_source_array(arr, strict);
+
+                        if has_sources {
+                            // Process as array of sources
+                            for (index, item) in arr.iter_mut().enumerate() {
+                                process_array_item_sources_with_path_update(
+                                    item,
+                                    &new_path,
+                                    &pointer_push(&child, &index.to_string()),
+                                    (key, index),
+                                    visitor,
+                                )?;
+                            }
+                        } else {
+                            // Recursively process nested structures
+                            find_and_replace_source_with_path_update(
+                                val, &new_path, &child, visitor,
+                            )?;
+                        }
+                    } else {
+                        // Recursively process other nested structures
+                        find_and_replace_source_with_path_update(val, &new_path, &child, visitor)?;
+                    }
+                } else {
+                    // Recursively process nested objects and arrays
+                    find_and_replace_source_with_path_update(val, &new_path, &child, visitor)?;
+                }
+            }
+        }
+        serde_json::Value::Array(arr) => {
+            for (index, val) in arr.iter_mut().enumerate() {
+                let child = pointer_push(pointer, &index.to_string());
+                find_and_replace_source_with_path_update(val, path, &child, visitor)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Number of entries of `arr` that are objects with a non-null `source`
+fn count_sources(arr: &[serde_json::Value]) -> usize {
+    arr.iter()
+        .filter(|item| item.get("source").is_some_and(|s| !s.is_null()))
+        .count()
+}
+
+/// Whether `arr` is walked as a list of sources, each extracted to
+/// `<path>/<index>`: some entry must have a source, or every entry when
+/// `strict` is set
+fn is_source_array(arr: &[serde_json::Value], strict: bool) -> bool {
+    let with_source = count_sources(arr);
+    with_source > 0 && (!strict || with_source == arr.len())
+}
+
+/// The `compression` declared next to a `source`
+fn sibling_compression(map: &serde_json::Map<String, serde_json::Value>) -> Option<String> {
+    map.get("compression")
+        .and_then(|c| c.as_str())
+        .map(str::to_string)
+}
+
+fn process_array_item_sources_with_path_update<V: SourceVisitor + ?Sized>(
+    item: &mut serde_json::Value,
+    path: &str,
+    pointer: &str,
+    entry: (&str, usize),
+    visitor: &mut V,
+) -> Result<()> {
+    if let serde_json::Value::Object(map) = item {
+        let compression = sibling_compression(map);
+        if references::is_remote(map) {
+            visitor.visit_remote(map, path, Some(entry));
+        }
+        for (key, val) in map.iter_mut() {
+            let child = pointer_push(pointer, key);
+            if key == "source" {
+                if let Some(source_str) = val.as_str() {
+                    let new_source = visitor
+                        .visit(source_str, compression.as_deref(), path, Some(entry))
+                        .with_context(|| format!("at {}", child))?;
+                    *val = serde_json::Value::String(new_source);
+                }
+            } else {
+                // Recursively process nested structures
+                process_nested_sources_with_path_update(val, path, &child, entry, visitor)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn process_nested_sources_with_path_update<V: SourceVisitor + ?Sized>(
+    value: &mut serde_json::Value,
+    path: &str,
+    pointer: &str,
+    entry: (&str, usize),
+    visitor: &mut V,
+) -> Result<()> {
+    match value {
+        serde_json::Value::Object(map) => {
+            let compression = sibling_compression(map);
+            if references::is_remote(map) {
+                visitor.visit_remote(map, path, Some(entry));
+            }
+            for (key, val) in map.iter_mut() {
+                let child = pointer_push(pointer, key);
+                if key == "source" {
+                    if let Some(source_str) = val.as_str() {
+                        let new_source = visitor
+                            .visit(source_str, compression.as_deref(), path, Some(entry))
+                            .with_context(|| format!("at {}", child))?;
+                        *val = serde_json::Value::String(new_source);
+                    }
+                } else {
+                    process_nested_sources_with_path_update(val, path, &child, entry, visitor)?;
+                }
+            }
+        }
+        serde_json::Value::Array(arr) => {
+            for (index, val) in arr.iter_mut().enumerate() {
+                let child = pointer_push(pointer, &index.to_string());
+                process_nested_sources_with_path_update(val, path, &child, entry, visitor)?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::checks;
+use crate::rootfs::create_symlink;
+use crate::verbosity::{status, warn};
+
+/// Sidecar at the root of a decoded directory marking it as holding
+/// `storage.links` as symlinks, with the attributes a symlink can't carry
+pub const LINKS_FILE: &str = "links.json";
+
+/// `storage.links` attributes kept in the sidecar
+const LINK_FIELDS: &[&str] = &["hard", "user", "group", "overwrite"];
+
+/// Attributes of each link materialized as a symlink, keyed by its absolute
+/// path
+pub type LinkMetadata = BTreeMap<String, Map<String, Value>>;
+
+/// Move the `storage.links` entries of `config` into the decoded tree as
+/// symlinks to their `target`, so `/etc/foo` becomes `<output_dir>/etc/foo`.
+///
+/// A link whose path collides with an extracted file (`extracted` holds
+/// their relative paths), or with anything in the tree that is not a
+/// symlink, stays in `config` with a warning. So does a link below one
+/// already moved: the symlink would be created through it, and assembly
+/// doesn't descend into symlinks to find it again. Returns the attributes
+/// of the links moved. A dry run reports the symlinks instead of creating them, on
+/// stderr with `to_stderr`.
+pub fn extract(
+    config: &mut Value,
+    output_dir: &Path,
+    extracted: &[String],
+    dry_run: bool,
+    to_stderr: bool,
+) -> Result<LinkMetadata> {
+    let mut moved = LinkMetadata::new();
+    let Some(links) = config
+        .pointer_mut("/storage/links")
+        .and_then(|l| l.as_array_mut())
+    else {
+        return Ok(moved);
+    };
+
+    let mut kept = Vec::new();
+    for link in links.drain(..) {
+        let path = link.get("path").and_then(|p| p.as_str());
+        let target = link.get("target").and_then(|t| t.as_str());
+        let (Some(path), Some(target)) = (path, target) else {
+            kept.push(link);
+            continue;
+        };
+        let relative = path.trim_start_matches('/');
+        checks::ensure_contained(relative, path)?;
+        let link_path = output_dir.join(relative);
+
+        let nested = moved
+            .keys()
+            .find(|moved_path| path.starts_with(&format!("{}/", moved_path)));
+        if let Some(parent_link) = nested {
+            warn(&format!(
+                "keeping link {} in decoded.ign, its path is below link {}",
+                path, parent_link
+            ));
+            kept.push(link);
+            continue;
+        }
+
+        // Nothing above the link may lead out of the tree
+        if let Some(parent) = Path::new(relative).parent() {
+            checks::ensure_no_symlinks_below(output_dir, parent)?;
+        }
+        if let Some(collision) = collision(relative, extracted, &link_path) {
+            warn(&format!(
+                "keeping link {} in decoded.ign, its path collides with {}",
+                path, collision
+            ));
+            kept.push(link);
+            continue;
+        }
+
+        if dry_run {
+            status(
+                to_stderr,
+                &format!("Would link {} -> {}", link_path.display(), target),
+            );
+        } else {
+            if let Some(parent) = link_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // A symlink left by an earlier run is replaced
+            if link_path.is_symlink() {
+                fs::remove_file(&link_path)?;
+            }
+            create_symlink(target, &link_path)?;
+        }
+
+        let fields = LINK_FIELDS
+            .iter()
+            .filter_map(|field| {
+                link.get(*field)
+                    .filter(|v| !v.is_null())
+                    .map(|v| (field.to_string(), v.clone()))
+            })
+            .collect();
+        moved.insert(path.to_string(), fields);
+    }
+    *links = kept;
+    Ok(moved)
+}
+
+/// What the link at `relative` would collide with: an extracted file at,
+/// above or below it, or a non-symlink already at `link_path`
+fn collision(relative: &str, extracted: &[String], link_path: &Path) -> Option<String> {
+    let claimed = extracted.iter().find(|file| {
+        *file == relative
+            || file.starts_with(&format!("{}/", relative))
+            || relative.starts_with(&format!("{}/", file))
+    });
+    if let Some(file) = claimed {
+        return Some(format!("extracted file {}", file));
+    }
+    fs::symlink_metadata(link_path)
+        .ok()
+        .filter(|m| !m.file_type().is_symlink())
+        .map(|_| link_path.display().to_string())
+}
+
+/// Write `links` to `links.json` in `output_dir`
+pub fn write(links: &LinkMetadata, output_dir: &Path) -> Result<PathBuf> {
+    let path = output_dir.join(LINKS_FILE);
+    fs::write(&path, serde_json::to_string_pretty(links)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Read `links.json` from a decoded directory, if there is one
+pub fn read(dir: &Path) -> Result<Option<LinkMetadata>> {
+    let path = dir.join(LINKS_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let links = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(links))
+}
+
+/// Turn every symlink below `files_dir` back into a `storage.links` entry
+/// with the symlink's target and the attributes recorded in `links`, in
+/// path order after the links decoded.ign already has. Symlinks whose path
+/// decoded.ign already links are left to it.
+pub fn restore(config: &mut Value, files_dir: &Path, links: &LinkMetadata) -> Result<()> {
+    let mut symlinks = Vec::new();
+    find_symlinks(files_dir, files_dir, &mut symlinks)?;
+    if symlinks.is_empty() {
+        return Ok(());
+    }
+
+    if !config["storage"].is_object() {
+        config["storage"] = Value::Object(Map::new());
+    }
+    let storage = &mut config["storage"];
+    if !storage["links"].is_array() {
+        storage["links"] = Value::Array(Vec::new());
+    }
+    let Some(entries) = storage["links"].as_array_mut() else {
+        return Ok(());
+    };
+
+    for relative in symlinks {
+        let components: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        let path = format!("/{}", components.join("/"));
+        if entries.iter().any(|e| e["path"] == path.as_str()) {
+            continue;
+        }
+        let link_path = files_dir.join(&relative);
+        let target = fs::read_link(&link_path)
+            .with_context(|| format!("Failed to read symlink {}", link_path.display()))?;
+
+        let mut entry = Map::new();
+        entry.insert("path".to_string(), Value::String(path.clone()));
+        entry.insert(
+            "target".to_string(),
+            Value::String(target.to_string_lossy().into_owned()),
+        );
+        if let Some(fields) = links.get(&path) {
+            entry.extend(fields.clone());
+        }
+        entries.push(Value::Object(entry));
+    }
+    Ok(())
+}
+
+/// Collect the paths of symlinks below `dir`, relative to `root`, without
+/// following them
+fn find_symlinks(root: &Path, dir: &Path, symlinks: &mut Vec<PathBuf>) -> Result<()> {
+    let mut children: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<_>>()?;
+    children.sort();
+    for path in children {
+        let file_type = fs::symlink_metadata(&path)?.file_type();
+        if file_type.is_symlink() {
+            symlinks.push(path.strip_prefix(root)?.to_path_buf());
+        } else if file_type.is_dir() {
+            find_symlinks(root, &path, symlinks)?;
+        }
+    }
+    Ok(())
+}
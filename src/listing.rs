@@ -0,0 +1,99 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::{find_and_replace_source_with_path_update, generated_file_name, SourceVisitor};
+
+/// An inline source as `disassemble` would extract it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListedSource {
+    /// Ignition path the source belongs to
+    pub path: String,
+    /// Array field and index for entries of lists such as `append`
+    pub entry: Option<(String, usize)>,
+    pub media_type: String,
+    /// Size after decoding and decompression
+    pub size: usize,
+    pub compression: Option<String>,
+    /// File name generated for a source whose path is empty
+    pub generated_name: Option<String>,
+}
+
+impl fmt::Display for ListedSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.path.is_empty() {
+            "(no path)"
+        } else {
+            &self.path
+        })?;
+        if let Some((field, index)) = &self.entry {
+            write!(f, " {}[{}]", field, index)?;
+        }
+        write!(f, "  {}  {} bytes", self.media_type, self.size)?;
+        if let Some(compression) = &self.compression {
+            write!(f, "  {}-compressed", compression)?;
+        }
+        if let Some(name) = &self.generated_name {
+            write!(f, "  -> {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Visitor recording each inline source instead of extracting it
+#[derive(Default)]
+struct Lister {
+    sources: Vec<ListedSource>,
+}
+
+impl SourceVisitor for Lister {
+    fn visit(
+        &mut self,
+        source: &str,
+        compression: Option<&str>,
+        path: &str,
+        entry: Option<(&str, usize)>,
+    ) -> Result<String> {
+        let resource = json!({ "source": source, "compression": compression });
+        let Some(content) = crate::verification::inline_content(&resource)
+            .map_err(|e| anyhow::anyhow!("{:#} for {}", e, path))?
+        else {
+            return Ok(source.to_string());
+        };
+
+        let media_type = data_url::DataUrl::process(source)
+            .map(|url| url.mime_type().to_string())
+            .unwrap_or_default();
+        let generated_name = path.trim_start_matches('/').is_empty().then(|| {
+            let mut attempt = 0;
+            let mut name = generated_file_name(&media_type, &content, attempt);
+            while self
+                .sources
+                .iter()
+                .any(|s| s.generated_name.as_ref() == Some(&name))
+            {
+                attempt += 1;
+                name = generated_file_name(&media_type, &content, attempt);
+            }
+            name
+        });
+
+        self.sources.push(ListedSource {
+            path: path.to_string(),
+            entry: entry.map(|(field, index)| (field.to_string(), index)),
+            media_type,
+            size: content.len(),
+            compression: compression.filter(|c| !c.is_empty()).map(str::to_string),
+            generated_name,
+        });
+        Ok(source.to_string())
+    }
+}
+
+/// Walk `config` the way disassembly does, returning its inline sources in
+/// the order they would be extracted
+pub fn collect(config: &Value) -> Result<Vec<ListedSource>> {
+    let mut lister = Lister::default();
+    find_and_replace_source_with_path_update(&mut config.clone(), "", "", &mut lister)?;
+    Ok(lister.sources)
+}
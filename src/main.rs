@@ -1,29 +1,37 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::{Parser, Subcommand};
-use ignition_config::Config;
-use std::fs;
+#[cfg(feature = "http")]
+use fcos_ignition_coder::diff_remote;
+use fcos_ignition_coder::{
+    assemble_ignition, convert_ignition, diff_ignition, disassemble_ignition,
+    disassemble_input_list, is_stdin, list_embedded, list_remotes, merge_decoded_dirs, run_fuzz,
+    set_verbosity, show_info, status, validate_ignition, Action, AssembleOptions, AssembleOrder,
+    ContentDigest, DisassembleOptions, Indent, NewlineMode, OverwriteMode, OwnershipPolicy,
+    PathGlob, PrintProgress, RemoteRewrite, Report, SplitLarge, SymlinkPolicy, Verbosity,
+    VersionRange, WarningKind, DECODED_IGN,
+};
 use std::path::{Path, PathBuf};
 
-#[cfg(test)]
-mod tests;
-
-/// Action to take when the target already exists
-#[derive(clap::ValueEnum, Clone, Debug)]
-enum Action {
-    /// Create new target (fail if it already exists) - default
-    New,
-    /// Add to or update existing target (merge/overwrite files)
-    Add,
-    /// Replace entire target (remove existing first)
-    Replace,
-}
-
 #[derive(Parser)]
 #[command(name = "fcos-ignition-coder")]
 #[command(about = "Decode and encode Fedora CoreOS Ignition configuration files")]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Parse and walk everything, printing the files that would be written
+    /// or overwritten, without changing the filesystem
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Print errors only, no status lines, progress or warnings
+    #[arg(long, short, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Also print every file and config written, with its size and media
+    /// type
+    #[arg(long, short, global = true)]
+    verbose: bool,
 }
 
 #[derive(Subcommand)]
@@ -31,20 +39,267 @@ enum Commands {
     /// Decode an Ignition file, extracting embedded files
     #[command(aliases = ["decode", "d", "div"])]
     Disassemble {
-        /// The ignition file to decode
-        ignition_file: PathBuf,
+        /// The ignition file to decode (`-` for stdin)
+        #[arg(required_unless_present = "input_list")]
+        ignition_file: Option<PathBuf>,
 
         /// The directory to place the decoded files in
-        target_dir: PathBuf,
+        #[arg(required_unless_present = "input_list")]
+        target_dir: Option<PathBuf>,
+
+        /// Decode every ignition file listed in this file (one path per
+        /// line, `-` for stdin), each into `<file>.decoded` next to it
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["ignition_file", "target_dir"])]
+        input_list: Option<PathBuf>,
+
+        /// Write decoded.ign as single-line JSON instead of pretty-printed
+        #[arg(long)]
+        compact: bool,
+
+        /// Indentation of pretty-printed JSON: a number of spaces or `tab`
+        /// (ignored with --compact)
+        #[arg(long, value_name = "N|tab", default_value_t = Indent::default())]
+        indent: Indent,
+
+        /// Emit JSON keys in Ignition's order (ignition, kernelArguments,
+        /// passwd, storage, systemd; versions, paths and names first in
+        /// nested objects) instead of alphabetically
+        #[arg(long)]
+        canonical_order: bool,
 
         /// Action to take with the target directory
         #[arg(long, default_value = "new")]
         action: Action,
+
+        /// What to do when an extracted file already exists in the target
+        /// directory (only possible with `--action add` or `--action merge`)
+        #[arg(long, value_enum, default_value = "overwrite")]
+        overwrite_mode: OverwriteMode,
+
+        /// Fail when a warning of this kind is reported (repeatable)
+        #[arg(long, value_enum)]
+        abort_on_warning: Vec<WarningKind>,
+
+        /// Materialize the config as a filesystem image (files at their
+        /// absolute paths with modes, ownership, directories and links)
+        /// instead of a decoded tree; no decoded.ign is written
+        #[arg(long)]
+        rootfs: bool,
+
+        /// Pipe each extracted file's content through this program (stdin to
+        /// stdout) before writing it
+        #[arg(long, value_name = "PROGRAM")]
+        transform_cmd: Option<PathBuf>,
+
+        /// Fail if a path is claimed by more than one of storage files,
+        /// directories and links
+        #[arg(long)]
+        verify_paths_unique: bool,
+
+        /// Fail, naming each offending path, if a file or directory mode is
+        /// outside 0 to 0o7777
+        #[arg(long)]
+        validate_modes: bool,
+
+        /// Fail, listing them, if the config has top-level keys its spec
+        /// version doesn't define (such as a misspelled `storag`) instead of
+        /// dropping them
+        #[arg(long)]
+        reject_unknown_sections: bool,
+
+        /// Fail, listing every mismatch, if the decoded (and decompressed)
+        /// content of an inline file source does not match its verification hash
+        #[arg(long)]
+        decode_verification_check: bool,
+
+        /// Fail on any parse warning, and instead of warning when an inline
+        /// source does not match its verification hash
+        #[arg(long)]
+        strict: bool,
+
+        /// Keep extracting the other top-level sections when one fails; the
+        /// failed sections stay inline in decoded.ign and are listed under
+        /// `_skipped_sections`
+        #[arg(long)]
+        keep_going: bool,
+
+        /// Produce byte-identical output across runs and machines. Currently
+        /// this sets the mtime of every written file and directory to
+        /// SOURCE_DATE_EPOCH (or the Unix epoch when unset)
+        #[arg(long)]
+        reproducible: bool,
+
+        /// Refuse input configs larger than this many bytes before reading them
+        #[arg(long, value_name = "BYTES")]
+        max_input_size: Option<u64>,
+
+        /// Decode systemd unit contents that are base64 (or gzipped and
+        /// base64) encoded into plain text, recording the encoding in
+        /// unit-encodings.json so assemble can restore it
+        #[arg(long)]
+        decode_unit_contents: bool,
+
+        /// Write each systemd unit's contents to units/<name> and each dropin
+        /// to units/<name>.d/<dropin>, leaving placeholders in decoded.ign
+        #[arg(long)]
+        extract_units: bool,
+
+        /// Group extracted units into subdirectories named after the target
+        /// their [Install] section hooks them into, e.g.
+        /// units/multi-user.target.wants/<name>; dropins follow their unit
+        #[arg(long, requires = "extract_units")]
+        split_units_dir: bool,
+
+        /// Write each passwd user's SSH authorized keys to
+        /// passwd/<user>/authorized_keys, one key per line, leaving
+        /// placeholders in decoded.ign
+        #[arg(long)]
+        extract_ssh_keys: bool,
+
+        /// Materialize storage.links as symlinks in the output directory,
+        /// keeping their other attributes in links.json for assemble
+        #[arg(long)]
+        extract_links: bool,
+
+        /// Print warnings repeated at least this many times once, with a count
+        #[arg(long, value_name = "N")]
+        quiet_warnings: Option<usize>,
+
+        /// The ignition file holds the config base64 encoded (and optionally
+        /// gzip compressed), as often stored in cloud metadata
+        #[arg(long)]
+        from_base64_file: bool,
+
+        /// Write a Containerfile snippet with a COPY line mapping each
+        /// extracted file to its absolute path in the config
+        #[arg(long, value_name = "FILE", conflicts_with = "rootfs")]
+        emit_dockerfile: Option<PathBuf>,
+
+        /// Write a .gitattributes into the target directory marking each
+        /// extracted file as text or binary based on its media type
+        #[arg(long, conflicts_with = "rootfs")]
+        emit_gitattributes: bool,
+
+        /// Write IGNITION_VERSION, EXTRACTED_FILE_COUNT, WARNING_COUNT and
+        /// TOTAL_BYTES to this file in .env format for CI
+        #[arg(long, value_name = "FILE", conflicts_with = "rootfs")]
+        emit_env_file: Option<PathBuf>,
+
+        /// Write an SBOM-style JSON listing each source's path, size,
+        /// sha256, media type, kind (inline or remote) and compression
+        #[arg(long, value_name = "FILE", conflicts_with = "rootfs")]
+        emit_sbom: Option<PathBuf>,
+
+        /// Write the extracted directory tree (names, sizes, file or
+        /// directory) to this file as nested JSON
+        #[arg(long, value_name = "FILE")]
+        output_tree_json: Option<PathBuf>,
+
+        /// Store extracted files content-addressed as blobs/<digest>-<hex>,
+        /// so identical contents are written once
+        #[arg(long, value_enum)]
+        digest: Option<ContentDigest>,
+
+        /// Write contents several sources share once, as blobs/sha256-<hex>,
+        /// pointing each of their placeholders at it
+        #[arg(long)]
+        dedup: bool,
+
+        /// Decode base64 sources straight to disk in chunks to bound memory
+        /// use; ignored with --transform-cmd, --digest and for sources
+        /// without a path, which need the whole content
+        #[arg(long)]
+        chunked_read: bool,
+
+        /// Decode and write the extracted files on all cores; the output is
+        /// the same as without it
+        #[arg(long)]
+        parallel: bool,
+
+        /// Only process the config if its spec version satisfies this range
+        /// (e.g. `>=3.3,<3.5`); otherwise report it and skip it
+        #[arg(long, value_name = "RANGE")]
+        version_range: Option<VersionRange>,
+
+        /// Fail before extracting anything unless the config's spec version
+        /// is exactly this one
+        #[arg(long, value_name = "X.Y.Z")]
+        expected_version: Option<String>,
+
+        /// TOML file mapping path globs to default mode, uid and gid, applied
+        /// to storage entries that don't specify them
+        #[arg(long, value_name = "TOML")]
+        ownership_policy: Option<PathBuf>,
+
+        /// Rewrite remote sources under OLD to the same path under NEW, e.g.
+        /// to point at a mirror (repeatable; the first match applies)
+        #[arg(long, value_name = "OLD=NEW")]
+        relativize_remote: Vec<RemoteRewrite>,
+
+        /// Only extract sources whose Ignition path matches this glob
+        /// (repeatable; `*` stays within one component, `**` spans them).
+        /// Other sources stay inline in decoded.ign
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<PathGlob>,
+
+        /// Leave sources whose Ignition path matches this glob inline in
+        /// decoded.ign (repeatable); wins over a matching --include
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<PathGlob>,
+
+        /// Fail unless exactly N files are extracted
+        #[arg(long, value_name = "N")]
+        assert_file_count: Option<usize>,
+
+        /// Only write decoded.ign (with placeholders) and the manifest; the
+        /// content files are decoded and counted but not written
+        #[arg(long, conflicts_with = "rootfs")]
+        config_only: bool,
+
+        /// Extract an array as indexed files (`<path>/<index>`) only when
+        /// every entry has a source, instead of when any entry does; mixed
+        /// arrays are reported either way
+        #[arg(long)]
+        strict_array_detection: bool,
+
+        /// Record each extracted file's media type and ignition path in the
+        /// `user.mime_type` and `user.ignition_path` extended attributes
+        /// (Linux only)
+        #[arg(long)]
+        set_xattrs: bool,
+
+        /// Give each extracted file the permission bits of its entry's
+        /// `mode`, without setuid, setgid and sticky (Unix only); files
+        /// whose entry has no mode keep the default one
+        #[arg(long)]
+        apply_mode: bool,
+
+        /// Merge these configs over ignition_file, in order and with
+        /// Ignition's merge semantics, and disassemble the result. The
+        /// manifest records which input each file came from
+        #[arg(long, value_name = "FILE", num_args = 1..)]
+        input_multiple: Vec<PathBuf>,
+
+        /// Keep the values of an existing manifest.json (and metadata.json),
+        /// including manual edits, and only add entries for new files; useful
+        /// with `--action add`
+        #[arg(long)]
+        no_clobber_manifest: bool,
+
+        /// File name of the decoded config written to target_dir
+        #[arg(long, value_name = "NAME", default_value = DECODED_IGN)]
+        ign_name: String,
+
+        /// Also write the mode, user, group, overwrite and compression of
+        /// each file to metadata.json. Assemble merges it back, and where it
+        /// disagrees with decoded.ign the metadata.json value wins
+        #[arg(long)]
+        metadata: bool,
     },
     /// Encode extracted files back into an Ignition file
     #[command(aliases = ["encode", "a", "prod"])]
     Assemble {
-        /// The file to write the encoded ignition to
+        /// The file to write the encoded ignition to (`-` for stdout)
         target_file: PathBuf,
 
         /// The directory containing the ignition file and file contents
@@ -54,749 +309,739 @@ enum Commands {
         #[arg(long)]
         compact: bool,
 
+        /// Indentation of pretty-printed JSON: a number of spaces or `tab`
+        /// (ignored with --compact)
+        #[arg(long, value_name = "N|tab", default_value_t = Indent::default())]
+        indent: Indent,
+
+        /// Emit JSON keys in Ignition's order (ignition, kernelArguments,
+        /// passwd, storage, systemd; versions, paths and names first in
+        /// nested objects) instead of alphabetically
+        #[arg(long)]
+        canonical_order: bool,
+
         /// Suppress fields that have default values
         #[arg(long)]
         default: bool,
 
+        /// Keep this field even at its default value under --default
+        /// (repeatable). `overwrite`, `append` and `enabled` are always
+        /// kept, since a default there overrides a merged parent config
+        #[arg(long, value_name = "KEY")]
+        keep_keys: Vec<String>,
+
         /// Action to take with the target file
         #[arg(long, default_value = "new")]
         action: Action,
-    },
-}
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+        /// Fail when a warning of this kind is reported (repeatable)
+        #[arg(long, value_enum)]
+        abort_on_warning: Vec<WarningKind>,
 
-    match cli.command {
-        Commands::Disassemble {
-            ignition_file,
-            target_dir,
-            action,
-        } => {
-            disassemble_ignition(&ignition_file, &target_dir, action)?;
-        }
-        Commands::Assemble {
-            target_file,
-            ignition_dir,
-            compact,
-            default,
-            action,
-        } => {
-            assemble_ignition(&target_file, &ignition_dir, compact, default, action)?;
-        }
-    }
+        /// Keep fields from the decoded config that the Ignition parser drops
+        #[arg(long, alias = "assemble-preserve-unknown")]
+        preserve_unknown: bool,
 
-    Ok(())
-}
+        /// Move sources larger than this many bytes out of the config into a
+        /// `<target>.split` directory, referencing them by URL
+        #[arg(long, value_name = "BYTES", requires = "split_base_url")]
+        split_large: Option<usize>,
 
-fn disassemble_ignition(input_path: &Path, output_dir: &Path, action: Action) -> Result<()> {
-    // Read the input Ignition file
-    let content = fs::read_to_string(input_path)
-        .with_context(|| format!("Failed to read input file: {}", input_path.display()))?;
+        /// Base URL the externalized sources will be uploaded to
+        #[arg(long, value_name = "URL", requires = "split_large")]
+        split_base_url: Option<String>,
 
-    // Parse the Ignition config using ignition-config crate
-    let (mut config, warnings) =
-        Config::parse_str(&content).with_context(|| "Failed to parse Ignition file")?;
+        /// Pipe each file's content through this program (stdin to stdout)
+        /// before embedding it
+        #[arg(long, value_name = "PROGRAM")]
+        transform_cmd: Option<PathBuf>,
 
-    // Print warnings if any
-    for warning in warnings {
-        eprintln!("Warning: {}", warning);
-    }
+        /// Produce byte-identical output across runs and machines. Currently
+        /// this sets the mtime of the written file to SOURCE_DATE_EPOCH (or
+        /// the Unix epoch when unset)
+        #[arg(long)]
+        reproducible: bool,
+
+        /// Refuse decoded configs larger than this many bytes before reading
+        /// them, and remote sources larger than this under --inline-remote
+        #[arg(long, value_name = "BYTES")]
+        max_input_size: Option<u64>,
+
+        /// Order of the storage files entries in the output
+        #[arg(long, value_enum, default_value = "source")]
+        assemble_order: AssembleOrder,
+
+        /// Print warnings repeated at least this many times once, with a count
+        #[arg(long, value_name = "N")]
+        quiet_warnings: Option<usize>,
+
+        /// Only process the config if its spec version satisfies this range
+        /// (e.g. `>=3.3,<3.5`); otherwise report it and skip it
+        #[arg(long, value_name = "RANGE")]
+        version_range: Option<VersionRange>,
+
+        /// TOML file mapping path globs to default mode, uid and gid, applied
+        /// to storage entries that don't specify them
+        #[arg(long, value_name = "TOML")]
+        ownership_policy: Option<PathBuf>,
+
+        /// Rewrite remote sources under OLD to the same path under NEW, e.g.
+        /// to point at a mirror (repeatable; the first match applies)
+        #[arg(long, value_name = "OLD=NEW")]
+        relativize_remote: Vec<RemoteRewrite>,
+
+        /// Fetch every http(s) source and embed it as a data URL, checking
+        /// its verification hash or adding one, for a self-contained config
+        #[arg(long)]
+        inline_remote: bool,
 
-    // Handle target directory based on action
-    if output_dir.exists() {
-        match action {
-            Action::New => {
-                anyhow::bail!(
-                    "Target directory already exists: {}. Use --action replace to overwrite or --action add to merge.",
-                    output_dir.display()
-                );
-            }
-            Action::Add => {
-                // Directory exists, we'll add/overwrite files within it
-                println!("Adding to existing directory: {}", output_dir.display());
-            }
-            Action::Replace => {
-                fs::remove_dir_all(output_dir).with_context(|| {
-                    format!(
-                        "Failed to remove existing target directory: {}",
-                        output_dir.display()
-                    )
-                })?;
-                println!("Replaced existing directory: {}", output_dir.display());
-            }
-        }
-    } else {
-        // Directory doesn't exist, all actions will create it
-        println!("Creating new directory: {}", output_dir.display());
-    }
+        /// Fail unless exactly N files are embedded
+        #[arg(long, value_name = "N")]
+        assert_file_count: Option<usize>,
 
-    // Create output directory
-    fs::create_dir_all(output_dir).with_context(|| {
-        format!(
-            "Failed to create output directory: {}",
-            output_dir.display()
-        )
-    })?;
-
-    // Work with the config based on version
-    let (modified_json, file_counter) = match &mut config {
-        Config::V3_0(cfg) => disassemble_v3_config(cfg, output_dir)?,
-        Config::V3_1(cfg) => disassemble_v3_config(cfg, output_dir)?,
-        Config::V3_2(cfg) => disassemble_v3_config(cfg, output_dir)?,
-        Config::V3_3(cfg) => disassemble_v3_config(cfg, output_dir)?,
-        Config::V3_4(cfg) => disassemble_v3_config(cfg, output_dir)?,
-        Config::V3_5(cfg) => disassemble_v3_config(cfg, output_dir)?,
-        _ => anyhow::bail!("Unsupported Ignition config version"),
-    };
+        /// Print the path of the decoded config found in ignition_dir and
+        /// exit without assembling
+        #[arg(long)]
+        print_config_path: bool,
 
-    // Write the modified Ignition file
-    let decoded_path = output_dir.join("decoded.ign");
-    fs::write(&decoded_path, modified_json)
-        .with_context(|| format!("Failed to write decoded.ign: {}", decoded_path.display()))?;
-
-    println!(
-        "\nDecoding complete! Extracted {} file(s) to {}",
-        file_counter,
-        output_dir.display()
-    );
-    println!(
-        "Modified Ignition file saved as: {}",
-        decoded_path.display()
-    );
+        /// Decoded config to read from ignition_dir; the first `*.ign` file
+        /// by name is used when it is absent
+        #[arg(long, value_name = "NAME", default_value = DECODED_IGN)]
+        ign_name: String,
 
-    Ok(())
-}
+        /// Omit entries whose content file is missing from ignition_dir,
+        /// with a warning, instead of failing
+        #[arg(long)]
+        allow_missing_files: bool,
+
+        /// Whether content files that are (or sit below) symlinks are read
+        /// through the link or rejected
+        #[arg(long, value_enum, default_value = "deny")]
+        follow_symlinks: SymlinkPolicy,
+
+        /// Line endings of text content files (by media type) before
+        /// embedding; binary files are never changed
+        #[arg(long, value_enum, default_value = "preserve")]
+        embed_newline: NewlineMode,
+
+        /// Read content files as committed at this git ref (e.g. HEAD or a
+        /// tag) instead of from the working tree; decoded.ign is still read
+        /// from disk
+        #[arg(long, value_name = "REF")]
+        content_from_git: Option<String>,
+
+        /// Write the config to stdout instead of target_file, which is then
+        /// ignored (same as passing `-`); status messages go to stderr
+        #[arg(long)]
+        stdout: bool,
 
-// Generic function to handle all v3.x configs (they all have the same structure for our purposes)
-fn disassemble_v3_config<T>(config: &mut T, output_dir: &Path) -> Result<(String, usize)>
-where
-    T: serde::Serialize + serde::de::DeserializeOwned,
-{
-    // Serialize to JSON value for manipulation
-    let mut json_value: serde_json::Value =
-        serde_json::to_value(&config).with_context(|| "Failed to serialize config")?;
+        /// End the output with a newline; by default the last byte is the
+        /// closing brace, as user-data fields expect
+        #[arg(long)]
+        newline: bool,
 
-    let mut file_counter = 0;
+        /// Check that each gzipped entry inflates back to its content file,
+        /// warning when the re-compressed bytes differ from the original
+        #[arg(long)]
+        verify_gzip_roundtrip: bool,
 
-    find_and_replace_source_with_path_update(&mut json_value, "", output_dir, &mut file_counter)?;
+        /// Warn about files in the ignition directory that its manifest.json
+        /// doesn't list, and listed files that are missing
+        #[arg(long)]
+        validate_manifest: bool,
 
-    let pretty_json = serde_json::to_string_pretty(&json_value)
-        .with_context(|| "Failed to serialize modified config")?;
+        /// Re-parse the assembled config and refuse to write it when it is
+        /// invalid, naming the JSON pointer of the offending field
+        #[arg(long)]
+        validate: bool,
 
-    Ok((pretty_json, file_counter))
-}
+        /// Stream content files through the base64 encoder into the output
+        /// to bound memory use; files that need their whole content, with
+        /// --transform-cmd, --verify-gzip-roundtrip, --content-from-git or
+        /// text under --embed-newline lf, are still encoded in memory
+        #[arg(long)]
+        chunked_write: bool,
 
-fn find_and_replace_source<F>(value: &mut serde_json::Value, path: &str, func: &mut F) -> Result<()>
-where
-    F: FnMut(&str, &str, bool, usize) -> Result<String>,
-{
-    match value {
-        serde_json::Value::Object(map) => {
-            let mut new_path = path.to_string();
-            if let Some(p) = map.get("path").and_then(|v| v.as_str()) {
-                new_path = p.to_string();
-            }
+        /// Give storage.files entries without a `mode` the permission bits
+        /// of their content file (Unix only); metadata.json and decoded.ign
+        /// modes still win
+        #[arg(long)]
+        mode_from_file: bool,
 
-            // Check if this object has both a path and array fields with sources
-            let has_path = map.contains_key("path");
-            let mut found_array_with_sources = false;
-
-            if has_path {
-                // Look for array fields that contain objects with sources
-                for (_key, val) in map.iter() {
-                    if let serde_json::Value::Array(arr) = val {
-                        // Check if any element in the array has a source
-                        if arr.iter().any(|item| {
-                            if let serde_json::Value::Object(obj) = item {
-                                obj.contains_key("source")
-                            } else {
-                                false
-                            }
-                        }) {
-                            found_array_with_sources = true;
-                            break;
-                        }
-                    }
-                }
-            }
+        /// Embed every content file in the base64url alphabet; by default
+        /// each keeps the alphabet its source used when it was disassembled
+        #[arg(long)]
+        base64url: bool,
 
-            for (key, val) in map.iter_mut() {
-                if key == "source" {
-                    if let Some(s) = val.as_str() {
-                        *val = serde_json::Value::String(func(&new_path, s, false, 0)?);
-                    }
-                } else if found_array_with_sources && key != "path" {
-                    // This might be an array field with sources
-                    if let serde_json::Value::Array(arr) = val {
-                        // Check if this array contains objects with sources
-                        let has_sources = arr.iter().any(|item| {
-                            if let serde_json::Value::Object(obj) = item {
-                                obj.contains_key("source")
-                            } else {
-                                false
-                            }
-                        });
-
-                        if has_sources {
-                            // Process as array of sources
-                            for (index, item) in arr.iter_mut().enumerate() {
-                                process_array_item_sources(item, &new_path, index, func)?;
-                            }
-                        } else {
-                            // Regular array processing
-                            find_and_replace_source(val, &new_path, func)?;
-                        }
-                    } else {
-                        find_and_replace_source(val, &new_path, func)?;
-                    }
-                } else {
-                    find_and_replace_source(val, &new_path, func)?;
-                }
-            }
-        }
-        serde_json::Value::Array(arr) => {
-            for val in arr.iter_mut() {
-                find_and_replace_source(val, path, func)?;
-            }
-        }
-        _ => (),
-    }
-    Ok(())
+        /// Fail on any parse warning before touching the output file
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Check that an Ignition file parses, printing its spec version,
+    /// warnings and a summary, without writing anything
+    #[command(aliases = ["check"])]
+    Validate {
+        /// The ignition file to check (`-` for stdin)
+        ignition_file: PathBuf,
+
+        /// Also fail on top-level keys the config's spec version doesn't
+        /// define
+        #[arg(long)]
+        reject_unknown_sections: bool,
+    },
+    /// List the files disassembling an Ignition file would extract, without
+    /// writing anything
+    List {
+        /// The ignition file to inspect (`-` for stdin)
+        ignition_file: PathBuf,
+    },
+    /// List every remote URL an Ignition file depends on
+    #[command(aliases = ["list-remote"])]
+    Remotes {
+        /// The ignition file to inspect (`-` for stdin)
+        ignition_file: PathBuf,
+
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Summarize an Ignition file: spec version, entry counts, decoded size,
+    /// compression and remote sources
+    #[command(aliases = ["stats"])]
+    Info {
+        /// The ignition file to inspect (`-` for stdin)
+        ignition_file: PathBuf,
+
+        /// Print the summary as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Compare two Ignition files by what they put on the system, ignoring
+    /// key order and source encoding, exiting non-zero when they differ
+    Diff {
+        /// The first ignition file
+        left: PathBuf,
+
+        /// The second ignition file
+        right: PathBuf,
+    },
+    /// Compare a local Ignition file with the one served at a URL, exiting
+    /// non-zero when they differ
+    #[cfg(feature = "http")]
+    DiffRemote {
+        /// The local ignition file
+        local_file: PathBuf,
+
+        /// URL the deployed config is served from
+        url: String,
+
+        /// Refuse either config when larger than this many bytes
+        #[arg(long, value_name = "BYTES")]
+        max_input_size: Option<u64>,
+    },
+    /// Convert an Ignition file to another spec version, reporting fields the
+    /// target version can't represent and refusing downgrades that lose any
+    Convert {
+        /// The ignition file to convert
+        input: PathBuf,
+
+        /// Where the converted config is written
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Target spec version, e.g. 3.4.0
+        #[arg(long, value_name = "VERSION")]
+        to_version: String,
+    },
+    /// Merge decoded directories into one, later inputs winning when
+    /// entries share a path or name (Ignition's merge semantics)
+    Merge {
+        /// Decoded directories, merged in order
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Directory the merged decoded tree is written to
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Round-trip random configs through disassemble and assemble
+    #[command(hide = true)]
+    Fuzz {
+        /// Number of random configs to try
+        #[arg(long, default_value_t = 100)]
+        iterations: usize,
+
+        /// Seed of the first config (random when omitted)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// When a round trip fails, write a unified diff of the decoded files
+        /// it changed to this file
+        #[arg(long, value_name = "FILE")]
+        emit_diff_on_roundtrip_failure: Option<PathBuf>,
+    },
 }
 
-fn process_array_item_sources<F>(
-    item: &mut serde_json::Value,
-    path: &str,
-    index: usize,
-    func: &mut F,
-) -> Result<()>
-where
-    F: FnMut(&str, &str, bool, usize) -> Result<String>,
-{
-    if let serde_json::Value::Object(map) = item {
-        for (key, val) in map.iter_mut() {
-            if key == "source" {
-                if let Some(s) = val.as_str() {
-                    *val = serde_json::Value::String(func(path, s, true, index)?);
-                }
-            } else {
-                // Recursively process nested structures
-                process_nested_sources(val, path, index, func)?;
-            }
-        }
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    if cli.quiet {
+        set_verbosity(Verbosity::Quiet);
+    } else if cli.verbose {
+        set_verbosity(Verbosity::Verbose);
     }
-    Ok(())
-}
 
-fn process_nested_sources<F>(
-    value: &mut serde_json::Value,
-    path: &str,
-    index: usize,
-    func: &mut F,
-) -> Result<()>
-where
-    F: FnMut(&str, &str, bool, usize) -> Result<String>,
-{
-    match value {
-        serde_json::Value::Object(map) => {
-            for (key, val) in map.iter_mut() {
-                if key == "source" {
-                    if let Some(s) = val.as_str() {
-                        *val = serde_json::Value::String(func(path, s, true, index)?);
+    match cli.command {
+        Commands::Disassemble {
+            ignition_file,
+            target_dir,
+            input_list,
+            compact,
+            indent,
+            canonical_order,
+            action,
+            overwrite_mode,
+            abort_on_warning,
+            rootfs,
+            transform_cmd,
+            verify_paths_unique,
+            validate_modes,
+            reject_unknown_sections,
+            decode_verification_check,
+            strict,
+            keep_going,
+            reproducible,
+            max_input_size,
+            decode_unit_contents,
+            extract_units,
+            split_units_dir,
+            extract_ssh_keys,
+            extract_links,
+            quiet_warnings,
+            from_base64_file,
+            emit_dockerfile,
+            emit_gitattributes,
+            emit_env_file,
+            emit_sbom,
+            output_tree_json,
+            digest,
+            dedup,
+            chunked_read,
+            parallel,
+            version_range,
+            expected_version,
+            ownership_policy,
+            relativize_remote,
+            include,
+            exclude,
+            assert_file_count,
+            config_only,
+            strict_array_detection,
+            set_xattrs,
+            apply_mode,
+            metadata,
+            input_multiple,
+            no_clobber_manifest,
+            ign_name,
+        } => {
+            let ownership_policy = ownership_policy
+                .as_deref()
+                .map(OwnershipPolicy::load)
+                .transpose()?;
+            let options = DisassembleOptions {
+                compact,
+                indent,
+                canonical_order,
+                action,
+                overwrite_mode,
+                abort_on_warning,
+                rootfs,
+                transform_cmd,
+                verify_paths_unique,
+                validate_modes,
+                reject_unknown_sections,
+                decode_verification_check,
+                strict,
+                keep_going,
+                reproducible,
+                max_input_size,
+                decode_unit_contents,
+                extract_units,
+                split_units_dir,
+                extract_ssh_keys,
+                extract_links,
+                quiet_warnings,
+                from_base64_file,
+                emit_dockerfile,
+                emit_gitattributes,
+                emit_env_file,
+                emit_sbom,
+                output_tree_json,
+                digest,
+                dedup,
+                chunked_read,
+                parallel,
+                version_range,
+                expected_version,
+                ownership_policy,
+                relativize_remote,
+                include,
+                exclude,
+                assert_file_count,
+                config_only,
+                strict_array_detection,
+                set_xattrs,
+                apply_mode,
+                metadata,
+                input_multiple,
+                no_clobber_manifest,
+                ign_name: Some(ign_name),
+                dry_run: cli.dry_run,
+            };
+            match (input_list, ignition_file, target_dir) {
+                (Some(input_list), _, _) => {
+                    let to_stderr = is_stdin(&input_list);
+                    let reports = disassemble_input_list(
+                        &input_list,
+                        &options,
+                        Some(&mut PrintProgress { to_stderr }),
+                    )?;
+                    for (target_dir, report) in &reports {
+                        print_disassembly_summary(report, target_dir, &options, to_stderr);
                     }
-                } else {
-                    process_nested_sources(val, path, index, func)?;
+                    status(
+                        to_stderr,
+                        &format!(
+                            "\nBatch complete! Decoded {} config(s), {} file(s) in total, {} skipped",
+                            reports.len(),
+                            reports.iter().map(|(_, r)| r.file_count).sum::<usize>(),
+                            reports.iter().filter(|(_, r)| r.skipped.is_some()).count()
+                        ),
+                    );
+                }
+                (None, Some(ignition_file), Some(target_dir)) => {
+                    let to_stderr = is_stdin(&ignition_file);
+                    let report = disassemble_ignition(
+                        &ignition_file,
+                        &target_dir,
+                        &options,
+                        Some(&mut PrintProgress { to_stderr }),
+                    )?;
+                    print_disassembly_summary(&report, &target_dir, &options, to_stderr);
                 }
+                _ => unreachable!("clap requires both paths without --input-list"),
             }
         }
-        serde_json::Value::Array(arr) => {
-            for val in arr.iter_mut() {
-                process_nested_sources(val, path, index, func)?;
-            }
+        Commands::Assemble {
+            target_file,
+            ignition_dir,
+            compact,
+            indent,
+            canonical_order,
+            default,
+            keep_keys,
+            action,
+            abort_on_warning,
+            preserve_unknown,
+            split_large,
+            split_base_url,
+            transform_cmd,
+            reproducible,
+            max_input_size,
+            assemble_order,
+            quiet_warnings,
+            version_range,
+            ownership_policy,
+            relativize_remote,
+            inline_remote,
+            assert_file_count,
+            print_config_path,
+            ign_name,
+            allow_missing_files,
+            follow_symlinks,
+            embed_newline,
+            content_from_git,
+            stdout,
+            newline,
+            verify_gzip_roundtrip,
+            validate_manifest,
+            validate,
+            chunked_write,
+            mode_from_file,
+            base64url,
+            strict,
+        } => {
+            let ownership_policy = ownership_policy
+                .as_deref()
+                .map(OwnershipPolicy::load)
+                .transpose()?;
+            let split_large = split_large
+                .zip(split_base_url)
+                .map(|(threshold, base_url)| SplitLarge {
+                    threshold,
+                    base_url,
+                });
+            let options = AssembleOptions {
+                compact,
+                indent,
+                canonical_order,
+                default,
+                keep_keys,
+                action,
+                abort_on_warning,
+                preserve_unknown,
+                split_large,
+                transform_cmd,
+                reproducible,
+                max_input_size,
+                order: assemble_order,
+                quiet_warnings,
+                version_range,
+                ownership_policy,
+                relativize_remote,
+                inline_remote,
+                assert_file_count,
+                print_config_path,
+                ign_name: Some(ign_name),
+                allow_missing_files,
+                follow_symlinks,
+                embed_newline,
+                content_from_git,
+                stdout: stdout || target_file.as_os_str() == "-",
+                newline,
+                verify_gzip_roundtrip,
+                validate_manifest,
+                validate,
+                chunked_write,
+                mode_from_file,
+                base64url,
+                strict,
+                dry_run: cli.dry_run,
+            };
+            let mut progress = PrintProgress {
+                to_stderr: options.stdout,
+            };
+            let report =
+                assemble_ignition(&target_file, &ignition_dir, &options, Some(&mut progress))?;
+            print_assembly_summary(&report, &target_file, &options);
         }
-        _ => (),
-    }
-    Ok(())
-}
-
-fn remove_default_values(value: &mut serde_json::Value) {
-    match value {
-        serde_json::Value::Object(map) => {
-            map.retain(|_, v| !is_default(v));
-            for (_, v) in map.iter_mut() {
-                remove_default_values(v);
-            }
+        Commands::Validate {
+            ignition_file,
+            reject_unknown_sections,
+        } => {
+            validate_ignition(&ignition_file, reject_unknown_sections)?;
         }
-        serde_json::Value::Array(arr) => {
-            arr.retain(|v| !is_default(v));
-            for v in arr.iter_mut() {
-                remove_default_values(v);
-            }
+        Commands::List { ignition_file } => {
+            list_embedded(&ignition_file)?;
         }
-        _ => (),
-    }
-}
-
-fn is_default(value: &serde_json::Value) -> bool {
-    match value {
-        serde_json::Value::Null => true,
-        serde_json::Value::String(s) => s.is_empty(),
-        serde_json::Value::Array(arr) => arr.is_empty(),
-        serde_json::Value::Object(map) => map.is_empty(),
-        serde_json::Value::Bool(b) => !b,
-        serde_json::Value::Number(n) => n.as_u64().unwrap_or(1) == 0,
-    }
-}
-
-fn assemble_ignition(
-    target_file: &Path,
-    ignition_dir: &Path,
-    compact: bool,
-    default: bool,
-    action: Action,
-) -> Result<()> {
-    // Handle target file based on action
-    if target_file.exists() {
-        match action {
-            Action::New => {
+        Commands::Remotes {
+            ignition_file,
+            json,
+        } => {
+            list_remotes(&ignition_file, json)?;
+        }
+        Commands::Info {
+            ignition_file,
+            json,
+        } => {
+            show_info(&ignition_file, json)?;
+        }
+        Commands::Diff { left, right } => {
+            let differences = diff_ignition(&left, &right)?;
+            for line in &differences {
+                println!("{}", line);
+            }
+            if !differences.is_empty() {
                 anyhow::bail!(
-                    "Target file already exists: {}. Use --action replace or --action add to overwrite.",
-                    target_file.display()
+                    "{} differs from {}: {} difference(s)",
+                    left.display(),
+                    right.display(),
+                    differences.len()
                 );
             }
-            Action::Add | Action::Replace => {
-                fs::remove_file(target_file).with_context(|| {
-                    format!(
-                        "Failed to remove existing target file: {}",
-                        target_file.display()
-                    )
-                })?;
-                println!("Overwriting existing file: {}", target_file.display());
-            }
-        }
-    } else {
-        // File doesn't exist, all actions will create it
-        println!("Creating new file: {}", target_file.display());
-    }
-
-    // Find the .ign file in the ignition_dir
-    let mut ignition_file = None;
-    for entry in fs::read_dir(ignition_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("ign") {
-            ignition_file = Some(path);
-            break;
-        }
-    }
-
-    let ignition_file = ignition_file.ok_or_else(|| {
-        anyhow::anyhow!(
-            "No .ign file found in ignition_dir: {}",
-            ignition_dir.display()
-        )
-    })?;
-
-    // Read the decoded Ignition file
-    let content = fs::read_to_string(&ignition_file)
-        .with_context(|| format!("Failed to read decoded file: {}", ignition_file.display()))?;
-
-    // Parse the Ignition config
-    let (config, warnings) =
-        Config::parse_str(&content).with_context(|| "Failed to parse decoded Ignition file")?;
-
-    // Print warnings if any
-    for warning in warnings {
-        eprintln!("Warning: {}", warning);
-    }
-
-    // Work with the config based on version
-    let (mut modified_json, file_counter) = match config {
-        Config::V3_0(cfg) => assemble_v3_config(&cfg, ignition_dir)?,
-        Config::V3_1(cfg) => assemble_v3_config(&cfg, ignition_dir)?,
-        Config::V3_2(cfg) => assemble_v3_config(&cfg, ignition_dir)?,
-        Config::V3_3(cfg) => assemble_v3_config(&cfg, ignition_dir)?,
-        Config::V3_4(cfg) => assemble_v3_config(&cfg, ignition_dir)?,
-        Config::V3_5(cfg) => assemble_v3_config(&cfg, ignition_dir)?,
-        _ => anyhow::bail!("Unsupported Ignition config version"),
-    };
-
-    if default {
-        let mut json_value: serde_json::Value = serde_json::from_str(&modified_json)?;
-        remove_default_values(&mut json_value);
-        modified_json = if compact {
-            serde_json::to_string(&json_value)?
-        } else {
-            serde_json::to_string_pretty(&json_value)?
-        };
-    } else {
-        if compact {
-            let json_value: serde_json::Value = serde_json::from_str(&modified_json)?;
-            modified_json = serde_json::to_string(&json_value)?;
+            status(
+                false,
+                &format!("{} matches {}", left.display(), right.display()),
+            );
         }
-    }
-
-    // Write the encoded Ignition file
-    fs::write(target_file, modified_json)
-        .with_context(|| format!("Failed to write output file: {}", target_file.display()))?;
-
-    println!(
-        "\nEncoding complete! Encoded {} file(s) into {}",
-        file_counter,
-        target_file.display()
-    );
-
-    Ok(())
-}
-
-fn assemble_v3_config<T>(config: &T, files_dir: &Path) -> Result<(String, usize)>
-where
-    T: serde::Serialize,
-{
-    // Serialize to JSON value for manipulation
-    let mut json_value: serde_json::Value =
-        serde_json::to_value(config).with_context(|| "Failed to serialize config")?;
-
-    let mut file_counter = 0;
-
-    find_and_replace_source(
-        &mut json_value,
-        "",
-        &mut |_path, source_str, _is_array, _array_index| {
-            if source_str.contains(";base64-placeholder,") {
-                // Extract media type and file path from the placeholder
-                let after_data = source_str.trim_start_matches("data:");
-                let parts: Vec<&str> = after_data.splitn(2, ";base64-placeholder,").collect();
-                let media_type = parts[0];
-                let file_path = if parts.len() > 1 && !parts[1].is_empty() {
-                    parts[1]
-                } else {
-                    return Err(anyhow::anyhow!(
-                        "Invalid placeholder format: missing file path"
-                    ));
-                };
-
-                let in_path = files_dir.join(file_path);
-
-                let file_content = fs::read(&in_path)?;
-                use base64::Engine;
-                let encoded = base64::engine::general_purpose::STANDARD.encode(&file_content);
-                file_counter += 1;
-                Ok(format!("data:{};base64,{}", media_type, encoded))
-            } else {
-                Ok(source_str.to_string())
+        Commands::Convert {
+            input,
+            output,
+            to_version,
+        } => {
+            let dropped = convert_ignition(&input, &output, &to_version)?;
+            status(
+                false,
+                &format!(
+                    "Converted {} to spec version {}: {}",
+                    input.display(),
+                    to_version,
+                    output.display()
+                ),
+            );
+            if !dropped.is_empty() {
+                status(
+                    false,
+                    &format!(
+                        "Dropped {} field(s) the target version can't represent",
+                        dropped.len()
+                    ),
+                );
             }
-        },
-    )?;
-
-    let pretty_json = serde_json::to_string_pretty(&json_value)
-        .with_context(|| "Failed to serialize encoded config")?;
-
-    Ok((pretty_json, file_counter))
-}
-
-fn find_and_replace_source_with_path_update(
-    value: &mut serde_json::Value,
-    path: &str,
-    output_dir: &Path,
-    file_counter: &mut usize,
-) -> Result<()> {
-    match value {
-        serde_json::Value::Object(map) => {
-            let mut new_path = path.to_string();
-            if let Some(p) = map.get("path").and_then(|v| v.as_str()) {
-                new_path = p.to_string();
+        }
+        Commands::Merge { inputs, output } => {
+            let report = merge_decoded_dirs(&inputs, &output, cli.dry_run)?;
+            if !cli.dry_run {
+                status(
+                    false,
+                    &format!(
+                        "Merged {} decoded director(ies) with {} file(s) into {}",
+                        inputs.len(),
+                        report.file_count,
+                        output.display()
+                    ),
+                );
             }
-
-            // Check if this object has both a path and array fields with sources
-            let has_path = map.contains_key("path");
-            let mut found_array_with_sources = false;
-
-            if has_path {
-                // Look for array fields that contain objects with sources
-                for (_key, val) in map.iter() {
-                    if let serde_json::Value::Array(arr) = val {
-                        // Check if any element in the array has a source
-                        if arr.iter().any(|item| {
-                            if let serde_json::Value::Object(obj) = item {
-                                obj.contains_key("source")
-                            } else {
-                                false
-                            }
-                        }) {
-                            found_array_with_sources = true;
-                            break;
-                        }
-                    }
-                }
+        }
+        #[cfg(feature = "http")]
+        Commands::DiffRemote {
+            local_file,
+            url,
+            max_input_size,
+        } => {
+            let differences = diff_remote(&local_file, &url, max_input_size)?;
+            for line in &differences {
+                println!("{}", line);
             }
-
-            for (key, val) in map.iter_mut() {
-                if key == "source" {
-                    if let Some(source_str) = val.as_str() {
-                        if source_str.starts_with("data:") {
-                            let url = data_url::DataUrl::process(source_str).map_err(|e| {
-                                anyhow::anyhow!("Failed to parse data URL: {:?}", e)
-                            })?;
-                            let (decoded_content, _) = url.decode_to_vec().unwrap();
-                            let media_type = url.mime_type().to_string();
-
-                            let relative_path = new_path.trim_start_matches("/");
-
-                            // Handle empty path by providing a default filename based on content type
-                            let effective_path = if relative_path.is_empty() {
-                                // Generate a filename based on the media type
-                                let extension = match media_type.as_str() {
-                                    "text/plain" => "data",
-                                    "application/json" => "json",
-                                    "application/yaml" => "yaml",
-                                    "text/yaml" => "yaml",
-                                    "application/x-yaml" => "yaml",
-                                    "text/x-yaml" => "yaml",
-                                    "application/xml" => "xml",
-                                    "text/xml" => "xml",
-                                    "text/html" => "html",
-                                    "application/javascript" => "js",
-                                    "text/css" => "css",
-                                    _ => "data",
-                                };
-                                format!("extracted_file_{}.{}", *file_counter, extension)
-                            } else {
-                                relative_path.to_string()
-                            };
-
-                            // Create the output file
-                            let file_path = output_dir.join(&effective_path);
-                            if let Some(parent) = file_path.parent() {
-                                fs::create_dir_all(parent)?;
-                            }
-
-                            fs::write(&file_path, decoded_content)?;
-
-                            // Update the source field to placeholder with relative file path
-                            *val = serde_json::Value::String(format!(
-                                "data:{};base64-placeholder,{}",
-                                media_type, effective_path
-                            ));
-
-                            *file_counter += 1;
-                        }
-                    }
-                } else if found_array_with_sources && key != "path" {
-                    // This might be an array field with sources
-                    if let serde_json::Value::Array(arr) = val {
-                        // Check if this array contains objects with sources
-                        let has_sources = arr.iter().any(|item| {
-                            if let serde_json::Value::Object(obj) = item {
-                                obj.contains_key("source")
-                            } else {
-                                false
-                            }
-                        });
-
-                        if has_sources {
-                            // Process as array of sources
-                            for (index, item) in arr.iter_mut().enumerate() {
-                                process_array_item_sources_with_path_update(
-                                    item,
-                                    &new_path,
-                                    index,
-                                    output_dir,
-                                    file_counter,
-                                )?;
-                            }
-                        } else {
-                            // Recursively process nested structures
-                            find_and_replace_source_with_path_update(
-                                val,
-                                &new_path,
-                                output_dir,
-                                file_counter,
-                            )?;
-                        }
-                    } else {
-                        // Recursively process other nested structures
-                        find_and_replace_source_with_path_update(
-                            val,
-                            &new_path,
-                            output_dir,
-                            file_counter,
-                        )?;
-                    }
-                } else {
-                    // Recursively process nested objects and arrays
-                    find_and_replace_source_with_path_update(
-                        val,
-                        &new_path,
-                        output_dir,
-                        file_counter,
-                    )?;
-                }
+            if !differences.is_empty() {
+                anyhow::bail!(
+                    "{} differs from {}: {} difference(s)",
+                    local_file.display(),
+                    url,
+                    differences.len()
+                );
             }
+            status(false, &format!("{} matches {}", local_file.display(), url));
         }
-        serde_json::Value::Array(arr) => {
-            for val in arr.iter_mut() {
-                find_and_replace_source_with_path_update(val, path, output_dir, file_counter)?;
-            }
+        Commands::Fuzz {
+            iterations,
+            seed,
+            emit_diff_on_roundtrip_failure,
+        } => {
+            run_fuzz(
+                iterations,
+                seed.unwrap_or_else(|| fastrand::u64(..)),
+                emit_diff_on_roundtrip_failure.as_deref(),
+            )?;
         }
-        _ => {}
     }
 
     Ok(())
 }
 
-fn process_array_item_sources_with_path_update(
-    item: &mut serde_json::Value,
-    path: &str,
-    index: usize,
-    output_dir: &Path,
-    file_counter: &mut usize,
-) -> Result<()> {
-    if let serde_json::Value::Object(map) = item {
-        for (key, val) in map.iter_mut() {
-            if key == "source" {
-                if let Some(source_str) = val.as_str() {
-                    if source_str.starts_with("data:") {
-                        let url = data_url::DataUrl::process(source_str)
-                            .map_err(|e| anyhow::anyhow!("Failed to parse data URL: {:?}", e))?;
-                        let (decoded_content, _) = url.decode_to_vec().unwrap();
-                        let media_type = url.mime_type().to_string();
-
-                        let relative_path = path.trim_start_matches("/");
-
-                        // Handle empty path by providing a default filename
-                        let effective_path = if relative_path.is_empty() {
-                            let extension = match media_type.as_str() {
-                                "text/plain" => "data",
-                                "application/json" => "json",
-                                "application/yaml" => "yaml",
-                                "text/yaml" => "yaml",
-                                "application/x-yaml" => "yaml",
-                                "text/x-yaml" => "yaml",
-                                "application/xml" => "xml",
-                                "text/xml" => "xml",
-                                "text/html" => "html",
-                                "application/javascript" => "js",
-                                "text/css" => "css",
-                                _ => "data",
-                            };
-                            format!("extracted_file_{}.{}", *file_counter, extension)
-                        } else {
-                            relative_path.to_string()
-                        };
-
-                        // Create directory and indexed file for array items
-                        let dir_path = output_dir.join(&effective_path);
-                        fs::create_dir_all(&dir_path)?;
-                        let file_path = dir_path.join(index.to_string());
-
-                        fs::write(&file_path, decoded_content)?;
-
-                        // Update the source field to placeholder with array path
-                        let array_file_path = format!("{}/{}", effective_path, index);
-                        *val = serde_json::Value::String(format!(
-                            "data:{};base64-placeholder,{}",
-                            media_type, array_file_path
-                        ));
-
-                        *file_counter += 1;
-                    }
-                }
-            } else {
-                // Recursively process nested structures
-                process_nested_sources_with_path_update(
-                    val,
-                    path,
-                    index,
-                    output_dir,
-                    file_counter,
-                )?;
-            }
-        }
+fn print_disassembly_summary(
+    report: &Report,
+    target_dir: &Path,
+    options: &DisassembleOptions,
+    to_stderr: bool,
+) {
+    if let Some(reason) = &report.skipped {
+        status(to_stderr, reason);
+    } else if options.dry_run {
+        status(
+            to_stderr,
+            &format!(
+                "\nDry run complete: would extract {} file(s) to {}",
+                report.file_count,
+                target_dir.display()
+            ),
+        );
+    } else if options.rootfs {
+        status(
+            to_stderr,
+            &format!(
+                "\nMaterialized {} file(s) into rootfs at {}",
+                report.file_count,
+                target_dir.display()
+            ),
+        );
+    } else {
+        status(
+            to_stderr,
+            &format!(
+                "\nDecoding complete! Extracted {} file(s) to {}",
+                report.file_count,
+                target_dir.display()
+            ),
+        );
+        status(
+            to_stderr,
+            &format!(
+                "Modified Ignition file saved as: {}",
+                target_dir
+                    .join(options.ign_name.as_deref().unwrap_or(DECODED_IGN))
+                    .display()
+            ),
+        );
+    }
+    if report.remote_count > 0 && report.skipped.is_none() {
+        status(
+            to_stderr,
+            &format!(
+                "{} remote source(s) fetched at boot, listed in {}",
+                report.remote_count,
+                target_dir.join("references.json").display()
+            ),
+        );
+    }
+    if !report.hash_mismatches.is_empty() {
+        status(
+            to_stderr,
+            &format!(
+                "{} verification hash mismatch(es):\n  {}",
+                report.hash_mismatches.len(),
+                report.hash_mismatches.join("\n  ")
+            ),
+        );
     }
-    Ok(())
 }
 
-fn process_nested_sources_with_path_update(
-    value: &mut serde_json::Value,
-    path: &str,
-    index: usize,
-    output_dir: &Path,
-    file_counter: &mut usize,
-) -> Result<()> {
-    match value {
-        serde_json::Value::Object(map) => {
-            for (key, val) in map.iter_mut() {
-                if key == "source" {
-                    if let Some(source_str) = val.as_str() {
-                        if source_str.starts_with("data:") {
-                            let url = data_url::DataUrl::process(source_str).map_err(|e| {
-                                anyhow::anyhow!("Failed to parse data URL: {:?}", e)
-                            })?;
-                            let (decoded_content, _) = url.decode_to_vec().unwrap();
-                            let media_type = url.mime_type().to_string();
-
-                            let relative_path = path.trim_start_matches("/");
-
-                            let effective_path = if relative_path.is_empty() {
-                                let extension = match media_type.as_str() {
-                                    "text/plain" => "data",
-                                    "application/json" => "json",
-                                    "application/yaml" => "yaml",
-                                    "text/yaml" => "yaml",
-                                    "application/x-yaml" => "yaml",
-                                    "text/x-yaml" => "yaml",
-                                    "application/xml" => "xml",
-                                    "text/xml" => "xml",
-                                    "text/html" => "html",
-                                    "application/javascript" => "js",
-                                    "text/css" => "css",
-                                    _ => "data",
-                                };
-                                format!("extracted_file_{}.{}", *file_counter, extension)
-                            } else {
-                                relative_path.to_string()
-                            };
-
-                            let dir_path = output_dir.join(&effective_path);
-                            fs::create_dir_all(&dir_path)?;
-                            let file_path = dir_path.join(index.to_string());
-
-                            fs::write(&file_path, decoded_content)?;
-
-                            let array_file_path = format!("{}/{}", effective_path, index);
-                            *val = serde_json::Value::String(format!(
-                                "data:{};base64-placeholder,{}",
-                                media_type, array_file_path
-                            ));
-
-                            *file_counter += 1;
-                        }
-                    }
-                } else {
-                    process_nested_sources_with_path_update(
-                        val,
-                        path,
-                        index,
-                        output_dir,
-                        file_counter,
-                    )?;
-                }
-            }
-        }
-        serde_json::Value::Array(arr) => {
-            for val in arr.iter_mut() {
-                process_nested_sources_with_path_update(
-                    val,
-                    path,
-                    index,
-                    output_dir,
-                    file_counter,
-                )?;
-            }
+fn print_assembly_summary(report: &Report, target_file: &Path, options: &AssembleOptions) {
+    let destination = if options.stdout {
+        "stdout".to_string()
+    } else {
+        target_file.display().to_string()
+    };
+    if let Some(reason) = &report.skipped {
+        status(options.stdout, reason);
+    } else if options.print_config_path {
+        // The config path is the only output
+    } else if options.dry_run {
+        status(
+            options.stdout,
+            &format!(
+                "\nDry run complete: would encode {} file(s) into {}",
+                report.file_count, destination
+            ),
+        );
+    } else {
+        status(
+            options.stdout,
+            &format!(
+                "\nEncoding complete! Encoded {} file(s) {} {}",
+                report.file_count,
+                if options.stdout { "to" } else { "into" },
+                destination
+            ),
+        );
+        if !report.extracted_paths.is_empty() {
+            status(
+                options.stdout,
+                &format!(
+                    "Externalized {} large source(s) to {}",
+                    report.extracted_paths.len(),
+                    target_file.with_extension("split").display()
+                ),
+            );
         }
-        _ => (),
     }
-    Ok(())
 }
@@ -0,0 +1,217 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// Name of the manifest written at the root of a decoded directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Revision of the placeholder format written by this version. Bump it
+/// whenever decoded.ign placeholders change in a way older assemblers would
+/// misread.
+pub const PLACEHOLDER_FORMAT: u32 = 1;
+
+/// Machine-readable index of a decoded directory
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    /// Version of the coder that wrote the decoded directory
+    #[serde(default)]
+    pub coder_version: Option<String>,
+    /// Placeholder format revision used in decoded.ign; directories written
+    /// before this was recorded use revision 1
+    #[serde(default = "initial_placeholder_format")]
+    pub placeholder_format: u32,
+    /// Paths of the `storage.files` entries in their original config order
+    #[serde(default)]
+    pub file_order: Vec<String>,
+    /// Input config each `storage.files` path last came from, when several
+    /// configs were merged
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub provenance: BTreeMap<String, String>,
+    /// Hash of the original compressed bytes of each gzipped source, keyed by
+    /// its file in the decoded directory, to tell whether re-compressing it
+    /// reproduces them
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub gzip_digests: BTreeMap<String, String>,
+    /// Every extracted file, ordered by ignition path
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<Artifact>,
+}
+
+/// One file of a decoded directory and the source it was extracted from
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Artifact {
+    /// Ignition path, or unit name for unit contents and user name for SSH
+    /// keys, the source belongs to
+    pub ignition_path: String,
+    /// `contents`, `sshAuthorizedKeys`, or the array entry the source came
+    /// from, e.g. `append[0]`
+    pub field: String,
+    /// File in the decoded directory, relative to its root
+    pub file: String,
+    pub media_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+    /// Size after decoding and decompression
+    pub size: usize,
+}
+
+impl Manifest {
+    /// Build the manifest for a config as it was before disassembly
+    pub fn from_config(config: &Value) -> Self {
+        let file_order = storage_files(config)
+            .iter()
+            .filter_map(|f| f.get("path").and_then(|p| p.as_str()))
+            .map(str::to_string)
+            .collect();
+        Self {
+            coder_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            placeholder_format: PLACEHOLDER_FORMAT,
+            file_order,
+            provenance: BTreeMap::new(),
+            gzip_digests: BTreeMap::new(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// Record `artifacts`, stably ordered by ignition path
+    pub fn set_artifacts(&mut self, mut artifacts: Vec<Artifact>) {
+        artifacts.sort_by(|a, b| a.ignition_path.cmp(&b.ignition_path));
+        self.artifacts = artifacts;
+    }
+
+    /// Combine with the manifest of the directory this one is merged into:
+    /// files keep their existing order with new paths after them, and
+    /// entries for the same file or path take this manifest's values
+    pub fn merge_over(&mut self, existing: Manifest) {
+        let mut file_order = existing.file_order;
+        for path in std::mem::take(&mut self.file_order) {
+            if !file_order.contains(&path) {
+                file_order.push(path);
+            }
+        }
+        self.file_order = file_order;
+
+        let mut provenance = existing.provenance;
+        provenance.append(&mut self.provenance);
+        self.provenance = provenance;
+
+        let mut gzip_digests = existing.gzip_digests;
+        gzip_digests.append(&mut self.gzip_digests);
+        self.gzip_digests = gzip_digests;
+
+        let mut artifacts: Vec<Artifact> = existing
+            .artifacts
+            .into_iter()
+            .filter(|old| !self.artifacts.iter().any(|new| new.file == old.file))
+            .collect();
+        artifacts.append(&mut self.artifacts);
+        self.set_artifacts(artifacts);
+    }
+
+    /// Compare the regular files below `dir` with the artifacts, returning a
+    /// warning for each file the manifest doesn't list and each listed file
+    /// that is missing. `sidecars` are files at the root of `dir` that are
+    /// never artifacts.
+    pub fn validate_tree(&self, dir: &Path, sidecars: &[&str]) -> Result<Vec<String>> {
+        let mut on_disk = BTreeSet::new();
+        collect_files(dir, "", &mut on_disk)?;
+        for sidecar in sidecars {
+            on_disk.remove(*sidecar);
+        }
+        let listed: BTreeSet<String> = self.artifacts.iter().map(|a| a.file.clone()).collect();
+
+        let mut warnings: Vec<String> = on_disk
+            .difference(&listed)
+            .map(|file| format!("{} is not listed in {}", file, MANIFEST_FILE))
+            .collect();
+        warnings.extend(
+            listed
+                .difference(&on_disk)
+                .map(|file| format!("{} is listed in {} but missing", file, MANIFEST_FILE)),
+        );
+        Ok(warnings)
+    }
+
+    /// Fail if the decoded directory uses a placeholder format this version
+    /// does not understand
+    pub fn check_placeholder_format(&self) -> Result<()> {
+        if self.placeholder_format > PLACEHOLDER_FORMAT {
+            anyhow::bail!(
+                "Unsupported placeholder format revision {} (written by fcos-ignition-coder {}); this version understands up to revision {}",
+                self.placeholder_format,
+                self.coder_version.as_deref().unwrap_or("unknown"),
+                PLACEHOLDER_FORMAT
+            );
+        }
+        Ok(())
+    }
+
+    pub fn read(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+        let manifest = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse manifest: {}", path.display()))?;
+        Ok(Some(manifest))
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<std::path::PathBuf> {
+        let path = dir.join(MANIFEST_FILE);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write manifest: {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Like [`Manifest::write`], but an existing manifest keeps all of its
+    /// values, including fields this version doesn't know, and only gains
+    /// the entries it is missing
+    pub fn write_preserving(&self, dir: &Path) -> Result<std::path::PathBuf> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.is_file() {
+            return self.write(dir);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+        let mut merged: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse manifest: {}", path.display()))?;
+        crate::merge::fill_missing(&mut merged, &serde_json::to_value(self)?);
+        fs::write(&path, serde_json::to_string_pretty(&merged)?)
+            .with_context(|| format!("Failed to write manifest: {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// Collect the regular files below `dir` as `/`-separated paths relative to
+/// the decoded directory, skipping symlinks and `.git`
+fn collect_files(dir: &Path, prefix: &str, files: &mut BTreeSet<String>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = format!("{}{}", prefix, name);
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() && name != ".git" {
+            collect_files(&entry.path(), &format!("{}/", relative), files)?;
+        } else if file_type.is_file() {
+            files.insert(relative);
+        }
+    }
+    Ok(())
+}
+
+fn initial_placeholder_format() -> u32 {
+    1
+}
+
+fn storage_files(config: &Value) -> &[Value] {
+    config
+        .pointer("/storage/files")
+        .and_then(|f| f.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
@@ -0,0 +1,160 @@
+use serde_json::{Map, Value};
+
+/// Field identifying the entries of a keyed list, by the list's name
+fn list_key(list: &str) -> Option<&'static str> {
+    match list {
+        "files" | "directories" | "links" => Some("path"),
+        "units" | "dropins" | "users" | "groups" | "raid" | "luks" => Some("name"),
+        "disks" | "filesystems" => Some("device"),
+        _ => None,
+    }
+}
+
+/// Merge `overlay` into `base` the way Ignition merges a child config into
+/// its parent:
+///
+/// - objects merge field by field, and overlay scalars replace base ones
+/// - null overlay fields are unset and leave the base value alone
+/// - entries of keyed lists (`files`, `directories` and `links` by `path`;
+///   `units`, `dropins`, `users` and `groups` by `name`; ...) merge with the
+///   base entry of the same key, or are appended when the key is new
+/// - lists of strings (ssh keys, kernel arguments, ...) gain the overlay
+///   values they don't have yet
+/// - other lists, such as `append`, are concatenated
+pub fn merge_configs(base: &mut Value, overlay: &Value) {
+    merge_value(base, overlay, "");
+}
+
+fn merge_value(base: &mut Value, overlay: &Value, name: &str) {
+    match (base, overlay) {
+        (_, Value::Null) => {}
+        (Value::Object(base), Value::Object(overlay)) => merge_objects(base, overlay),
+        (Value::Array(base), Value::Array(overlay)) => merge_lists(base, overlay, name),
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+fn merge_objects(base: &mut Map<String, Value>, overlay: &Map<String, Value>) {
+    for (key, value) in overlay {
+        match base.get_mut(key) {
+            Some(existing) if !existing.is_null() => merge_value(existing, value, key),
+            _ if value.is_null() => {}
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn merge_lists(base: &mut Vec<Value>, overlay: &[Value], name: &str) {
+    let key = list_key(name);
+    for item in overlay {
+        let existing = match (key, item) {
+            (Some(key), Value::Object(_)) => {
+                let id = item.get(key).filter(|id| !id.is_null());
+                id.and_then(|id| base.iter_mut().find(|b| b.get(key) == Some(id)))
+            }
+            (_, Value::String(_)) => base.iter_mut().find(|b| *b == item),
+            _ => None,
+        };
+        match existing {
+            Some(existing) => merge_value(existing, item, ""),
+            None => base.push(item.clone()),
+        }
+    }
+}
+
+/// Add what `new` has and `existing` lacks, keeping every value already in
+/// `existing`: objects gain missing fields and lists gain missing items
+pub fn fill_missing(existing: &mut Value, new: &Value) {
+    match (existing, new) {
+        (Value::Object(existing), Value::Object(new)) => {
+            for (key, value) in new {
+                match existing.get_mut(key) {
+                    Some(current) => fill_missing(current, value),
+                    None => {
+                        existing.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(existing), Value::Array(new)) => {
+            for item in new {
+                if !existing.contains(item) {
+                    existing.push(item.clone());
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Marker between the media type and the relative file of a placeholder
+const PLACEHOLDER_MARKER: &str = ";base64-placeholder,";
+
+/// Prefix the relative file of every placeholder in `config` with the index
+/// of the decoded directory it belongs to, so placeholders of different
+/// directories stay apart through a merge
+pub fn tag_placeholders(config: &mut Value, input: usize) {
+    for_each_placeholder(config, &mut |media_type, relative| {
+        Ok(format!(
+            "{}{}{}/{}",
+            media_type, PLACEHOLDER_MARKER, input, relative
+        ))
+    })
+    .expect("tagging a placeholder can't fail");
+}
+
+/// Replace every placeholder tagged by [`tag_placeholders`] with one for the
+/// relative file `resolve` returns for its directory index and relative file
+pub fn resolve_placeholders<F>(config: &mut Value, resolve: &mut F) -> anyhow::Result<()>
+where
+    F: FnMut(usize, &str) -> anyhow::Result<String>,
+{
+    for_each_placeholder(config, &mut |media_type, tagged| {
+        let (input, relative) = tagged
+            .split_once('/')
+            .and_then(|(input, relative)| Some((input.parse().ok()?, relative)))
+            .ok_or_else(|| anyhow::anyhow!("Untagged placeholder file: {}", tagged))?;
+        let relative = resolve(input, relative)?;
+        Ok(format!("{}{}{}", media_type, PLACEHOLDER_MARKER, relative))
+    })
+}
+
+/// Rewrite every placeholder string below `value` from its media type part
+/// and relative file
+fn for_each_placeholder<F>(value: &mut Value, rewrite: &mut F) -> anyhow::Result<()>
+where
+    F: FnMut(&str, &str) -> anyhow::Result<String>,
+{
+    match value {
+        Value::String(s) => {
+            if let Some((media_type, relative)) = s.split_once(PLACEHOLDER_MARKER) {
+                *s = rewrite(media_type, relative)?;
+            }
+        }
+        Value::Object(map) => {
+            for val in map.values_mut() {
+                for_each_placeholder(val, rewrite)?;
+            }
+        }
+        Value::Array(arr) => {
+            for val in arr {
+                for_each_placeholder(val, rewrite)?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// The `path` of every `storage.files` entry
+pub fn file_paths(config: &Value) -> Vec<&str> {
+    config
+        .pointer("/storage/files")
+        .and_then(|f| f.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|f| f.get("path").and_then(|p| p.as_str()))
+        .collect()
+}
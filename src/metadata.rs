@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Sidecar at the root of a decoded directory holding per-file metadata
+pub const METADATA_FILE: &str = "metadata.json";
+
+/// `storage.files` attributes kept in the sidecar; `compression` lives in
+/// the entry's `contents`
+const FILE_FIELDS: &[&str] = &["mode", "user", "group", "overwrite"];
+
+/// Metadata of each `storage.files` entry, keyed by its absolute path
+pub type FileMetadata = BTreeMap<String, Map<String, Value>>;
+
+/// Collect the mode, user, group, overwrite and compression of every
+/// `storage.files` entry that sets any of them
+pub fn collect(config: &Value) -> FileMetadata {
+    let mut metadata = FileMetadata::new();
+    for file in storage_files(config) {
+        let Some(path) = file.get("path").and_then(|p| p.as_str()) else {
+            continue;
+        };
+        let mut fields = Map::new();
+        for field in FILE_FIELDS {
+            if let Some(value) = file.get(*field).and_then(without_nulls) {
+                fields.insert(field.to_string(), value);
+            }
+        }
+        if let Some(compression) = file
+            .pointer("/contents/compression")
+            .and_then(without_nulls)
+        {
+            fields.insert("compression".to_string(), compression);
+        }
+        if !fields.is_empty() {
+            metadata.insert(path.to_string(), fields);
+        }
+    }
+    metadata
+}
+
+/// Write [`collect`] of `config` to `metadata.json` in `output_dir`. With
+/// `preserve`, paths already in an existing file keep their metadata and
+/// only new paths are added.
+pub fn write(config: &Value, output_dir: &Path, preserve: bool) -> Result<PathBuf> {
+    let mut metadata = collect(config);
+    if preserve {
+        if let Some(existing) = read(output_dir)? {
+            metadata.extend(existing);
+        }
+    }
+
+    let path = output_dir.join(METADATA_FILE);
+    fs::write(&path, serde_json::to_string_pretty(&metadata)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Read `metadata.json` from a decoded directory, if there is one
+pub fn read(dir: &Path) -> Result<Option<FileMetadata>> {
+    let path = dir.join(METADATA_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let metadata = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(metadata))
+}
+
+/// Merge sidecar metadata into the `storage.files` entries of `config`.
+///
+/// The sidecar takes precedence: a field it sets replaces the value in
+/// decoded.ign, while fields it leaves out keep their decoded.ign value.
+/// Entries for paths the config doesn't have are ignored.
+pub fn apply(config: &mut Value, metadata: &FileMetadata) {
+    let files = config
+        .pointer_mut("/storage/files")
+        .and_then(|f| f.as_array_mut());
+    for file in files.into_iter().flatten() {
+        let Some(fields) = file
+            .get("path")
+            .and_then(|p| p.as_str())
+            .and_then(|path| metadata.get(path))
+        else {
+            continue;
+        };
+        for (field, value) in fields {
+            if field == "compression" {
+                if let Some(contents) = file.get_mut("contents").filter(|c| c.is_object()) {
+                    contents["compression"] = value.clone();
+                }
+            } else {
+                file[field.as_str()] = value.clone();
+            }
+        }
+    }
+}
+
+fn storage_files(config: &Value) -> &[Value] {
+    config
+        .pointer("/storage/files")
+        .and_then(|f| f.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// `value` with null object members removed, or `None` when nothing is left
+fn without_nulls(value: &Value) -> Option<Value> {
+    match value {
+        Value::Null => None,
+        Value::Object(map) => {
+            let kept: Map<String, Value> = map
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            (!kept.is_empty()).then_some(Value::Object(kept))
+        }
+        other => Some(other.clone()),
+    }
+}
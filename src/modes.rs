@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Mode bits carried between a config and its decoded tree: the permission
+/// bits, without setuid, setgid and sticky
+const PERMISSION_BITS: u32 = 0o777;
+
+/// Set the file each `storage.files` entry's `contents` was extracted to
+/// under `output_dir` to the `mode` the entry declares. Only files in
+/// `written` are changed; entries without a mode keep the default one.
+pub fn apply(config: &Value, output_dir: &Path, written: &[PathBuf]) -> Result<()> {
+    for file in storage_files(config) {
+        let Some(path) = file.get("path").and_then(|p| p.as_str()) else {
+            continue;
+        };
+        let Some(mode) = file.get("mode").and_then(|m| m.as_u64()) else {
+            continue;
+        };
+        let target = output_dir.join(path.trim_start_matches('/'));
+        if written.contains(&target) {
+            set_mode(&target, mode as u32 & PERMISSION_BITS)?;
+        }
+    }
+    Ok(())
+}
+
+/// Give each `storage.files` entry without a `mode` the permission bits of
+/// the file its `contents` placeholder names in `files_dir`
+pub fn read_back(config: &mut Value, files_dir: &Path) -> Result<()> {
+    let files = config
+        .pointer_mut("/storage/files")
+        .and_then(|f| f.as_array_mut());
+    for file in files.into_iter().flatten() {
+        if file.get("mode").is_some_and(|m| !m.is_null()) {
+            continue;
+        }
+        let Some(relative) = file
+            .pointer("/contents/source")
+            .and_then(|s| s.as_str())
+            .and_then(|s| s.split_once(";base64-placeholder,"))
+            .map(|(_, relative)| relative)
+        else {
+            continue;
+        };
+        if let Some(mode) = file_mode(&files_dir.join(relative))? {
+            file["mode"] = Value::from(mode & PERMISSION_BITS);
+        }
+    }
+    Ok(())
+}
+
+fn storage_files(config: &Value) -> &[Value] {
+    config
+        .pointer("/storage/files")
+        .and_then(|f| f.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Failed to set mode on {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Mode of the file at `path`, if it exists
+#[cfg(unix)]
+fn file_mode(path: &Path) -> Result<Option<u32>> {
+    use std::os::unix::fs::PermissionsExt;
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(Some(metadata.permissions().mode())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read mode of {}", path.display())),
+    }
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> Result<Option<u32>> {
+    Ok(None)
+}
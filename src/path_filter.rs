@@ -0,0 +1,34 @@
+use std::str::FromStr;
+
+/// A glob over absolute Ignition paths, as given to `--include` and
+/// `--exclude`: `*` stays within one component, `**` spans components
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathGlob(glob::Pattern);
+
+impl PathGlob {
+    pub fn matches(&self, path: &str) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        self.0.matches_with(path, options)
+    }
+}
+
+impl FromStr for PathGlob {
+    type Err = anyhow::Error;
+
+    fn from_str(pattern: &str) -> anyhow::Result<Self> {
+        glob::Pattern::new(pattern)
+            .map(PathGlob)
+            .map_err(|e| anyhow::anyhow!("Invalid path glob '{}': {}", pattern, e))
+    }
+}
+
+/// Whether the source of `path` is extracted: it must match one of
+/// `include` (when there are any) and none of `exclude`, so an exclude wins
+/// over an include matching the same path
+pub fn is_selected(path: &str, include: &[PathGlob], exclude: &[PathGlob]) -> bool {
+    (include.is_empty() || include.iter().any(|glob| glob.matches(path)))
+        && !exclude.iter().any(|glob| glob.matches(path))
+}
@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Ownership and mode defaults for storage entries whose path matches a glob
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Glob matched against the entry's absolute path (`*` stays within one
+    /// component, `**` spans components)
+    pub path: String,
+    /// Mode for files that don't specify one
+    pub mode: Option<u32>,
+    /// Mode for directories that don't specify one
+    pub directory_mode: Option<u32>,
+    /// Owner uid for files, directories and links that don't specify one
+    pub uid: Option<u64>,
+    /// Group gid for files, directories and links that don't specify one
+    pub gid: Option<u64>,
+}
+
+/// Defaults loaded from an `--ownership-policy` TOML file:
+///
+/// ```toml
+/// [[rule]]
+/// path = "/etc/**"
+/// mode = 0o644
+/// uid = 0
+/// ```
+///
+/// For each attribute the first matching rule that sets it wins. Values the
+/// config already specifies are never changed.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OwnershipPolicy {
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<PolicyRule>,
+}
+
+impl OwnershipPolicy {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read ownership policy: {}", path.display()))?;
+        let policy: Self = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse ownership policy: {}", path.display()))?;
+        for rule in &policy.rules {
+            glob::Pattern::new(&rule.path)
+                .with_context(|| format!("Invalid path glob in ownership policy: {}", rule.path))?;
+        }
+        Ok(policy)
+    }
+
+    /// Fill in missing modes and ownership on the storage entries of `config`
+    pub fn apply(&self, config: &mut Value) {
+        for section in ["files", "directories", "links"] {
+            let entries = config
+                .pointer_mut(&format!("/storage/{}", section))
+                .and_then(|v| v.as_array_mut());
+            for entry in entries.into_iter().flatten() {
+                let Some(path) = entry.get("path").and_then(|p| p.as_str()) else {
+                    continue;
+                };
+                let matching: Vec<&PolicyRule> =
+                    self.rules.iter().filter(|r| matches(r, path)).collect();
+                if matching.is_empty() {
+                    continue;
+                }
+
+                let mode = match section {
+                    "files" => matching.iter().find_map(|r| r.mode),
+                    "directories" => matching.iter().find_map(|r| r.directory_mode),
+                    _ => None,
+                };
+                if let Some(mode) = mode {
+                    set_if_missing(entry, "/mode", Value::from(mode));
+                }
+                // An owner given by name already specifies ownership
+                if let Some(uid) = matching.iter().find_map(|r| r.uid) {
+                    if !has_value(entry, "/user/name") {
+                        set_if_missing(entry, "/user/id", Value::from(uid));
+                    }
+                }
+                if let Some(gid) = matching.iter().find_map(|r| r.gid) {
+                    if !has_value(entry, "/group/name") {
+                        set_if_missing(entry, "/group/id", Value::from(gid));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn matches(rule: &PolicyRule, path: &str) -> bool {
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    glob::Pattern::new(&rule.path)
+        .map(|pattern| pattern.matches_with(path, options))
+        .unwrap_or(false)
+}
+
+fn has_value(entry: &Value, pointer: &str) -> bool {
+    entry.pointer(pointer).is_some_and(|v| !v.is_null())
+}
+
+/// Set the value at `pointer` unless the config already holds a non-null
+/// value there, creating intermediate objects as needed
+fn set_if_missing(entry: &mut Value, pointer: &str, value: Value) {
+    if has_value(entry, pointer) {
+        return;
+    }
+    let mut target = entry;
+    let tokens: Vec<&str> = pointer.trim_start_matches('/').split('/').collect();
+    for token in &tokens[..tokens.len() - 1] {
+        if !target.get(*token).is_some_and(Value::is_object) {
+            target[*token] = Value::Object(Default::default());
+        }
+        target = &mut target[*token];
+    }
+    target[tokens[tokens.len() - 1]] = value;
+}
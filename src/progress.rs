@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use crate::verbosity::detail;
+
+/// Details of a single file moved between a config and the decoded tree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileEvent {
+    /// The `path` of the config entry the content belongs to
+    pub ignition_path: String,
+    /// Location of the content file on disk
+    pub file: PathBuf,
+    /// Media type of the data URL
+    pub media_type: String,
+    /// Size of the decoded content in bytes
+    pub size: usize,
+}
+
+/// Receives per-file progress from disassembly and assembly, so frontends can
+/// render progress without parsing stdout
+pub trait ProgressSink {
+    /// Called after a file has been written during disassembly
+    fn on_file_extracted(&mut self, _event: &FileEvent) {}
+
+    /// Called after a file has been embedded during assembly
+    fn on_file_embedded(&mut self, _event: &FileEvent) {}
+}
+
+/// Sink used by the CLI that prints one line per file under `--verbose`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrintProgress {
+    /// Print to stderr, keeping stdout free for an output config
+    pub to_stderr: bool,
+}
+
+impl PrintProgress {
+    /// Print `line` with the event's size and media type when verbose
+    fn print(&self, line: String, event: &FileEvent) {
+        detail(
+            self.to_stderr,
+            &format!("{} ({} bytes, {})", line, event.size, event.media_type),
+        );
+    }
+}
+
+impl ProgressSink for PrintProgress {
+    fn on_file_extracted(&mut self, event: &FileEvent) {
+        self.print(
+            format!(
+                "Extracted {} -> {}",
+                event.ignition_path,
+                event.file.display()
+            ),
+            event,
+        );
+    }
+
+    fn on_file_embedded(&mut self, event: &FileEvent) {
+        self.print(
+            format!(
+                "Embedded {} <- {}",
+                event.ignition_path,
+                event.file.display()
+            ),
+            event,
+        );
+    }
+}
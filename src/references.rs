@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Inventory of the sources a config fetches at boot, written at the root of
+/// the decoded directory
+pub const REFERENCES_FILE: &str = "references.json";
+
+/// A non-`data:` source of a config, as listed in `references.json`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Reference {
+    /// Ignition path the source belongs to
+    pub path: String,
+    /// Array field and index for entries of lists such as `append`, e.g.
+    /// `append[0]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<Value>,
+    #[serde(rename = "httpHeaders", skip_serializing_if = "Option::is_none")]
+    pub http_headers: Option<Value>,
+}
+
+/// Whether `resource` has a `source` Ignition fetches rather than embeds
+pub fn is_remote(resource: &Map<String, Value>) -> bool {
+    resource
+        .get("source")
+        .and_then(|s| s.as_str())
+        .is_some_and(|s| !s.is_empty() && !s.starts_with("data:"))
+}
+
+/// The reference for the remote `resource` of the ignition `path`
+pub fn reference(
+    resource: &Map<String, Value>,
+    path: &str,
+    entry: Option<(&str, usize)>,
+) -> Reference {
+    let field = |name: &str| resource.get(name).filter(|v| !v.is_null()).cloned();
+    Reference {
+        path: path.to_string(),
+        entry: entry.map(|(field, index)| format!("{}[{}]", field, index)),
+        source: resource
+            .get("source")
+            .and_then(|s| s.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        verification: field("verification"),
+        http_headers: field("httpHeaders"),
+    }
+}
+
+/// Write `references` to `references.json` in `output_dir`
+pub fn write(references: &[Reference], output_dir: &Path) -> Result<PathBuf> {
+    let path = output_dir.join(REFERENCES_FILE);
+    fs::write(&path, serde_json::to_string_pretty(references)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,222 @@
+use anyhow::Context;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::verification::{self, HashAlgorithm};
+
+/// A remote URL a config depends on, with every section that references it
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RemoteSource {
+    pub url: String,
+    pub sections: Vec<String>,
+}
+
+/// Collect every non-`data:` source in the config, deduplicated by URL and
+/// ordered by first appearance.
+///
+/// Sections are reported as the dotted path to the referencing entry with
+/// array indices removed, e.g. `storage.files.append` or
+/// `ignition.config.merge`.
+pub fn collect_remotes(config: &Value) -> Vec<RemoteSource> {
+    let mut order = Vec::new();
+    let mut sections: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    walk(config, &mut Vec::new(), &mut |url, section| {
+        let entry = sections.entry(url.to_string()).or_insert_with(|| {
+            order.push(url.to_string());
+            Vec::new()
+        });
+        if !entry.iter().any(|s| s == section) {
+            entry.push(section.to_string());
+        }
+    });
+
+    order
+        .into_iter()
+        .map(|url| {
+            let sections = sections.remove(&url).unwrap_or_default();
+            RemoteSource { url, sections }
+        })
+        .collect()
+}
+
+/// A `<old-base>=<new-base>` rewrite of remote source URLs, e.g. to point a
+/// config at an internal mirror
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteRewrite {
+    from: String,
+    to: String,
+}
+
+impl RemoteRewrite {
+    /// Swap the base of `url` when it lies under `from`. Only whole path
+    /// segments match, so `https://a/b` covers `https://a/b/c` but not
+    /// `https://a/bc`, and everything after the base is kept as is.
+    pub fn apply(&self, url: &str) -> Option<String> {
+        let rest = url.strip_prefix(&self.from)?;
+        if !(rest.is_empty() || rest.starts_with(['/', '?', '#'])) {
+            return None;
+        }
+        Some(format!("{}{}", self.to, rest))
+    }
+}
+
+impl FromStr for RemoteRewrite {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> anyhow::Result<Self> {
+        let (from, to) = spec
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Expected <old-base>=<new-base>, got '{}'", spec))?;
+        for base in [from, to] {
+            if !base.contains("://") {
+                anyhow::bail!("Remote base must be an absolute URL: '{}'", base);
+            }
+        }
+        Ok(Self {
+            from: from.trim_end_matches('/').to_string(),
+            to: to.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+/// Rewrite every remote source with the first matching rewrite, returning how
+/// many sources changed
+pub fn rewrite_remotes(config: &mut Value, rewrites: &[RemoteRewrite]) -> usize {
+    let mut rewritten = 0;
+    rewrite_walk(config, rewrites, &mut rewritten);
+    rewritten
+}
+
+fn rewrite_walk(value: &mut Value, rewrites: &[RemoteRewrite], rewritten: &mut usize) {
+    match value {
+        Value::Object(map) => {
+            for (key, val) in map.iter_mut() {
+                if key == "source" {
+                    if let Some(source) = val.as_str().filter(|s| !s.starts_with("data:")) {
+                        if let Some(new) = rewrites.iter().find_map(|r| r.apply(source)) {
+                            *val = Value::String(new);
+                            *rewritten += 1;
+                        }
+                    }
+                } else {
+                    rewrite_walk(val, rewrites, rewritten);
+                }
+            }
+        }
+        Value::Array(arr) => {
+            for val in arr {
+                rewrite_walk(val, rewrites, rewritten);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Replace every `http(s)://` source with a `data:` URL of the content
+/// `fetch` returns for it, returning how many sources were inlined.
+///
+/// Content is checked against an existing `verification.hash`, and resources
+/// without one get a `sha512` hash of it. `httpHeaders` only apply to remote
+/// fetches and are dropped.
+pub fn inline_remotes<F>(config: &mut Value, fetch: &mut F) -> anyhow::Result<usize>
+where
+    F: FnMut(&str) -> anyhow::Result<Vec<u8>>,
+{
+    let mut inlined = 0;
+    inline_walk(config, fetch, &mut inlined)?;
+    Ok(inlined)
+}
+
+fn inline_walk<F>(value: &mut Value, fetch: &mut F, inlined: &mut usize) -> anyhow::Result<()>
+where
+    F: FnMut(&str) -> anyhow::Result<Vec<u8>>,
+{
+    match value {
+        Value::Object(map) => {
+            let url = map
+                .get("source")
+                .and_then(|s| s.as_str())
+                .filter(|s| s.starts_with("http://") || s.starts_with("https://"))
+                .map(str::to_string);
+            if let Some(url) = url {
+                use base64::Engine;
+                let fetched = fetch(&url)?;
+                let encoded = base64::engine::general_purpose::STANDARD.encode(&fetched);
+                map.insert(
+                    "source".to_string(),
+                    Value::String(format!("data:;base64,{}", encoded)),
+                );
+                map.remove("httpHeaders");
+                inline_verification(value, &url)?;
+                *inlined += 1;
+                return Ok(());
+            }
+            for val in map.values_mut() {
+                inline_walk(val, fetch, inlined)?;
+            }
+        }
+        Value::Array(arr) => {
+            for val in arr {
+                inline_walk(val, fetch, inlined)?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Check the freshly inlined content of `resource` against its hash, or add
+/// one when it has none
+fn inline_verification(resource: &mut Value, url: &str) -> anyhow::Result<()> {
+    let content = verification::inline_content(resource)
+        .with_context(|| format!("Content fetched from {}", url))?
+        .unwrap_or_default();
+    match verification::existing_hash(resource) {
+        Some(expected) => {
+            let (algorithm, _) = verification::parse_hash(expected)?;
+            let actual = algorithm.hash(&content);
+            if actual != expected {
+                anyhow::bail!(
+                    "Content fetched from {} does not match its verification hash: expected {}, got {}",
+                    url,
+                    expected,
+                    actual
+                );
+            }
+        }
+        None => {
+            resource["verification"] =
+                serde_json::json!({ "hash": HashAlgorithm::Sha512.hash(&content) });
+        }
+    }
+    Ok(())
+}
+
+fn walk<'a, F>(value: &'a Value, keys: &mut Vec<&'a str>, found: &mut F)
+where
+    F: FnMut(&str, &str),
+{
+    match value {
+        Value::Object(map) => {
+            if let Some(source) = map.get("source").and_then(|s| s.as_str()) {
+                if !source.is_empty() && !source.starts_with("data:") {
+                    let section: Vec<&str> =
+                        keys.iter().copied().filter(|k| *k != "contents").collect();
+                    found(source, &section.join("."));
+                }
+            }
+            for (key, val) in map {
+                keys.push(key);
+                walk(val, keys, found);
+                keys.pop();
+            }
+        }
+        Value::Array(arr) => {
+            for val in arr {
+                walk(val, keys, found);
+            }
+        }
+        _ => (),
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Timestamp applied to every output under `--reproducible`, taken from
+/// `SOURCE_DATE_EPOCH` when set and the Unix epoch otherwise
+pub fn fixed_mtime() -> SystemTime {
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(0);
+    SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+/// Set the fixed mtime on every written file and on each directory between
+/// it and `root` (inclusive), so the tree is identical across runs.
+pub fn apply_fixed_mtimes(root: &Path, written: &[PathBuf]) -> Result<()> {
+    let mtime = fixed_mtime();
+    let mut directories = BTreeSet::new();
+
+    for path in written {
+        set_mtime(path, mtime)?;
+        let mut parent = path.parent();
+        while let Some(dir) = parent {
+            if !dir.starts_with(root) {
+                break;
+            }
+            directories.insert(dir.to_path_buf());
+            parent = dir.parent();
+        }
+    }
+
+    // Directories last, since writing files inside them bumps their mtime
+    for dir in directories.iter().rev() {
+        set_dir_mtime(dir, mtime)?;
+    }
+
+    Ok(())
+}
+
+pub fn set_mtime(path: &Path, mtime: SystemTime) -> Result<()> {
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|f| f.set_modified(mtime))
+        .with_context(|| format!("Failed to set modification time on {}", path.display()))
+}
+
+#[cfg(unix)]
+fn set_dir_mtime(path: &Path, mtime: SystemTime) -> Result<()> {
+    fs::File::open(path)
+        .and_then(|f| f.set_modified(mtime))
+        .with_context(|| format!("Failed to set modification time on {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn set_dir_mtime(_path: &Path, _mtime: SystemTime) -> Result<()> {
+    Ok(())
+}
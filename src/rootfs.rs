@@ -0,0 +1,227 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::checks::ensure_no_symlinks_below;
+
+/// Default mode Ignition applies to files that don't specify one
+const DEFAULT_FILE_MODE: u32 = 0o644;
+
+/// Default mode Ignition applies to directories that don't specify one
+const DEFAULT_DIR_MODE: u32 = 0o755;
+
+/// Materialize the storage section of a config as a filesystem image under
+/// `root`, so that `/etc/foo` in the config ends up at `<root>/etc/foo`.
+///
+/// Directories are created first, then files (with any `append` fragments
+/// concatenated after the contents), then links. Modes are applied on Unix;
+/// ownership is applied when the process is allowed to change it and is
+/// silently skipped otherwise. Returns the number of files written.
+pub fn materialize_rootfs(config: &Value, root: &Path) -> Result<usize> {
+    let storage = config.get("storage");
+    let entries = |section: &str| -> Vec<&Value> {
+        storage
+            .and_then(|s| s.get(section))
+            .and_then(|v| v.as_array())
+            .map(|arr| arr.iter().collect())
+            .unwrap_or_default()
+    };
+
+    let mut directory_modes = Vec::new();
+    for dir in entries("directories") {
+        let ignition_path = entry_path(dir)?;
+        let target = rootfs_path(root, ignition_path)?;
+        ensure_no_symlinks_below(root, Path::new(ignition_path))?;
+        fs::create_dir_all(&target)
+            .with_context(|| format!("Failed to create directory: {}", target.display()))?;
+        apply_ownership(dir, &target)?;
+        directory_modes.push((target, entry_mode(dir, DEFAULT_DIR_MODE)));
+    }
+
+    let mut file_counter = 0;
+    for file in entries("files") {
+        let ignition_path = entry_path(file)?;
+        let target = rootfs_path(root, ignition_path)?;
+        ensure_no_symlinks_below(root, Path::new(ignition_path))?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut content = Vec::new();
+        let resources = file.get("contents").into_iter().chain(
+            file.get("append")
+                .and_then(|a| a.as_array())
+                .into_iter()
+                .flatten(),
+        );
+        for resource in resources {
+            content.extend(resource_content(resource, ignition_path)?);
+        }
+
+        crate::checks::ensure_regular_target(&target)?;
+        fs::write(&target, content)
+            .with_context(|| format!("Failed to write file: {}", target.display()))?;
+        set_mode(&target, entry_mode(file, DEFAULT_FILE_MODE))?;
+        apply_ownership(file, &target)?;
+        file_counter += 1;
+    }
+
+    for link in entries("links") {
+        let ignition_path = entry_path(link)?;
+        let target = rootfs_path(root, ignition_path)?;
+        let link_target = link
+            .get("target")
+            .and_then(|t| t.as_str())
+            .with_context(|| format!("Link {} has no target", target.display()))?;
+        // A link made earlier in the loop may sit on the way to this one
+        if let Some(parent) = Path::new(ignition_path).parent() {
+            ensure_no_symlinks_below(root, parent)?;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        if link.get("hard").and_then(|h| h.as_bool()).unwrap_or(false) {
+            let original = rootfs_path(root, link_target)?;
+            ensure_no_symlinks_below(root, Path::new(link_target))?;
+            fs::hard_link(&original, &target)
+                .with_context(|| format!("Failed to create hard link: {}", target.display()))?;
+        } else {
+            create_symlink(link_target, &target)?;
+        }
+        apply_ownership(link, &target)?;
+    }
+
+    // Directory modes go last so restrictive modes don't block the writes above
+    for (target, mode) in directory_modes {
+        set_mode(&target, mode)?;
+    }
+
+    Ok(file_counter)
+}
+
+/// The paths `materialize_rootfs` would create under `root`, in the order it
+/// creates them. File contents are decoded so bad sources fail the same way.
+pub fn plan_rootfs(config: &Value, root: &Path) -> Result<Vec<PathBuf>> {
+    let mut planned = Vec::new();
+    for section in ["directories", "files", "links"] {
+        let entries = config
+            .pointer(&format!("/storage/{}", section))
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten();
+        for entry in entries {
+            let ignition_path = entry_path(entry)?;
+            if section == "files" {
+                let resources = entry.get("contents").into_iter().chain(
+                    entry
+                        .get("append")
+                        .and_then(|a| a.as_array())
+                        .into_iter()
+                        .flatten(),
+                );
+                for resource in resources {
+                    resource_content(resource, ignition_path)?;
+                }
+            }
+            planned.push(rootfs_path(root, ignition_path)?);
+        }
+    }
+    Ok(planned)
+}
+
+fn entry_path(entry: &Value) -> Result<&str> {
+    entry
+        .get("path")
+        .and_then(|p| p.as_str())
+        .context("Storage entry has no path")
+}
+
+fn entry_mode(entry: &Value, default: u32) -> u32 {
+    entry
+        .get("mode")
+        .and_then(|m| m.as_u64())
+        .map(|m| m as u32 & 0o7777)
+        .unwrap_or(default)
+}
+
+/// Map an absolute ignition path onto `root`, refusing paths that would escape it
+fn rootfs_path(root: &Path, ignition_path: &str) -> Result<PathBuf> {
+    let mut target = root.to_path_buf();
+    for component in Path::new(ignition_path).components() {
+        match component {
+            Component::Normal(part) => target.push(part),
+            Component::RootDir | Component::CurDir => {}
+            _ => anyhow::bail!(
+                "Refusing to materialize path outside rootfs: {}",
+                ignition_path
+            ),
+        }
+    }
+    Ok(target)
+}
+
+/// Decode the inline `source` of a resource, inflating gzip content and
+/// failing for remote sources
+fn resource_content(resource: &Value, ignition_path: &str) -> Result<Vec<u8>> {
+    let source = match resource.get("source").and_then(|s| s.as_str()) {
+        Some(source) => source,
+        None => return Ok(Vec::new()),
+    };
+    match crate::verification::inline_content(resource)
+        .with_context(|| format!("Failed to decode source for {}", ignition_path))?
+    {
+        Some(content) => Ok(content),
+        None => anyhow::bail!(
+            "Cannot materialize remote source for {}: {}",
+            ignition_path,
+            source
+        ),
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Failed to set mode on {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn apply_ownership(entry: &Value, path: &Path) -> Result<()> {
+    let uid = entry.pointer("/user/id").and_then(|id| id.as_u64());
+    let gid = entry.pointer("/group/id").and_then(|id| id.as_u64());
+    if uid.is_none() && gid.is_none() {
+        return Ok(());
+    }
+
+    match std::os::unix::fs::lchown(path, uid.map(|id| id as u32), gid.map(|id| id as u32)) {
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => Ok(()),
+        result => result.with_context(|| format!("Failed to set ownership on {}", path.display())),
+    }
+}
+
+#[cfg(not(unix))]
+fn apply_ownership(_entry: &Value, _path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn create_symlink(link_target: &str, path: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(link_target, path)
+        .with_context(|| format!("Failed to create symlink: {}", path.display()))
+}
+
+#[cfg(not(unix))]
+pub fn create_symlink(_link_target: &str, path: &Path) -> Result<()> {
+    anyhow::bail!(
+        "Symlinks are not supported on this platform: {}",
+        path.display()
+    )
+}
@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::verification::{inline_content, to_hex};
+use crate::{find_and_replace_source_with_path_update, SourceVisitor};
+
+/// Where the content of a source comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// Embedded as a `data:` URL
+    Inline,
+    /// Fetched by Ignition at provisioning time
+    Remote,
+}
+
+/// One source of a config, as listed in the SBOM
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SbomEntry {
+    /// Ignition path the source belongs to
+    pub path: String,
+    /// Array field and index for entries of lists such as `append`, e.g.
+    /// `append[0]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+    pub kind: SourceKind,
+    /// URL of a remote source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Media type of an inline source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    /// Size of inline content after decoding and decompression
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    /// Hex SHA-256 of inline content after decoding and decompression
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+}
+
+/// Visitor recording every source the disassembly walk finds
+struct SbomCollector {
+    strict_array_detection: bool,
+    entries: Vec<SbomEntry>,
+}
+
+impl SourceVisitor for SbomCollector {
+    fn visit(
+        &mut self,
+        source: &str,
+        compression: Option<&str>,
+        path: &str,
+        entry: Option<(&str, usize)>,
+    ) -> Result<String> {
+        let resource = json!({ "source": source, "compression": compression });
+        let content = inline_content(&resource).with_context(|| format!("Source of {}", path))?;
+        let media_type = content.as_ref().and_then(|_| {
+            data_url::DataUrl::process(source)
+                .ok()
+                .map(|url| url.mime_type().to_string())
+        });
+
+        self.entries.push(SbomEntry {
+            path: path.to_string(),
+            entry: entry.map(|(field, index)| format!("{}[{}]", field, index)),
+            kind: if content.is_some() {
+                SourceKind::Inline
+            } else {
+                SourceKind::Remote
+            },
+            url: content.is_none().then(|| source.to_string()),
+            media_type,
+            size: content.as_ref().map(Vec::len),
+            sha256: content.as_ref().map(|c| {
+                use sha2::Digest;
+                to_hex(&sha2::Sha256::digest(c))
+            }),
+            compression: compression.filter(|c| !c.is_empty()).map(str::to_string),
+        });
+        Ok(source.to_string())
+    }
+
+    fn strict_array_detection(&self) -> bool {
+        self.strict_array_detection
+    }
+}
+
+/// Every source of `config`, in the order disassembly walks them
+pub fn collect(config: &Value, strict_array_detection: bool) -> Result<Vec<SbomEntry>> {
+    let mut collector = SbomCollector {
+        strict_array_detection,
+        entries: Vec::new(),
+    };
+    find_and_replace_source_with_path_update(&mut config.clone(), "", "", &mut collector)?;
+    Ok(collector.entries)
+}
+
+/// Write the SBOM of `config` to `path`
+pub fn write(config: &Value, path: &Path, strict_array_detection: bool) -> Result<()> {
+    let sbom = json!({
+        "tool": "fcos-ignition-coder",
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "files": collect(config, strict_array_detection)?,
+    });
+    fs::write(path, serde_json::to_string_pretty(&sbom)?)
+        .with_context(|| format!("Failed to write SBOM: {}", path.display()))
+}
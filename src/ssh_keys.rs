@@ -0,0 +1,109 @@
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::verbosity::warn;
+
+/// Directory of the decoded tree holding each user's extracted SSH keys
+pub const PASSWD_DIR: &str = "passwd";
+
+/// Start of the placeholder that replaces a user's `sshAuthorizedKeys`
+const PLACEHOLDER_PREFIX: &str = "data:text/plain;base64-placeholder,";
+
+/// File of the decoded tree holding the keys of `user`:
+/// `passwd/<user>/authorized_keys`
+pub fn keys_file(user: &str) -> String {
+    format!("{}/{}/authorized_keys", PASSWD_DIR, user)
+}
+
+/// Replace the `sshAuthorizedKeys` of every user that has some with a
+/// one-element array holding a placeholder naming its keys file, returning
+/// the name of each user and their keys, one per line in their original
+/// order. Users whose keys are empty or span lines can't be written one per
+/// line and keep them inline.
+pub fn take_authorized_keys(config: &mut Value) -> Vec<(String, String)> {
+    let mut taken = Vec::new();
+    let users = config
+        .pointer_mut("/passwd/users")
+        .and_then(|u| u.as_array_mut());
+
+    for user in users.into_iter().flatten() {
+        let Some(name) = user.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        let name = name.to_string();
+        let Some(keys) = user
+            .get("sshAuthorizedKeys")
+            .and_then(|k| k.as_array())
+            .filter(|k| !k.is_empty())
+        else {
+            continue;
+        };
+        let keys: Option<Vec<&str>> = keys
+            .iter()
+            .map(|k| {
+                k.as_str()
+                    .filter(|k| !k.trim().is_empty() && !k.contains(['\n', '\r']))
+            })
+            .collect();
+        let Some(keys) = keys else {
+            warn(&format!(
+                "keeping the SSH keys of {} in decoded.ign, one of them is empty or spans lines",
+                name
+            ));
+            continue;
+        };
+
+        let mut text = keys.join("\n");
+        text.push('\n');
+        user["sshAuthorizedKeys"] = Value::Array(vec![Value::String(format!(
+            "{}{}",
+            PLACEHOLDER_PREFIX,
+            keys_file(&name)
+        ))]);
+        taken.push((name, text));
+    }
+    taken
+}
+
+/// Put back the keys of every user whose `sshAuthorizedKeys` is a
+/// placeholder, one key per non-blank line of the text `read` returns for
+/// its keys file. When `read` returns `None` the user loses the field.
+pub fn restore_authorized_keys<F>(config: &mut Value, mut read: F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<Option<String>>,
+{
+    let users = config
+        .pointer_mut("/passwd/users")
+        .and_then(|u| u.as_array_mut());
+
+    for user in users.into_iter().flatten() {
+        let relative = match user.get("sshAuthorizedKeys").and_then(|k| k.as_array()) {
+            Some(keys) if keys.len() == 1 => keys[0]
+                .as_str()
+                .and_then(|k| k.strip_prefix(PLACEHOLDER_PREFIX))
+                .map(str::to_string),
+            _ => None,
+        };
+        let Some(relative) = relative else {
+            continue;
+        };
+
+        match read(&relative)? {
+            Some(text) => {
+                let keys = text
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(|line| Value::String(line.to_string()))
+                    .collect();
+                user["sshAuthorizedKeys"] = Value::Array(keys);
+            }
+            None => {
+                if let Some(user) = user.as_object_mut() {
+                    user.remove("sshAuthorizedKeys");
+                }
+            }
+        }
+    }
+    Ok(())
+}
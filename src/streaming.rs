@@ -0,0 +1,168 @@
+use anyhow::{Context, Result};
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Marks a source whose content is only encoded when the config is written
+const DEFERRED_MARKER: &str = ";base64-deferred,";
+
+/// A content file `--chunked-write` streams into the output in place of
+/// its marker
+#[derive(Debug)]
+pub struct DeferredSource {
+    /// Source string standing in for the content in the config
+    pub marker: String,
+    pub media_type: String,
+    pub file: PathBuf,
+    /// Gzip the content while encoding it
+    pub gzip: bool,
+    /// Encode in the base64url alphabet
+    pub base64url: bool,
+}
+
+impl DeferredSource {
+    /// Defer the content of `file`, numbered `index` among the deferred
+    /// sources of a config
+    pub fn new(media_type: &str, file: PathBuf, gzip: bool, base64url: bool, index: usize) -> Self {
+        DeferredSource {
+            marker: format!("data:{}{}{}", media_type, DEFERRED_MARKER, index),
+            media_type: media_type.to_string(),
+            file,
+            gzip,
+            base64url,
+        }
+    }
+}
+
+/// Whether `source` is the marker of a deferred source
+pub fn is_deferred(source: &str) -> bool {
+    source.starts_with("data:") && source.contains(DEFERRED_MARKER)
+}
+
+/// The base64 payload of a data URL, when it is plain base64 that can be
+/// decoded in chunks. Payloads with percent-escapes or whitespace return
+/// `None` and go through the buffered decoder.
+pub fn base64_payload(source: &str) -> Option<&str> {
+    let (header, payload) = source.split_once(',')?;
+    if !header.to_ascii_lowercase().ends_with(";base64") {
+        return None;
+    }
+    payload
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'='))
+        .then_some(payload)
+}
+
+/// Media type parameter a placeholder carries when its source used the
+/// base64url alphabet
+pub const BASE64URL_PARAMETER: &str = ";base64url";
+
+/// The base64 payload of a data URL written in the URL-safe alphabet, with
+/// `-` and `_` in place of `+` and `/`. Payloads that use neither are
+/// taken as standard base64.
+pub fn base64url_payload(source: &str) -> Option<&str> {
+    let (header, payload) = source.split_once(',')?;
+    if !header.to_ascii_lowercase().ends_with(";base64") {
+        return None;
+    }
+    let url_safe = payload
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'='));
+    (url_safe && payload.contains(['-', '_'])).then_some(payload)
+}
+
+/// Base64 engine of the standard or the URL-safe alphabet. It pads what it
+/// encodes and decodes payloads with or without padding, which data URLs
+/// don't require.
+pub fn base64_engine(base64url: bool) -> GeneralPurpose {
+    let alphabet = if base64url {
+        &base64::alphabet::URL_SAFE
+    } else {
+        &base64::alphabet::STANDARD
+    };
+    GeneralPurpose::new(
+        alphabet,
+        GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    )
+}
+
+/// Number of bytes a base64 payload decodes to
+pub fn decoded_len(payload: &str) -> usize {
+    payload.trim_end_matches('=').len() * 3 / 4
+}
+
+/// Decode a base64 payload straight into `path` through a fixed-size buffer,
+/// returning the number of bytes written
+pub fn stream_base64_to_file(payload: &str, path: &Path) -> Result<usize> {
+    let engine = base64_engine(false);
+    let mut reader = base64::read::DecoderReader::new(payload.as_bytes(), &engine);
+
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    let written = io::copy(&mut reader, &mut writer)
+        .with_context(|| format!("Failed to decode content into {}", path.display()))?;
+    writer.flush()?;
+
+    Ok(written as usize)
+}
+
+/// Gzip encoder with a zero mtime and unknown OS byte, so the same input
+/// always produces the same stream
+pub fn gzip_encoder<W: Write>(writer: W) -> flate2::write::GzEncoder<W> {
+    flate2::GzBuilder::new()
+        .mtime(0)
+        .operating_system(255)
+        .write(writer, flate2::Compression::default())
+}
+
+/// Write the serialized config `json` to `out`, encoding the content of
+/// each deferred source through a fixed-size buffer where its marker is
+pub fn write_with_deferred(
+    json: &str,
+    deferred: &[DeferredSource],
+    out: &mut impl Write,
+) -> Result<()> {
+    let mut spans = Vec::new();
+    for source in deferred {
+        let needle = serde_json::to_string(&source.marker)?;
+        if let Some(start) = json.find(&needle) {
+            spans.push((start, start + needle.len(), source));
+        }
+    }
+    spans.sort_by_key(|(start, _, _)| *start);
+
+    let mut written = 0;
+    for (start, end, source) in spans {
+        out.write_all(&json.as_bytes()[written..start])?;
+        // The opening quote and the URL header, as serde_json escapes them
+        let header = serde_json::to_string(&format!("data:{};base64,", source.media_type))?;
+        out.write_all(&header.as_bytes()[..header.len() - 1])?;
+        encode_file(source, out)?;
+        out.write_all(b"\"")?;
+        written = end;
+    }
+    out.write_all(&json.as_bytes()[written..])?;
+    Ok(())
+}
+
+/// Base64-encode the content file of `source` into `out`
+fn encode_file(source: &DeferredSource, out: &mut impl Write) -> Result<()> {
+    let file = File::open(&source.file)
+        .with_context(|| format!("Failed to open {}", source.file.display()))?;
+    let mut reader = BufReader::new(file);
+    let engine = base64_engine(source.base64url);
+    let mut encoder = base64::write::EncoderWriter::new(out, &engine);
+    if source.gzip {
+        let mut gzip = gzip_encoder(&mut encoder);
+        io::copy(&mut reader, &mut gzip)?;
+        gzip.finish()?;
+    } else {
+        io::copy(&mut reader, &mut encoder)?;
+    }
+    encoder
+        .finish()
+        .with_context(|| format!("Failed to encode {}", source.file.display()))?;
+    Ok(())
+}
@@ -1,6 +1,12 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use crate::{assemble_ignition, disassemble_ignition, Action};
+    use crate::validate_ignition;
+    use crate::warnings::WarningKind;
+    use crate::{
+        assemble_ignition, disassemble_ignition, Action, AssembleOptions, AssembleOrder,
+        DisassembleOptions, NewlineMode, SplitLarge,
+    };
     use std::fs;
     use tempfile::TempDir;
 
@@ -28,7 +34,13 @@ mod tests {
 }"#;
         fs::write(&input_path, test_ignition).unwrap();
 
-        disassemble_ignition(&input_path, &decoded_dir, Action::New).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
 
         assert!(decoded_dir.join("decoded.ign").exists());
         assert!(decoded_dir.join("etc/test").exists());
@@ -71,7 +83,16 @@ mod tests {
         fs::create_dir_all(file_path.parent().unwrap()).unwrap();
         fs::write(file_path, file_content).unwrap();
 
-        assemble_ignition(&target_file, &ignition_dir, false, true, Action::New).unwrap();
+        assemble_ignition(
+            &target_file,
+            &ignition_dir,
+            &AssembleOptions {
+                default: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
 
         assert!(target_file.exists());
 
@@ -104,8 +125,23 @@ mod tests {
 }"#;
         fs::write(&input_path, test_ignition).unwrap();
 
-        disassemble_ignition(&input_path, &decoded_dir, Action::New).unwrap();
-        assemble_ignition(&output_path, &decoded_dir, false, true, Action::New).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        assemble_ignition(
+            &output_path,
+            &decoded_dir,
+            &AssembleOptions {
+                default: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
 
         let input_json: serde_json::Value = serde_json::from_str(test_ignition).unwrap();
         let output_json: serde_json::Value =
@@ -150,7 +186,13 @@ mod tests {
 }"#;
         fs::write(&input_path, test_ignition).unwrap();
 
-        disassemble_ignition(&input_path, &decoded_dir, Action::New).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
 
         // Check single file was created as a file
         assert!(decoded_dir.join("etc/test-single").exists());
@@ -202,7 +244,13 @@ mod tests {
         fs::write(&input_path, test_ignition).unwrap();
 
         // Disassemble
-        disassemble_ignition(&input_path, &decoded_dir, Action::New).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
 
         // Verify array structure was created
         assert!(decoded_dir.join("etc/motd").is_dir());
@@ -210,7 +258,16 @@ mod tests {
         assert!(decoded_dir.join("etc/motd/1").exists());
 
         // Assemble back
-        assemble_ignition(&output_path, &decoded_dir, false, true, Action::New).unwrap();
+        assemble_ignition(
+            &output_path,
+            &decoded_dir,
+            &AssembleOptions {
+                default: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
 
         // Parse both JSON files to compare structure
         let input_json: serde_json::Value = serde_json::from_str(test_ignition).unwrap();
@@ -273,7 +330,12 @@ mod tests {
         fs::write(decoded_dir.join("existing_file"), "old content").unwrap();
 
         // Without replace flag, should fail
-        let result = disassemble_ignition(&input_path, &decoded_dir, Action::New);
+        let result = disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        );
         assert!(result.is_err());
         assert!(result
             .unwrap_err()
@@ -281,7 +343,16 @@ mod tests {
             .contains("Target directory already exists"));
 
         // With replace flag, should succeed
-        disassemble_ignition(&input_path, &decoded_dir, Action::Replace).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions {
+                action: Action::Replace,
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
 
         // Verify old content was removed and new content exists
         assert!(!decoded_dir.join("existing_file").exists());
@@ -323,7 +394,15 @@ mod tests {
         fs::write(&target_file, "old ignition content").unwrap();
 
         // Without replace flag, should fail
-        let result = assemble_ignition(&target_file, &ignition_dir, false, true, Action::New);
+        let result = assemble_ignition(
+            &target_file,
+            &ignition_dir,
+            &AssembleOptions {
+                default: true,
+                ..Default::default()
+            },
+            None,
+        );
         assert!(result.is_err());
         assert!(result
             .unwrap_err()
@@ -331,7 +410,17 @@ mod tests {
             .contains("Target file already exists"));
 
         // With replace flag, should succeed
-        assemble_ignition(&target_file, &ignition_dir, false, true, Action::Replace).unwrap();
+        assemble_ignition(
+            &target_file,
+            &ignition_dir,
+            &AssembleOptions {
+                default: true,
+                action: Action::Replace,
+                ..Default::default()
+            },
+            None,
+        )
+        .unwrap();
 
         // Verify new content exists
         assert!(target_file.exists());
@@ -339,4 +428,5484 @@ mod tests {
         assert!(output_content.contains("data:text/plain;charset=US-ASCII;base64,dGVzdCBjb250ZW50"));
         assert!(!output_content.contains("old ignition content"));
     }
+
+    #[test]
+    fn test_abort_on_warning_kind() {
+        let temp_dir = TempDir::new().unwrap();
+        let clean_path = temp_dir.path().join("clean.ign");
+        let unknown_path = temp_dir.path().join("unknown.ign");
+
+        fs::write(&clean_path, r#"{"ignition": {"version": "3.0.0"}}"#).unwrap();
+        fs::write(
+            &unknown_path,
+            r#"{"ignition": {"version": "3.4.0"}, "storag": {}}"#,
+        )
+        .unwrap();
+
+        // Without a selected kind, an unknown-field warning stays informational
+        let unknown_dir = temp_dir.path().join("unknown");
+        disassemble_ignition(
+            &unknown_path,
+            &unknown_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert!(unknown_dir.join("decoded.ign").exists());
+
+        let options = DisassembleOptions {
+            abort_on_warning: vec![WarningKind::UnknownField],
+            action: Action::Replace,
+            ..Default::default()
+        };
+
+        // A selected kind aborts before anything is written
+        let aborted_dir = temp_dir.path().join("aborted");
+        let err = disassemble_ignition(&unknown_path, &aborted_dir, &options, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Aborting on unknown-field warning: unused key: $.storag"));
+        assert!(!aborted_dir.exists());
+
+        // A valid config of an older spec version raises no warning at all
+        let clean_dir = temp_dir.path().join("clean");
+        let report = disassemble_ignition(&clean_path, &clean_dir, &options, None).unwrap();
+        assert!(report.warnings.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_disassemble_rootfs() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let rootfs_dir = temp_dir.path().join("rootfs");
+
+        let test_ignition = r#"{
+  "ignition": {
+    "version": "3.4.0"
+  },
+  "storage": {
+    "directories": [
+      {
+        "path": "/var/lib/app",
+        "mode": 448
+      }
+    ],
+    "files": [
+      {
+        "path": "/usr/local/bin/setup.sh",
+        "mode": 493,
+        "contents": {
+          "source": "data:,echo%20hi"
+        }
+      },
+      {
+        "path": "/etc/motd",
+        "contents": {
+          "source": "data:,Hello"
+        },
+        "append": [
+          {
+            "source": "data:,%20World"
+          }
+        ]
+      }
+    ],
+    "links": [
+      {
+        "path": "/usr/local/bin/setup",
+        "target": "/usr/local/bin/setup.sh"
+      }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            rootfs: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &rootfs_dir, &options, None).unwrap();
+
+        let mode = |path: &str| {
+            fs::symlink_metadata(rootfs_dir.join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o7777
+        };
+
+        // Files land at their absolute paths with declared or default modes
+        let script = rootfs_dir.join("usr/local/bin/setup.sh");
+        assert_eq!(fs::read_to_string(script).unwrap(), "echo hi");
+        assert_eq!(mode("usr/local/bin/setup.sh"), 0o755);
+        assert_eq!(
+            fs::read_to_string(rootfs_dir.join("etc/motd")).unwrap(),
+            "Hello World"
+        );
+        assert_eq!(mode("etc/motd"), 0o644);
+
+        // Directories and links are real filesystem objects
+        assert!(rootfs_dir.join("var/lib/app").is_dir());
+        assert_eq!(mode("var/lib/app"), 0o700);
+        assert_eq!(
+            fs::read_link(rootfs_dir.join("usr/local/bin/setup")).unwrap(),
+            std::path::Path::new("/usr/local/bin/setup.sh")
+        );
+
+        // The rootfs is an image of the config, not a decoded tree
+        assert!(!rootfs_dir.join("decoded.ign").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rootfs_refuses_writes_through_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let outside = temp_dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("secret"), "secret").unwrap();
+
+        let chained = |second: serde_json::Value| {
+            serde_json::json!({
+                "ignition": { "version": "3.4.0" },
+                "storage": {
+                    "links": [
+                        { "path": "/a", "target": outside.to_str().unwrap() },
+                        second
+                    ]
+                }
+            })
+        };
+        let configs = [
+            // A symlink below a link made earlier in the run
+            chained(serde_json::json!({ "path": "/a/pwned", "target": "anything" })),
+            // A hard link to a file reached through that link
+            chained(serde_json::json!({ "path": "/b", "target": "/a/secret", "hard": true })),
+        ];
+        for (i, config) in configs.iter().enumerate() {
+            let rootfs_dir = temp_dir.path().join(format!("rootfs-{}", i));
+            fs::write(&input_path, config.to_string()).unwrap();
+            let options = DisassembleOptions {
+                rootfs: true,
+                ..Default::default()
+            };
+            let err = disassemble_ignition(&input_path, &rootfs_dir, &options, None).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains("Refusing to write through symlink"),
+                "{}",
+                err
+            );
+            assert!(!rootfs_dir.join("b").exists());
+        }
+        assert!(!outside.join("pwned").is_symlink());
+
+        // Files and directories under a link are refused too
+        let rootfs_dir = temp_dir.path().join("rootfs-files");
+        fs::create_dir(&rootfs_dir).unwrap();
+        std::os::unix::fs::symlink(&outside, rootfs_dir.join("etc")).unwrap();
+        for section in ["files", "directories"] {
+            let config = serde_json::json!({
+                "ignition": { "version": "3.4.0" },
+                "storage": { (section): [ { "path": "/etc/planted" } ] }
+            });
+            fs::write(&input_path, config.to_string()).unwrap();
+            let options = DisassembleOptions {
+                rootfs: true,
+                action: Action::Add,
+                ..Default::default()
+            };
+            assert!(disassemble_ignition(&input_path, &rootfs_dir, &options, None).is_err());
+        }
+        assert!(!outside.join("planted").exists());
+
+        // The --emit-* outputs describe a decoded tree
+        let options = DisassembleOptions {
+            rootfs: true,
+            emit_gitattributes: true,
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &rootfs_dir, &options, None).unwrap_err();
+        assert!(err.to_string().contains("don't apply to --rootfs"));
+    }
+
+    #[test]
+    fn test_assemble_preserve_unknown() {
+        let temp_dir = TempDir::new().unwrap();
+        let ignition_dir = temp_dir.path().join("ignition");
+        fs::create_dir(&ignition_dir).unwrap();
+
+        let decoded_ign = r#"{
+  "ignition": {
+    "version": "3.4.0"
+  },
+  "x-comment": "maintained by ops",
+  "storage": {
+    "files": [
+      {
+        "path": "/etc/test",
+        "x-owner-team": "infra",
+        "contents": {
+          "source": "data:text/plain;base64-placeholder,etc/test"
+        }
+      }
+    ]
+  }
+}"#;
+        fs::write(ignition_dir.join("decoded.ign"), decoded_ign).unwrap();
+        fs::create_dir_all(ignition_dir.join("etc")).unwrap();
+        fs::write(ignition_dir.join("etc/test"), "test content").unwrap();
+
+        // Without the flag the parser drops the unknown fields
+        let lossy_file = temp_dir.path().join("lossy.ign");
+        assemble_ignition(
+            &lossy_file,
+            &ignition_dir,
+            &AssembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        let lossy: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&lossy_file).unwrap()).unwrap();
+        assert!(lossy.get("x-comment").is_none());
+
+        // With the flag they are restored while sources are still embedded
+        let target_file = temp_dir.path().join("output.ign");
+        let options = AssembleOptions {
+            preserve_unknown: true,
+            ..Default::default()
+        };
+        assemble_ignition(&target_file, &ignition_dir, &options, None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&target_file).unwrap()).unwrap();
+        assert_eq!(output["x-comment"], "maintained by ops");
+        assert_eq!(output["storage"]["files"][0]["x-owner-team"], "infra");
+        assert_eq!(
+            output["storage"]["files"][0]["contents"]["source"],
+            "data:text/plain;base64,dGVzdCBjb250ZW50"
+        );
+    }
+
+    #[test]
+    fn test_assemble_split_large() {
+        let temp_dir = TempDir::new().unwrap();
+        let ignition_dir = temp_dir.path().join("ignition");
+        fs::create_dir(&ignition_dir).unwrap();
+        let target_file = temp_dir.path().join("output.ign");
+
+        let decoded_ign = r#"{
+  "ignition": {
+    "version": "3.4.0"
+  },
+  "storage": {
+    "files": [
+      {
+        "path": "/etc/small",
+        "contents": {
+          "source": "data:text/plain;base64-placeholder,etc/small"
+        }
+      },
+      {
+        "path": "/opt/big.img",
+        "contents": {
+          "source": "data:application/octet-stream;base64-placeholder,opt/big.img"
+        }
+      }
+    ]
+  }
+}"#;
+        fs::write(ignition_dir.join("decoded.ign"), decoded_ign).unwrap();
+        fs::create_dir_all(ignition_dir.join("etc")).unwrap();
+        fs::create_dir_all(ignition_dir.join("opt")).unwrap();
+        fs::write(ignition_dir.join("etc/small"), "tiny").unwrap();
+        let big_content = vec![7u8; 4096];
+        fs::write(ignition_dir.join("opt/big.img"), &big_content).unwrap();
+
+        let options = AssembleOptions {
+            split_large: Some(SplitLarge {
+                threshold: 1024,
+                base_url: "https://mirror.example.com/ign/".to_string(),
+            }),
+            ..Default::default()
+        };
+        assemble_ignition(&target_file, &ignition_dir, &options, None).unwrap();
+
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&target_file).unwrap()).unwrap();
+        let files = &output["storage"]["files"];
+        assert_eq!(
+            files[0]["contents"]["source"],
+            "data:text/plain;base64,dGlueQ=="
+        );
+        assert_eq!(
+            files[1]["contents"]["source"],
+            "https://mirror.example.com/ign/opt/big.img"
+        );
+
+        // The externalized content and its upload manifest sit next to the output
+        let split_dir = temp_dir.path().join("output.split");
+        assert_eq!(
+            fs::read(split_dir.join("opt/big.img")).unwrap(),
+            big_content
+        );
+        let manifest: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(split_dir.join("upload-manifest.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(manifest.as_array().unwrap().len(), 1);
+        assert_eq!(
+            manifest[0]["url"],
+            "https://mirror.example.com/ign/opt/big.img"
+        );
+        assert_eq!(manifest[0]["size"], 4096);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_transform_cmd_identity() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = r#"{
+  "ignition": {
+    "version": "3.4.0"
+  },
+  "storage": {
+    "files": [
+      {
+        "path": "/etc/test",
+        "contents": {
+          "source": "data:text/plain;charset=US-ASCII;base64,dGVzdCBjb250ZW50"
+        }
+      }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let cat = std::path::PathBuf::from("cat");
+        let options = DisassembleOptions {
+            transform_cmd: Some(cat.clone()),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/test")).unwrap(),
+            "test content"
+        );
+
+        let options = AssembleOptions {
+            default: true,
+            transform_cmd: Some(cat),
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let input_json: serde_json::Value = serde_json::from_str(test_ignition).unwrap();
+        let output_json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_path).unwrap()).unwrap();
+        assert_eq!(input_json, output_json);
+
+        // A failing transform aborts the run
+        let options = DisassembleOptions {
+            action: Action::Replace,
+            transform_cmd: Some(std::path::PathBuf::from("false")),
+            ..Default::default()
+        };
+        let result = disassemble_ignition(&input_path, &decoded_dir, &options, None);
+        assert!(format!("{:#}", result.unwrap_err())
+            .contains("Transform command false failed for /etc/test"));
+    }
+
+    #[test]
+    fn test_verify_paths_unique() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": {
+    "version": "3.4.0"
+  },
+  "storage": {
+    "directories": [
+      {
+        "path": "/etc/app"
+      }
+    ],
+    "files": [
+      {
+        "path": "/etc/app",
+        "contents": {
+          "source": "data:,conflict"
+        }
+      }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            verify_paths_unique: true,
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &decoded_dir, &options, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("/etc/app is claimed by storage.files[0], storage.directories[0]"));
+        assert!(!decoded_dir.exists());
+    }
+
+    #[test]
+    fn test_disassemble_reproducible() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+
+        let test_ignition = r#"{
+  "ignition": {
+    "version": "3.4.0"
+  },
+  "storage": {
+    "files": [
+      {
+        "path": "/etc/test",
+        "contents": {
+          "source": "data:,test%20content"
+        }
+      },
+      {
+        "path": "/etc/motd",
+        "append": [
+          {
+            "source": "data:,Hello"
+          }
+        ]
+      }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            reproducible: true,
+            ..Default::default()
+        };
+
+        // Snapshot every file's relative path, bytes and mtime under a tree
+        fn snapshot(
+            root: &std::path::Path,
+        ) -> Vec<(std::path::PathBuf, Vec<u8>, std::time::SystemTime)> {
+            let mut entries = Vec::new();
+            let mut pending = vec![root.to_path_buf()];
+            while let Some(dir) = pending.pop() {
+                for entry in fs::read_dir(&dir).unwrap() {
+                    let path = entry.unwrap().path();
+                    let metadata = fs::metadata(&path).unwrap();
+                    let relative = path.strip_prefix(root).unwrap().to_path_buf();
+                    if metadata.is_dir() {
+                        pending.push(path);
+                    } else {
+                        entries.push((
+                            relative,
+                            fs::read(&path).unwrap(),
+                            metadata.modified().unwrap(),
+                        ));
+                    }
+                }
+            }
+            entries.sort();
+            entries
+        }
+
+        let first_dir = temp_dir.path().join("first");
+        let second_dir = temp_dir.path().join("second");
+        disassemble_ignition(&input_path, &first_dir, &options, None).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        disassemble_ignition(&input_path, &second_dir, &options, None).unwrap();
+
+        let first = snapshot(&first_dir);
+        assert_eq!(first.len(), 4);
+        assert!(first
+            .iter()
+            .any(|(path, _, _)| path == std::path::Path::new("manifest.json")));
+        assert_eq!(first, snapshot(&second_dir));
+        assert_eq!(
+            fs::metadata(first_dir.join("etc"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            fs::metadata(second_dir.join("etc"))
+                .unwrap()
+                .modified()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_max_input_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{"ignition": {"version": "3.4.0"}}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            max_input_size: Some(16),
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap_err();
+        assert!(format!("{:#}", err).contains("exceeding the maximum input size of 16 bytes"));
+        assert!(!decoded_dir.exists());
+
+        // A limit at least as large as the input is accepted
+        let options = DisassembleOptions {
+            max_input_size: Some(test_ignition.len() as u64),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+    }
+
+    #[test]
+    fn test_decode_unit_contents() {
+        use base64::Engine;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let unit_text = "[Unit]\nDescription=Hello\n\n[Service]\nExecStart=/usr/bin/echo hello\n";
+        let encoded = base64::engine::general_purpose::STANDARD.encode(unit_text);
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "systemd": {
+                "units": [
+                    { "name": "hello.service", "enabled": true, "contents": encoded },
+                    { "name": "plain.service", "contents": "[Unit]\nDescription=Plain\n" }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            decode_unit_contents: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        // The encoded unit is readable plain text; the plain one is untouched
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        assert_eq!(decoded["systemd"]["units"][0]["contents"], unit_text);
+        assert_eq!(
+            decoded["systemd"]["units"][1]["contents"],
+            "[Unit]\nDescription=Plain\n"
+        );
+        let encodings = fs::read_to_string(decoded_dir.join("unit-encodings.json")).unwrap();
+        assert!(encodings.contains(r#""hello.service": "base64""#));
+        assert!(!encodings.contains("plain.service"));
+
+        // Assembly restores the original encoding
+        let options = AssembleOptions {
+            default: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_path).unwrap()).unwrap();
+        assert_eq!(output, test_ignition);
+    }
+
+    #[test]
+    fn test_collect_remotes() {
+        let config = serde_json::json!({
+            "ignition": {
+                "version": "3.4.0",
+                "config": {
+                    "merge": [{ "source": "https://example.com/base.ign" }]
+                },
+                "security": {
+                    "tls": {
+                        "certificateAuthorities": [{ "source": "https://example.com/ca.pem" }]
+                    }
+                }
+            },
+            "storage": {
+                "files": [
+                    {
+                        "path": "/etc/app.conf",
+                        "contents": { "source": "https://example.com/app.conf" }
+                    },
+                    {
+                        "path": "/etc/inline",
+                        "contents": { "source": "data:,inline" }
+                    },
+                    {
+                        "path": "/etc/log",
+                        "append": [
+                            { "source": "tftp://10.0.0.1/log" },
+                            { "source": "https://example.com/app.conf" }
+                        ]
+                    }
+                ]
+            }
+        });
+
+        let remotes = crate::remotes::collect_remotes(&config);
+        let urls: Vec<&str> = remotes.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/base.ign",
+                "https://example.com/ca.pem",
+                "https://example.com/app.conf",
+                "tftp://10.0.0.1/log",
+            ]
+        );
+        assert_eq!(remotes[0].sections, vec!["ignition.config.merge"]);
+        assert_eq!(
+            remotes[1].sections,
+            vec!["ignition.security.tls.certificateAuthorities"]
+        );
+        assert_eq!(
+            remotes[2].sections,
+            vec!["storage.files", "storage.files.append"]
+        );
+    }
+
+    #[test]
+    fn test_assemble_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": {
+    "version": "3.4.0"
+  },
+  "storage": {
+    "files": [
+      { "path": "/etc/b", "contents": { "source": "data:,b" } },
+      { "path": "/etc/c", "contents": { "source": "data:,c" } },
+      { "path": "/etc/a", "contents": { "source": "data:,a" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        // Reorder the decoded config by hand, as an editor might
+        let decoded_path = decoded_dir.join("decoded.ign");
+        let mut decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&decoded_path).unwrap()).unwrap();
+        decoded["storage"]["files"]
+            .as_array_mut()
+            .unwrap()
+            .swap(0, 1);
+        fs::write(&decoded_path, decoded.to_string()).unwrap();
+
+        let assembled_order = |order: AssembleOrder| {
+            let target_file = temp_dir.path().join(format!("{:?}.ign", order));
+            let options = AssembleOptions {
+                order,
+                ..Default::default()
+            };
+            assemble_ignition(&target_file, &decoded_dir, &options, None).unwrap();
+            let output: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(target_file).unwrap()).unwrap();
+            output["storage"]["files"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|f| f["path"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            assembled_order(AssembleOrder::Source),
+            ["/etc/c", "/etc/b", "/etc/a"]
+        );
+        assert_eq!(
+            assembled_order(AssembleOrder::Sorted),
+            ["/etc/a", "/etc/b", "/etc/c"]
+        );
+        assert_eq!(
+            assembled_order(AssembleOrder::Manifest),
+            ["/etc/b", "/etc/c", "/etc/a"]
+        );
+    }
+
+    #[test]
+    fn test_directory_arguments_that_are_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        fs::write(&input_path, r#"{"ignition": {"version": "3.4.0"}}"#).unwrap();
+        let plain_file = temp_dir.path().join("plain");
+        fs::write(&plain_file, "not a directory").unwrap();
+
+        for action in [Action::New, Action::Add, Action::Replace] {
+            let options = DisassembleOptions {
+                action,
+                ..Default::default()
+            };
+            let err = disassemble_ignition(&input_path, &plain_file, &options, None).unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("Target exists and is a file, not a directory"));
+        }
+        assert_eq!(fs::read_to_string(&plain_file).unwrap(), "not a directory");
+
+        let target_file = temp_dir.path().join("output.ign");
+        let err = assemble_ignition(&target_file, &plain_file, &AssembleOptions::default(), None)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Ignition directory exists and is a file, not a directory"));
+        assert!(!target_file.exists());
+    }
+
+    #[test]
+    fn test_merge_reference_verification_roundtrip() {
+        use sha2::{Digest, Sha512};
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let merged = r#"{"ignition":{"version":"3.4.0"}}"#;
+        let merged_hash = format!(
+            "sha512-{}",
+            crate::verification::to_hex(&Sha512::digest(merged))
+        );
+        let test_ignition = serde_json::json!({
+            "ignition": {
+                "version": "3.4.0",
+                "config": {
+                    "merge": [{
+                        "source": "data:application/json,%7B%22ignition%22%3A%7B%22version%22%3A%223.4.0%22%7D%7D",
+                        "verification": { "hash": merged_hash }
+                    }]
+                }
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        let options = AssembleOptions {
+            default: true,
+            ..Default::default()
+        };
+        let merge_hash = |path: &std::path::Path| {
+            let output: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+            output["ignition"]["config"]["merge"][0]["verification"]["hash"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+
+        // An untouched round trip keeps the original hash
+        let unchanged_path = temp_dir.path().join("unchanged.ign");
+        assemble_ignition(&unchanged_path, &decoded_dir, &options, None).unwrap();
+        assert_eq!(merge_hash(&unchanged_path), merged_hash);
+
+        // Editing the extracted merge config recomputes the hash
+        let edited = r#"{"ignition":{"version":"3.4.0"},"passwd":{}}"#;
+        let extracted = crate::generated_file_name("application/json", merged.as_bytes(), 0);
+        fs::write(decoded_dir.join(extracted), edited).unwrap();
+        let edited_path = temp_dir.path().join("edited.ign");
+        assemble_ignition(&edited_path, &decoded_dir, &options, None).unwrap();
+        assert_eq!(
+            merge_hash(&edited_path),
+            format!(
+                "sha512-{}",
+                crate::verification::to_hex(&Sha512::digest(edited))
+            )
+        );
+    }
+
+    #[test]
+    fn test_quiet_warnings_collapses_repeats() {
+        use crate::warnings::{format_warnings, ConfigWarning};
+
+        let warning = |message: &str| ConfigWarning {
+            kind: WarningKind::UnknownField,
+            message: message.to_string(),
+        };
+        let warnings = vec![
+            warning("unused key: $.x"),
+            warning("unused key: $.y"),
+            warning("unused key: $.x"),
+            warning("unused key: $.x"),
+        ];
+
+        assert_eq!(
+            format_warnings(&warnings, Some(3)),
+            vec![
+                "Warning: 3 identical warnings: unused key: $.x",
+                "Warning: unused key: $.y",
+            ]
+        );
+
+        // Below the threshold every occurrence is still printed
+        assert_eq!(format_warnings(&warnings, Some(4)).len(), 4);
+        assert_eq!(format_warnings(&warnings, None).len(), 4);
+    }
+
+    #[test]
+    fn test_disassemble_from_base64_file() {
+        use base64::Engine;
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } }
+    ]
+  }
+}"#;
+        let options = DisassembleOptions {
+            from_base64_file: true,
+            ..Default::default()
+        };
+
+        // Plain base64, wrapped the way `base64` wraps long lines
+        let encoded = base64::engine::general_purpose::STANDARD.encode(test_ignition);
+        let wrapped: Vec<String> = encoded
+            .as_bytes()
+            .chunks(76)
+            .map(|c| String::from_utf8(c.to_vec()).unwrap())
+            .collect();
+        let input_path = temp_dir.path().join("userdata.b64");
+        fs::write(&input_path, wrapped.join("\n")).unwrap();
+        let decoded_dir = temp_dir.path().join("decoded");
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/test")).unwrap(),
+            "test content"
+        );
+
+        // Base64 of a gzipped config
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(test_ignition.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        let gz_input_path = temp_dir.path().join("userdata.gz.b64");
+        fs::write(
+            &gz_input_path,
+            base64::engine::general_purpose::STANDARD.encode(gzipped),
+        )
+        .unwrap();
+        let gz_decoded_dir = temp_dir.path().join("decoded-gz");
+        disassemble_ignition(&gz_input_path, &gz_decoded_dir, &options, None).unwrap();
+        assert_eq!(
+            fs::read_to_string(gz_decoded_dir.join("etc/test")).unwrap(),
+            "test content"
+        );
+    }
+
+    #[test]
+    fn test_progress_sink_receives_file_events() {
+        use crate::progress::{FileEvent, ProgressSink};
+
+        #[derive(Default)]
+        struct Capture {
+            extracted: Vec<FileEvent>,
+            embedded: Vec<FileEvent>,
+        }
+
+        impl ProgressSink for Capture {
+            fn on_file_extracted(&mut self, event: &FileEvent) {
+                self.extracted.push(event.clone());
+            }
+
+            fn on_file_embedded(&mut self, event: &FileEvent) {
+                self.embedded.push(event.clone());
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } },
+      { "path": "/etc/motd", "append": [ { "source": "data:,a" }, { "source": "data:,bc" } ] }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let mut capture = Capture::default();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            Some(&mut capture),
+        )
+        .unwrap();
+
+        assert_eq!(capture.extracted.len(), 3);
+        let test_event = capture
+            .extracted
+            .iter()
+            .find(|e| e.ignition_path == "/etc/test")
+            .unwrap();
+        assert_eq!(test_event.file, decoded_dir.join("etc/test"));
+        assert_eq!(test_event.size, 12);
+        assert_eq!(test_event.media_type, "text/plain;charset=US-ASCII");
+
+        assemble_ignition(
+            &temp_dir.path().join("output.ign"),
+            &decoded_dir,
+            &AssembleOptions::default(),
+            Some(&mut capture),
+        )
+        .unwrap();
+        assert_eq!(capture.embedded.len(), 3);
+    }
+
+    #[test]
+    fn test_overwrite_mode_on_conflicting_file() {
+        use crate::OverwriteMode;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,new%20content" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let run = |mode: OverwriteMode| {
+            let decoded_dir = temp_dir.path().join(format!("decoded-{:?}", mode));
+            fs::create_dir_all(decoded_dir.join("etc")).unwrap();
+            fs::write(decoded_dir.join("etc/test"), "old content").unwrap();
+
+            let options = DisassembleOptions {
+                action: Action::Add,
+                overwrite_mode: mode,
+                ..Default::default()
+            };
+            let result = disassemble_ignition(&input_path, &decoded_dir, &options, None);
+            let content = fs::read_to_string(decoded_dir.join("etc/test")).unwrap();
+            (result, content)
+        };
+
+        let (result, content) = run(OverwriteMode::Overwrite);
+        result.unwrap();
+        assert_eq!(content, "new content");
+
+        let (result, content) = run(OverwriteMode::Skip);
+        result.unwrap();
+        assert_eq!(content, "old content");
+
+        let (result, content) = run(OverwriteMode::Error);
+        let err = format!("{:#}", result.unwrap_err());
+        assert!(
+            err.contains("Refusing to overwrite existing file"),
+            "{}",
+            err
+        );
+        assert_eq!(content, "old content");
+    }
+
+    #[test]
+    fn test_emit_dockerfile_copy_snippet() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let dockerfile_path = temp_dir.path().join("Containerfile.snippet");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } },
+      { "path": "/etc/my app.conf", "contents": { "source": "data:,x" } },
+      { "path": "/etc/remote", "contents": { "source": "https://example.com/remote" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            emit_dockerfile: Some(dockerfile_path.clone()),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let snippet = fs::read_to_string(&dockerfile_path).unwrap();
+        let copies: Vec<&str> = snippet.lines().filter(|l| l.starts_with("COPY")).collect();
+        assert_eq!(
+            copies,
+            vec![
+                "COPY etc/test /etc/test",
+                r#"COPY ["etc/my app.conf", "/etc/my app.conf"]"#,
+            ]
+        );
+        assert!(decoded_dir.join("etc/test").is_file());
+        assert!(decoded_dir.join("etc/my app.conf").is_file());
+    }
+
+    #[test]
+    fn test_version_range_skips_out_of_range_configs() {
+        use crate::version_range::VersionRange;
+
+        let temp_dir = TempDir::new().unwrap();
+        let range: VersionRange = ">=3.3,<3.5".parse().unwrap();
+        assert!("3.3.0,bogus".parse::<VersionRange>().is_err());
+
+        let options = DisassembleOptions {
+            version_range: Some(range.clone()),
+            ..Default::default()
+        };
+
+        for version in ["3.2.0", "3.3.0", "3.4.0", "3.5.0"] {
+            let input_path = temp_dir.path().join(format!("{}.ign", version));
+            let config = format!(
+                r#"{{
+  "ignition": {{ "version": "{}" }},
+  "storage": {{
+    "files": [
+      {{ "path": "/etc/test", "contents": {{ "source": "data:,test%20content" }} }}
+    ]
+  }}
+}}"#,
+                version
+            );
+            fs::write(&input_path, config).unwrap();
+
+            let decoded_dir = temp_dir.path().join(format!("decoded-{}", version));
+            disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+            let expected = version == "3.3.0" || version == "3.4.0";
+            assert_eq!(decoded_dir.exists(), expected, "version {}", version);
+        }
+
+        // Assemble skips before touching an existing target
+        let target_file = temp_dir.path().join("output.ign");
+        fs::write(&target_file, "existing").unwrap();
+        let options = AssembleOptions {
+            action: Action::Replace,
+            version_range: Some(">=3.5".parse().unwrap()),
+            ..Default::default()
+        };
+        assemble_ignition(
+            &target_file,
+            &temp_dir.path().join("decoded-3.4.0"),
+            &options,
+            None,
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&target_file).unwrap(), "existing");
+    }
+
+    #[test]
+    fn test_decode_verification_check() {
+        use crate::verification::HashAlgorithm;
+
+        let temp_dir = TempDir::new().unwrap();
+        let good_hash = HashAlgorithm::Sha512.hash(b"test content");
+        let bad_hash = HashAlgorithm::Sha256.hash(b"something else");
+
+        let config = |second_hash: &str| {
+            format!(
+                r#"{{
+  "ignition": {{ "version": "3.4.0" }},
+  "storage": {{
+    "files": [
+      {{ "path": "/etc/good", "contents": {{ "source": "data:,test%20content", "verification": {{ "hash": "{}" }} }} }},
+      {{ "path": "/etc/bad", "contents": {{ "source": "data:,test%20content", "verification": {{ "hash": "{}" }} }} }}
+    ]
+  }}
+}}"#,
+                good_hash, second_hash
+            )
+        };
+
+        let options = DisassembleOptions {
+            decode_verification_check: true,
+            ..Default::default()
+        };
+
+        let matching_path = temp_dir.path().join("matching.ign");
+        fs::write(&matching_path, config(&good_hash)).unwrap();
+        disassemble_ignition(
+            &matching_path,
+            &temp_dir.path().join("decoded-matching"),
+            &options,
+            None,
+        )
+        .unwrap();
+
+        let mismatched_path = temp_dir.path().join("mismatched.ign");
+        let mismatched_dir = temp_dir.path().join("decoded-mismatched");
+        fs::write(&mismatched_path, config(&bad_hash)).unwrap();
+        let err = disassemble_ignition(&mismatched_path, &mismatched_dir, &options, None)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("storage.files[1].contents (/etc/bad)"),
+            "{}",
+            err
+        );
+        assert!(err.contains(&bad_hash), "{}", err);
+        assert!(!err.contains("/etc/good"), "{}", err);
+        assert!(!mismatched_dir.exists());
+
+        // --strict fails the same way
+        let strict = DisassembleOptions {
+            strict: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&mismatched_path, &mismatched_dir, &strict, None).unwrap_err();
+        assert!(!mismatched_dir.exists());
+
+        // Otherwise mismatches are only reported
+        let report = disassemble_ignition(
+            &mismatched_path,
+            &mismatched_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(report.hash_mismatches.len(), 1);
+        assert!(report.hash_mismatches[0].starts_with("storage.files[1].contents (/etc/bad)"));
+        assert!(report.hash_mismatches[0].contains(&bad_hash));
+        assert_eq!(
+            fs::read_to_string(mismatched_dir.join("etc/bad")).unwrap(),
+            "test content"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_refuses_to_extract_over_fifo() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        fs::create_dir_all(decoded_dir.join("etc")).unwrap();
+        let fifo_path = decoded_dir.join("etc/test");
+        let status = std::process::Command::new("mkfifo")
+            .arg(&fifo_path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let options = DisassembleOptions {
+            action: Action::Add,
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap_err();
+        let err = format!("{:#}", err);
+        assert!(
+            err.contains("Refusing to write over existing named pipe"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_unsupported_placeholder_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let target_file = temp_dir.path().join("output.ign");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        let manifest_path = decoded_dir.join("manifest.json");
+        let mut manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest["placeholder_format"], 1);
+        assert_eq!(manifest["coder_version"], env!("CARGO_PKG_VERSION"));
+
+        manifest["placeholder_format"] = serde_json::json!(99);
+        fs::write(&manifest_path, manifest.to_string()).unwrap();
+
+        let err = assemble_ignition(
+            &target_file,
+            &decoded_dir,
+            &AssembleOptions::default(),
+            None,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.contains("Unsupported placeholder format revision 99"),
+            "{}",
+            err
+        );
+        assert!(!target_file.exists());
+    }
+
+    #[test]
+    fn test_ownership_policy_fills_missing_mode() {
+        use crate::policy::OwnershipPolicy;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let policy_path = temp_dir.path().join("policy.toml");
+
+        fs::write(
+            &policy_path,
+            r#"
+[[rule]]
+path = "/etc/**"
+mode = 0o644
+uid = 0
+
+[[rule]]
+path = "/**"
+mode = 0o600
+"#,
+        )
+        .unwrap();
+        let policy = OwnershipPolicy::load(&policy_path).unwrap();
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/app/test", "contents": { "source": "data:,a" } },
+      { "path": "/etc/explicit", "mode": 493, "contents": { "source": "data:,b" } },
+      { "path": "/var/other", "contents": { "source": "data:,c" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            ownership_policy: Some(policy.clone()),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        let files = decoded["storage"]["files"].as_array().unwrap();
+        assert_eq!(files[0]["mode"], 0o644);
+        assert_eq!(files[0]["user"]["id"], 0);
+        assert_eq!(files[1]["mode"], 0o755);
+        assert_eq!(files[2]["mode"], 0o600);
+        assert!(files[2]["user"].is_null());
+
+        // The policy is also consulted when embedding
+        let bare_dir = temp_dir.path().join("bare");
+        disassemble_ignition(&input_path, &bare_dir, &DisassembleOptions::default(), None).unwrap();
+        let output_path = temp_dir.path().join("output.ign");
+        let options = AssembleOptions {
+            ownership_policy: Some(policy),
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &bare_dir, &options, None).unwrap();
+        let assembled: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(assembled["storage"]["files"][0]["mode"], 0o644);
+    }
+
+    #[test]
+    fn test_fuzz_round_trips() {
+        use crate::fuzz::{random_config, run_fuzz};
+
+        assert_eq!(random_config(7), random_config(7));
+        run_fuzz(25, 1234, None).unwrap();
+    }
+
+    #[test]
+    fn test_keep_going_isolates_failed_sections() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+
+        let test_ignition = r#"{
+  "ignition": {
+    "version": "3.4.0",
+    "config": { "merge": [ { "source": "data:,%7B%7D" } ] }
+  },
+  "storage": {
+    "files": [
+      { "path": "/etc/good", "contents": { "source": "data:,good" } },
+      { "path": "/etc/broken", "contents": { "source": "data:no-comma" } }
+    ]
+  },
+  "systemd": {
+    "units": [ { "name": "test.service", "enabled": true } ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        // Without --keep-going the broken entry aborts the run
+        let strict_dir = temp_dir.path().join("strict");
+        assert!(disassemble_ignition(
+            &input_path,
+            &strict_dir,
+            &DisassembleOptions::default(),
+            None
+        )
+        .is_err());
+
+        let decoded_dir = temp_dir.path().join("decoded");
+        let options = DisassembleOptions {
+            keep_going: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        assert_eq!(decoded["systemd"]["units"][0]["name"], "test.service");
+        assert!(decoded["ignition"]["config"]["merge"][0]["source"]
+            .as_str()
+            .unwrap()
+            .contains(";base64-placeholder,"));
+
+        // The failed section stays inline and its partial output is removed
+        assert_eq!(
+            decoded["storage"]["files"][0]["contents"]["source"],
+            "data:,good"
+        );
+        assert!(!decoded_dir.join("etc/good").exists());
+        assert!(decoded["_skipped_sections"]["storage"].is_string());
+        assert!(decoded["_skipped_sections"].get("systemd").is_none());
+    }
+
+    #[test]
+    fn test_relativize_remote_keeps_path() {
+        use crate::remotes::RemoteRewrite;
+
+        assert!("no-equals-sign".parse::<RemoteRewrite>().is_err());
+        assert!("relative/path=https://mirror"
+            .parse::<RemoteRewrite>()
+            .is_err());
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/a", "contents": { "source": "https://example.com/assets/v1/a.conf?rev=2" } },
+      { "path": "/etc/b", "contents": { "source": "https://example.com/assetsextra/b.conf" } },
+      { "path": "/etc/c", "contents": { "source": "data:,inline" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            relativize_remote: vec!["https://example.com/assets/=http://mirror.internal/fcos"
+                .parse()
+                .unwrap()],
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        let files = &decoded["storage"]["files"];
+        assert_eq!(
+            files[0]["contents"]["source"],
+            "http://mirror.internal/fcos/v1/a.conf?rev=2"
+        );
+        // Only whole path segments of the base match
+        assert_eq!(
+            files[1]["contents"]["source"],
+            "https://example.com/assetsextra/b.conf"
+        );
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/c")).unwrap(),
+            "inline"
+        );
+    }
+
+    #[test]
+    fn test_emit_gitattributes() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } },
+      { "path": "/etc/settings.json", "contents": { "source": "data:application/json,%7B%7D" } },
+      { "path": "/usr/lib/blob", "contents": { "source": "data:application/octet-stream;base64,AAEC" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            emit_gitattributes: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let attributes = fs::read_to_string(decoded_dir.join(".gitattributes")).unwrap();
+        let lines: Vec<&str> = attributes.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "/etc/test text",
+                "/etc/settings.json text",
+                "/usr/lib/blob binary"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assert_file_count() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/a", "contents": { "source": "data:,a" } },
+      { "path": "/etc/b", "contents": { "source": "data:,b" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let mismatch = DisassembleOptions {
+            assert_file_count: Some(3),
+            ..Default::default()
+        };
+        let err = disassemble_ignition(
+            &input_path,
+            &temp_dir.path().join("mismatch"),
+            &mismatch,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Expected 3 file(s) but processed 2");
+
+        let decoded_dir = temp_dir.path().join("decoded");
+        let matching = DisassembleOptions {
+            assert_file_count: Some(2),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &matching, None).unwrap();
+
+        let target_file = temp_dir.path().join("output.ign");
+        let options = AssembleOptions {
+            assert_file_count: Some(1),
+            ..Default::default()
+        };
+        assert!(assemble_ignition(&target_file, &decoded_dir, &options, None).is_err());
+        assert!(!target_file.exists());
+    }
+
+    #[test]
+    fn test_digest_names_content_addressed_blobs() {
+        use crate::digest::ContentDigest;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/a", "contents": { "source": "data:,same" } },
+      { "path": "/etc/b", "contents": { "source": "data:,same" } },
+      { "path": "/etc/c", "append": [ { "source": "data:,other" } ] }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            digest: Some(ContentDigest::Blake3),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let expected_blob = format!("blobs/blake3-{}", blake3::hash(b"same").to_hex());
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join(&expected_blob)).unwrap(),
+            "same"
+        );
+        let blobs = fs::read_dir(decoded_dir.join("blobs")).unwrap().count();
+        assert_eq!(blobs, 2);
+        assert!(!decoded_dir.join("etc").exists());
+
+        let decoded = fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap();
+        assert!(decoded.contains(&expected_blob));
+
+        let output_path = temp_dir.path().join("output.ign");
+        assemble_ignition(
+            &output_path,
+            &decoded_dir,
+            &AssembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        let round_trip_dir = temp_dir.path().join("round-trip");
+        disassemble_ignition(
+            &output_path,
+            &round_trip_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(round_trip_dir.join("etc/b")).unwrap(),
+            "same"
+        );
+        assert_eq!(
+            fs::read_to_string(round_trip_dir.join("etc/c/0")).unwrap(),
+            "other"
+        );
+    }
+
+    #[test]
+    fn test_print_config_path() {
+        use crate::find_ignition_file;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_ignition_file(&decoded_dir, None).unwrap(),
+            decoded_dir.join("decoded.ign")
+        );
+
+        let target_file = temp_dir.path().join("output.ign");
+        let options = AssembleOptions {
+            print_config_path: true,
+            ..Default::default()
+        };
+        assemble_ignition(&target_file, &decoded_dir, &options, None).unwrap();
+        assert!(!target_file.exists());
+    }
+
+    #[test]
+    fn test_chunked_read_streams_large_sources() {
+        use base64::Engine;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let mut rng = fastrand::Rng::with_seed(1243);
+        let large: Vec<u8> = (0..5 * 1024 * 1024 + 1).map(|_| rng.u8(..)).collect();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&large);
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    {
+                        "path": "/var/large.bin",
+                        "contents": { "source": format!("data:application/octet-stream;base64,{}", encoded) }
+                    },
+                    {
+                        "path": "/etc/unpadded",
+                        "contents": { "source": "data:;base64,YWI" }
+                    },
+                    {
+                        "path": "/etc/plain",
+                        "contents": { "source": "data:,plain%20text" }
+                    }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            chunked_read: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        assert_eq!(fs::read(decoded_dir.join("var/large.bin")).unwrap(), large);
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/unpadded")).unwrap(),
+            "ab"
+        );
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/plain")).unwrap(),
+            "plain text"
+        );
+    }
+
+    #[test]
+    fn test_chunked_write_matches_in_memory_assembly() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    {
+                        "path": "/etc/plain",
+                        "contents": { "source": "data:text/plain;base64,cGxhaW4=" }
+                    },
+                    {
+                        "path": "/etc/hashed",
+                        "contents": {
+                            "source": "data:,old",
+                            "verification": { "hash": format!(
+                                "sha512-{}",
+                                crate::verification::to_hex(&{
+                                    use sha2::Digest;
+                                    sha2::Sha512::digest(b"old")
+                                })
+                            ) }
+                        }
+                    },
+                    {
+                        "path": "/etc/zipped",
+                        "contents": {
+                            "compression": "gzip",
+                            "source": "data:;base64,H4sIAAAAAAAA/8tIzcnJBwCGphA2BQAAAA=="
+                        }
+                    },
+                    {
+                        "path": "/etc/appended",
+                        "append": [ { "source": "data:,tail" } ]
+                    }
+                ]
+            },
+            "systemd": {
+                "units": [ { "name": "a.service", "contents": "[Unit]\n" } ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+        disassemble_ignition(&input_path, &decoded_dir, &Default::default(), None).unwrap();
+        fs::write(decoded_dir.join("etc/hashed"), "new").unwrap();
+
+        let assemble = |chunked_write: bool, name: &str| {
+            let target = temp_dir.path().join(name);
+            let options = AssembleOptions {
+                chunked_write,
+                ..Default::default()
+            };
+            assemble_ignition(&target, &decoded_dir, &options, None).unwrap();
+            fs::read_to_string(target).unwrap()
+        };
+        let in_memory = assemble(false, "in-memory.ign");
+        let chunked = assemble(true, "chunked.ign");
+        assert_eq!(chunked, in_memory);
+
+        let config: serde_json::Value = serde_json::from_str(&chunked).unwrap();
+        let hashed = &config["storage"]["files"][1]["contents"];
+        assert_eq!(
+            crate::verification::inline_content(hashed)
+                .unwrap()
+                .unwrap(),
+            b"new"
+        );
+        assert!(crate::verification::inline_hash_mismatches(&config).is_empty());
+    }
+
+    #[test]
+    fn test_expected_version_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.3.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            expected_version: Some("3.4.0".to_string()),
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &decoded_dir, &options, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("3.3.0") && err.contains("3.4.0"), "{}", err);
+        assert!(!decoded_dir.exists());
+
+        let options = DisassembleOptions {
+            expected_version: Some("3.3".to_string()),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+    }
+
+    #[test]
+    fn test_allow_missing_files_drops_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/kept", "contents": { "source": "data:,kept" } },
+      { "path": "/etc/missing", "contents": { "source": "data:,missing" } },
+      { "path": "/etc/motd", "append": [ { "source": "data:,a" }, { "source": "data:,b" } ] }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        fs::remove_file(decoded_dir.join("etc/missing")).unwrap();
+        fs::remove_file(decoded_dir.join("etc/motd/0")).unwrap();
+
+        let target_file = temp_dir.path().join("output.ign");
+        assert!(assemble_ignition(
+            &target_file,
+            &decoded_dir,
+            &AssembleOptions::default(),
+            None
+        )
+        .is_err());
+
+        let options = AssembleOptions {
+            allow_missing_files: true,
+            ..Default::default()
+        };
+        assemble_ignition(&target_file, &decoded_dir, &options, None).unwrap();
+
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&target_file).unwrap()).unwrap();
+        let files = output["storage"]["files"].as_array().unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f["path"].as_str().unwrap()).collect();
+        assert_eq!(paths, vec!["/etc/kept", "/etc/motd"]);
+        let append = files[1]["append"].as_array().unwrap();
+        assert_eq!(append.len(), 1);
+        assert_eq!(
+            append[0]["source"],
+            "data:text/plain;charset=US-ASCII;base64,Yg=="
+        );
+    }
+
+    #[test]
+    fn test_emit_env_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let env_path = temp_dir.path().join("ignition.env");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } },
+      { "path": "/etc/motd", "append": [ { "source": "data:,hello" } ] }
+    ]
+  },
+  "unknownTopLevel": true
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            emit_env_file: Some(env_path.clone()),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let env = fs::read_to_string(&env_path).unwrap();
+        assert_eq!(
+            env,
+            "IGNITION_VERSION=3.4.0\nEXTRACTED_FILE_COUNT=2\nWARNING_COUNT=1\nTOTAL_BYTES=17\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks_policy() {
+        use crate::SymlinkPolicy;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let outside = temp_dir.path().join("secret");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        fs::write(&outside, "outside content").unwrap();
+        fs::remove_file(decoded_dir.join("etc/test")).unwrap();
+        std::os::unix::fs::symlink(&outside, decoded_dir.join("etc/test")).unwrap();
+
+        let target_file = temp_dir.path().join("output.ign");
+        let err = assemble_ignition(
+            &target_file,
+            &decoded_dir,
+            &AssembleOptions::default(),
+            None,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("Refusing to follow symlink"), "{}", err);
+        assert!(!target_file.exists());
+
+        let options = AssembleOptions {
+            follow_symlinks: SymlinkPolicy::Allow,
+            ..Default::default()
+        };
+        assemble_ignition(&target_file, &decoded_dir, &options, None).unwrap();
+        assert!(fs::read_to_string(&target_file)
+            .unwrap()
+            .contains("b3V0c2lkZSBjb250ZW50"));
+    }
+
+    /// Serve `body` to the first `connections` requests on a local port,
+    /// returning the URL
+    #[cfg(feature = "http")]
+    fn serve(body: String, connections: usize) -> String {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/config.ign", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.unwrap();
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request).unwrap();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        url
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_diff_remote_reports_drift() {
+        use crate::diff_remote;
+
+        let temp_dir = TempDir::new().unwrap();
+        let local_path = temp_dir.path().join("local.ign");
+
+        let local = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/same", "contents": { "source": "data:,same" } },
+      { "path": "/etc/changed", "contents": { "source": "data:,new%20longer%20content" } }
+    ]
+  }
+}"#;
+        // Same content as local except /etc/changed, with /etc/same base64
+        // encoded to show that encoding differences are ignored
+        let served = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/same", "contents": { "source": "data:;base64,c2FtZQ==" } },
+      { "path": "/etc/changed", "contents": { "source": "data:,old" } }
+    ]
+  }
+}"#;
+        fs::write(&local_path, local).unwrap();
+
+        let url = serve(served.to_string(), 2);
+        let differences = diff_remote(&local_path, &url, None).unwrap();
+        assert_eq!(
+            differences,
+            vec!["file /etc/changed changed: 3 -> 18 bytes".to_string()]
+        );
+
+        fs::write(&local_path, served).unwrap();
+        assert!(diff_remote(&local_path, &url, None).unwrap().is_empty());
+
+        // A served config over the size cap is refused
+        let url = serve(served.to_string(), 2);
+        let size = served.len() as u64;
+        assert!(diff_remote(&local_path, &url, Some(size)).is_ok());
+        fs::write(&local_path, "{}").unwrap();
+        let err = diff_remote(&local_path, &url, Some(size - 1)).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("Failed to read response"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_output_tree_json() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let tree_path = temp_dir.path().join("tree.json");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            output_tree_json: Some(tree_path.clone()),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let tree: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&tree_path).unwrap()).unwrap();
+        assert_eq!(tree["name"], "decoded");
+        assert_eq!(tree["type"], "directory");
+
+        let children = tree["children"].as_array().unwrap();
+        let etc = children.iter().find(|c| c["name"] == "etc").unwrap();
+        assert_eq!(etc["type"], "directory");
+        assert_eq!(
+            etc["children"],
+            serde_json::json!([{ "name": "test", "type": "file", "size": 12 }])
+        );
+        assert!(children.iter().any(|c| c["name"] == "decoded.ign"));
+    }
+
+    #[test]
+    fn test_embed_newline_lf() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/motd", "contents": { "source": "data:text/plain;base64,aGVsbG8K" } },
+      { "path": "/usr/lib/blob", "contents": { "source": "data:application/octet-stream;base64,AAEC" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        // Simulate editing on Windows
+        fs::write(decoded_dir.join("etc/motd"), "hello\r\nworld\r\n").unwrap();
+        fs::write(decoded_dir.join("usr/lib/blob"), b"\r\n\x00").unwrap();
+
+        let options = AssembleOptions {
+            embed_newline: NewlineMode::Lf,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        use base64::Engine;
+        let decode = |index: usize| {
+            let source = output["storage"]["files"][index]["contents"]["source"]
+                .as_str()
+                .unwrap();
+            let payload = source.split_once(";base64,").unwrap().1;
+            base64::engine::general_purpose::STANDARD
+                .decode(payload)
+                .unwrap()
+        };
+        assert_eq!(decode(0), b"hello\nworld\n");
+        assert_eq!(decode(1), b"\r\n\x00");
+    }
+
+    #[test]
+    fn test_disassemble_gzip_compressed_source() {
+        use base64::Engine;
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"compressed content\n").unwrap();
+        let gzipped = base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap());
+
+        let config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    {
+                        "path": "/etc/gzipped",
+                        "contents": {
+                            "compression": "gzip",
+                            "source": format!("data:;base64,{}", gzipped)
+                        }
+                    },
+                    {
+                        "path": "/etc/plain",
+                        "contents": { "compression": "", "source": "data:,plain" }
+                    }
+                ]
+            }
+        });
+        fs::write(&input_path, config.to_string()).unwrap();
+
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/gzipped")).unwrap(),
+            "compressed content\n"
+        );
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/plain")).unwrap(),
+            "plain"
+        );
+
+        // The compression stays next to the placeholder for assembly
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        assert_eq!(
+            decoded["storage"]["files"][0]["contents"]["compression"],
+            "gzip"
+        );
+
+        // Unknown algorithms are rejected rather than written undecoded
+        let mut config = config;
+        config["storage"]["files"][0]["contents"]["compression"] = serde_json::json!("xz");
+        fs::write(&input_path, config.to_string()).unwrap();
+        let err = disassemble_ignition(
+            &input_path,
+            &temp_dir.path().join("decoded-xz"),
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("Unsupported compression 'xz' for /etc/gzipped"));
+    }
+
+    #[test]
+    fn test_config_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let env_path = temp_dir.path().join("stats.env");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } },
+      { "path": "/etc/append", "append": [{ "source": "data:,more" }] }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            config_only: true,
+            emit_env_file: Some(env_path.clone()),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        assert!(decoded_dir.join("decoded.ign").is_file());
+        assert!(decoded_dir.join("manifest.json").is_file());
+        assert!(!decoded_dir.join("etc").exists());
+
+        let decoded = fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap();
+        assert!(decoded.contains("base64-placeholder,etc/test"));
+        assert!(decoded.contains("base64-placeholder,etc/append/0"));
+
+        let env = fs::read_to_string(&env_path).unwrap();
+        assert!(env.contains("EXTRACTED_FILE_COUNT=2"));
+        assert!(env.contains("TOTAL_BYTES=16"));
+    }
+
+    #[test]
+    fn test_validate_modes() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/ok", "mode": 420 },
+      { "path": "/etc/bad", "mode": 99999 }
+    ],
+    "directories": [{ "path": "/var/negative", "mode": -1 }]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            validate_modes: true,
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("storage.files[1] (/etc/bad): mode 99999 is outside 0..=0o7777"));
+        assert!(message.contains("storage.directories[0] (/var/negative): mode -1"));
+        assert!(!message.contains("/etc/ok"));
+        assert!(!decoded_dir.exists());
+
+        // Without the flag the config is extracted as before
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_assemble_recompresses_gzip() {
+        use base64::Engine;
+        use std::io::Read;
+
+        let temp_dir = TempDir::new().unwrap();
+        let decoded_dir = temp_dir.path().join("decoded");
+        let first_path = temp_dir.path().join("first.ign");
+        let second_path = temp_dir.path().join("second.ign");
+
+        fs::create_dir_all(decoded_dir.join("etc")).unwrap();
+        fs::write(decoded_dir.join("etc/gzipped"), "compressed content\n").unwrap();
+        fs::write(
+            decoded_dir.join("decoded.ign"),
+            r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      {
+        "path": "/etc/gzipped",
+        "contents": {
+          "compression": "gzip",
+          "source": "data:text/plain;base64-placeholder,etc/gzipped"
+        }
+      }
+    ]
+  }
+}"#,
+        )
+        .unwrap();
+
+        assemble_ignition(&first_path, &decoded_dir, &AssembleOptions::default(), None).unwrap();
+        assemble_ignition(
+            &second_path,
+            &decoded_dir,
+            &AssembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        // Repeated assembly produces identical output
+        let first = fs::read_to_string(&first_path).unwrap();
+        assert_eq!(first, fs::read_to_string(&second_path).unwrap());
+
+        let output: serde_json::Value = serde_json::from_str(&first).unwrap();
+        let contents = &output["storage"]["files"][0]["contents"];
+        assert_eq!(contents["compression"], "gzip");
+        let source = contents["source"].as_str().unwrap();
+        let payload = source.split_once(";base64,").unwrap().1;
+        let gzipped = base64::engine::general_purpose::STANDARD
+            .decode(payload)
+            .unwrap();
+        let mut inflated = String::new();
+        flate2::read::GzDecoder::new(gzipped.as_slice())
+            .read_to_string(&mut inflated)
+            .unwrap();
+        assert_eq!(inflated, "compressed content\n");
+    }
+
+    #[test]
+    fn test_corrupt_data_url_is_an_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        // A single base64 character can't encode any byte
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/foo", "contents": { "source": "data:;base64,Q" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let err = disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("Failed to decode base64 body for /etc/foo"));
+        assert!(message.contains("data:;base64,Q"));
+        assert!(!decoded_dir.join("etc/foo").exists());
+    }
+
+    #[test]
+    fn test_content_from_git() {
+        use std::process::Command;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let repo_dir = temp_dir.path().join("repo");
+        let decoded_dir = repo_dir.join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,committed" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+        fs::create_dir(&repo_dir).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(&repo_dir)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "decoded"]);
+
+        // Dirty the working tree after committing
+        fs::write(decoded_dir.join("etc/test"), "uncommitted").unwrap();
+
+        let options = AssembleOptions {
+            content_from_git: Some("HEAD".to_string()),
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        // base64 of "committed"
+        assert_eq!(
+            output["storage"]["files"][0]["contents"]["source"],
+            "data:text/plain;charset=US-ASCII;base64,Y29tbWl0dGVk"
+        );
+
+        let options = AssembleOptions {
+            content_from_git: Some("no-such-ref".to_string()),
+            ..Default::default()
+        };
+        let err = assemble_ignition(
+            &temp_dir.path().join("bad-ref.ign"),
+            &decoded_dir,
+            &options,
+            None,
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("Failed to read etc/test at git ref no-such-ref"));
+    }
+
+    #[test]
+    fn test_strict_array_detection() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+
+        // Only the first append entry has a source
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      {
+        "path": "/etc/mixed",
+        "append": [{ "source": "data:,fragment" }, { "compression": "" }]
+      }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        // By default any source makes it a list of indexed fragments
+        let lenient_dir = temp_dir.path().join("lenient");
+        disassemble_ignition(
+            &input_path,
+            &lenient_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(lenient_dir.join("etc/mixed/0")).unwrap(),
+            "fragment"
+        );
+
+        // Strict mode walks the mixed array as a plain nested structure
+        let strict_dir = temp_dir.path().join("strict");
+        let options = DisassembleOptions {
+            strict_array_detection: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &strict_dir, &options, None).unwrap();
+        assert!(strict_dir.join("etc/mixed").is_file());
+        assert_eq!(
+            fs::read_to_string(strict_dir.join("etc/mixed")).unwrap(),
+            "fragment"
+        );
+
+        let decoded = fs::read_to_string(strict_dir.join("decoded.ign")).unwrap();
+        assert!(decoded.contains("base64-placeholder,etc/mixed\""));
+    }
+
+    #[test]
+    fn test_path_traversal_is_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("out/decoded");
+
+        for (name, file) in [
+            (
+                "contents",
+                r#"{ "path": "/../../escaped", "contents": { "source": "data:,evil" } }"#,
+            ),
+            (
+                "append",
+                r#"{ "path": "/etc/../../../escaped", "append": [{ "source": "data:,evil" }] }"#,
+            ),
+        ] {
+            let test_ignition = format!(
+                r#"{{ "ignition": {{ "version": "3.4.0" }}, "storage": {{ "files": [{}] }} }}"#,
+                file
+            );
+            fs::write(&input_path, test_ignition).unwrap();
+
+            let err = disassemble_ignition(
+                &input_path,
+                &decoded_dir,
+                &DisassembleOptions::default(),
+                None,
+            )
+            .unwrap_err();
+            assert!(
+                format!("{:#}", err)
+                    .contains("Refusing to extract path outside the output directory"),
+                "{} entry was not refused",
+                name
+            );
+            assert!(!temp_dir.path().join("escaped").exists());
+            assert!(!temp_dir.path().join("out/escaped").exists());
+            fs::remove_dir_all(&decoded_dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_default_keeps_zero_ids_and_mode() {
+        let temp_dir = TempDir::new().unwrap();
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        fs::create_dir_all(&decoded_dir).unwrap();
+        fs::write(
+            decoded_dir.join("decoded.ign"),
+            r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      {
+        "path": "/etc/rootonly",
+        "mode": 0,
+        "overwrite": false,
+        "user": { "id": 0, "name": null },
+        "group": { "id": 0 }
+      }
+    ]
+  }
+}"#,
+        )
+        .unwrap();
+
+        let options = AssembleOptions {
+            default: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        let file = &output["storage"]["files"][0];
+        assert_eq!(file["user"], serde_json::json!({ "id": 0 }));
+        assert_eq!(file["group"], serde_json::json!({ "id": 0 }));
+        assert_eq!(file["mode"], 0);
+        // An explicit `false` overrides a parent config on merge
+        assert_eq!(file["overwrite"], false);
+    }
+
+    #[test]
+    fn test_default_keeps_merge_significant_and_listed_keys() {
+        let mut config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    {
+                        "path": "/etc/fragment",
+                        "append": [],
+                        "overwrite": null
+                    }
+                ],
+                "filesystems": [
+                    { "device": "/dev/vdb", "wipeFilesystem": false, "label": "" }
+                ]
+            },
+            "systemd": {
+                "units": [ { "name": "a.service", "enabled": false, "mask": false } ]
+            }
+        });
+        crate::remove_config_defaults(&mut config, &["label".to_string()]);
+
+        assert_eq!(
+            config["storage"]["files"][0],
+            serde_json::json!({ "path": "/etc/fragment", "append": [] })
+        );
+        assert_eq!(
+            config["storage"]["filesystems"][0],
+            serde_json::json!({ "device": "/dev/vdb", "label": "" })
+        );
+        assert_eq!(
+            config["systemd"]["units"][0],
+            serde_json::json!({ "name": "a.service", "enabled": false })
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_set_xattrs() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/app.json", "contents": { "source": "data:application/json,%7B%7D" } }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            set_xattrs: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let file = decoded_dir.join("etc/app.json");
+        assert_eq!(
+            xattr::get(&file, "user.mime_type").unwrap(),
+            Some(b"application/json".to_vec())
+        );
+        assert_eq!(
+            xattr::get(&file, "user.ignition_path").unwrap(),
+            Some(b"/etc/app.json".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_reject_unknown_sections() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let options = DisassembleOptions {
+            reject_unknown_sections: true,
+            ..Default::default()
+        };
+
+        let misspelled = r#"{
+  "ignition": { "version": "3.2.0" },
+  "storag": { "files": [] },
+  "kernelArguments": { "shouldExist": ["quiet"] }
+}"#;
+        fs::write(&input_path, misspelled).unwrap();
+        let err = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap_err();
+        // kernelArguments only exists from spec 3.3 on
+        assert!(format!("{:#}", err).contains(
+            "Unknown top-level section(s) for spec version 3.2.0: kernelArguments, storag"
+        ));
+        assert!(!decoded_dir.exists());
+
+        let valid = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": { "files": [] },
+  "kernelArguments": { "shouldExist": ["quiet"] }
+}"#;
+        fs::write(&input_path, valid).unwrap();
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+    }
+
+    #[test]
+    fn test_metadata_sidecar_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      {
+        "path": "/etc/test",
+        "mode": 420,
+        "user": { "name": "core" },
+        "contents": { "source": "data:,test%20content" }
+      }
+    ]
+  }
+}"#;
+        fs::write(&input_path, test_ignition).unwrap();
+
+        let options = DisassembleOptions {
+            metadata: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let metadata_path = decoded_dir.join("metadata.json");
+        let mut metadata: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&metadata_path).unwrap()).unwrap();
+        assert_eq!(
+            metadata,
+            serde_json::json!({ "/etc/test": { "mode": 420, "user": { "name": "core" } } })
+        );
+
+        // Permissions managed in the sidecar win over decoded.ign
+        metadata["/etc/test"]["mode"] = serde_json::json!(0o600);
+        metadata["/etc/test"]["group"] = serde_json::json!({ "id": 0 });
+        fs::write(&metadata_path, metadata.to_string()).unwrap();
+
+        assemble_ignition(
+            &output_path,
+            &decoded_dir,
+            &AssembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        let file = &output["storage"]["files"][0];
+        assert_eq!(file["mode"], 0o600);
+        assert_eq!(file["user"]["name"], "core");
+        assert_eq!(file["group"]["id"], 0);
+    }
+
+    #[test]
+    fn test_assemble_to_stdout() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let target = temp_dir.path().join("-");
+
+        fs::write(&input_path, r#"{ "ignition": { "version": "3.4.0" } }"#).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        let options = AssembleOptions {
+            stdout: true,
+            compact: true,
+            ..Default::default()
+        };
+        assemble_ignition(&target, &decoded_dir, &options, None).unwrap();
+        assert!(!target.exists());
+
+        // Split sources are written next to an output file, which stdout lacks
+        let options = AssembleOptions {
+            stdout: true,
+            split_large: Some(SplitLarge {
+                threshold: 1,
+                base_url: "https://example.com".to_string(),
+            }),
+            ..Default::default()
+        };
+        assert!(assemble_ignition(&target, &decoded_dir, &options, None).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+
+        fs::write(
+            &input_path,
+            r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": { "files": [{ "path": "/etc/test", "mode": 420 }] },
+  "systemd": { "units": [{ "name": "test.service", "enabled": true }] },
+  "passwd": { "users": [{ "name": "core" }] }
+}"#,
+        )
+        .unwrap();
+        validate_ignition(&input_path, false).unwrap();
+        // Nothing besides the input is written
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        fs::write(&input_path, r#"{ "ignition": { "version": "3.4.0" }, "#).unwrap();
+        assert!(validate_ignition(&input_path, false).is_err());
+
+        fs::write(
+            &input_path,
+            r#"{ "ignition": { "version": "3.4.0" }, "storage": { "files": [{ "path": "/etc/test", "mode": 99999 }] } }"#,
+        )
+        .unwrap();
+        let err = validate_ignition(&input_path, false).unwrap_err();
+        assert!(format!("{:#}", err).contains("mode 99999"));
+
+        fs::write(
+            &input_path,
+            r#"{ "ignition": { "version": "3.4.0" }, "storag": {} }"#,
+        )
+        .unwrap();
+        validate_ignition(&input_path, false).unwrap();
+        assert!(validate_ignition(&input_path, true).is_err());
+    }
+
+    #[test]
+    fn test_input_multiple_merges_with_provenance() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path().join("base.ign");
+        let overlay_path = temp_dir.path().join("overlay.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        fs::write(
+            &base_path,
+            r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/base", "contents": { "source": "data:,base" } },
+      { "path": "/etc/shared", "mode": 420, "contents": { "source": "data:,from%20base" } }
+    ]
+  },
+  "passwd": { "users": [{ "name": "core", "sshAuthorizedKeys": ["ssh-ed25519 AAAA base"] }] }
+}"#,
+        )
+        .unwrap();
+        fs::write(
+            &overlay_path,
+            r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/shared", "contents": { "source": "data:,from%20overlay" } },
+      { "path": "/etc/overlay", "contents": { "source": "data:,overlay" } }
+    ]
+  },
+  "passwd": { "users": [{ "name": "core", "sshAuthorizedKeys": ["ssh-ed25519 AAAA overlay"] }] }
+}"#,
+        )
+        .unwrap();
+
+        let options = DisassembleOptions {
+            input_multiple: vec![overlay_path.clone()],
+            ..Default::default()
+        };
+        disassemble_ignition(&base_path, &decoded_dir, &options, None).unwrap();
+
+        // The overlay wins on the shared path, keeping base fields it doesn't set
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/shared")).unwrap(),
+            "from overlay"
+        );
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/base")).unwrap(),
+            "base"
+        );
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        let files = decoded["storage"]["files"].as_array().unwrap();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[1]["path"], "/etc/shared");
+        assert_eq!(files[1]["mode"], 420);
+        assert_eq!(
+            decoded["passwd"]["users"][0]["sshAuthorizedKeys"],
+            serde_json::json!(["ssh-ed25519 AAAA base", "ssh-ed25519 AAAA overlay"])
+        );
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("manifest.json")).unwrap())
+                .unwrap();
+        let base = base_path.display().to_string();
+        let overlay = overlay_path.display().to_string();
+        assert_eq!(
+            manifest["provenance"],
+            serde_json::json!({
+                "/etc/base": base,
+                "/etc/shared": overlay,
+                "/etc/overlay": overlay
+            })
+        );
+    }
+
+    #[test]
+    fn test_no_clobber_manifest_keeps_manual_edits() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let mut config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    { "path": "/etc/test", "user": { "name": "core" }, "contents": { "source": "data:,test" } }
+                ]
+            }
+        });
+        fs::write(&input_path, config.to_string()).unwrap();
+        let options = DisassembleOptions {
+            metadata: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        // Hand edits: new ownership and a note in the manifest
+        let metadata_path = decoded_dir.join("metadata.json");
+        fs::write(
+            &metadata_path,
+            r#"{ "/etc/test": { "user": { "id": 1000 } } }"#,
+        )
+        .unwrap();
+        let manifest_path = decoded_dir.join("manifest.json");
+        let mut manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        manifest["notes"] = serde_json::json!("edited by hand");
+        fs::write(&manifest_path, manifest.to_string()).unwrap();
+
+        // Re-extract incrementally with an additional file
+        config["storage"]["files"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({ "path": "/etc/new", "mode": 384, "contents": { "source": "data:,new" } }));
+        fs::write(&input_path, config.to_string()).unwrap();
+        let options = DisassembleOptions {
+            action: Action::Add,
+            metadata: true,
+            no_clobber_manifest: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let metadata: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&metadata_path).unwrap()).unwrap();
+        assert_eq!(
+            metadata["/etc/test"]["user"],
+            serde_json::json!({ "id": 1000 })
+        );
+        assert_eq!(metadata["/etc/new"]["mode"], 384);
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest["notes"], "edited by hand");
+        assert_eq!(
+            manifest["file_order"],
+            serde_json::json!(["/etc/test", "/etc/new"])
+        );
+    }
+
+    #[test]
+    fn test_list_embedded_sources() {
+        use base64::Engine;
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"inflated text").unwrap();
+        let gzipped = base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap());
+
+        let config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": { "files": [
+                {
+                    "path": "/etc/motd",
+                    "append": [
+                        { "source": "data:,one" },
+                        { "source": "https://example.com/two" }
+                    ]
+                },
+                {
+                    "path": "/etc/packed",
+                    "contents": {
+                        "source": format!("data:;base64,{}", gzipped),
+                        "compression": "gzip"
+                    }
+                },
+                { "path": "", "contents": { "source": "data:application/json,%7B%7D" } }
+            ] }
+        });
+
+        let sources = crate::listing::collect(&config).unwrap();
+        let lines: Vec<String> = sources.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "/etc/motd append[0]  text/plain;charset=US-ASCII  3 bytes",
+                "/etc/packed  text/plain;charset=US-ASCII  13 bytes  gzip-compressed",
+                "(no path)  application/json  2 bytes  -> extracted_44136fa355b3.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dry_run_changes_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let target_file = temp_dir.path().join("output.ign");
+
+        let config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": { "files": [
+                { "path": "/etc/test", "contents": { "source": "data:,new" } }
+            ] }
+        });
+        fs::write(&input_path, config.to_string()).unwrap();
+        fs::create_dir(&decoded_dir).unwrap();
+        fs::write(decoded_dir.join("precious"), "keep me").unwrap();
+
+        let options = DisassembleOptions {
+            action: Action::Replace,
+            dry_run: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        let entries: Vec<_> = fs::read_dir(&decoded_dir).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("precious")).unwrap(),
+            "keep me"
+        );
+
+        // A dry run still fails where the real run would
+        let options = DisassembleOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        assert!(disassemble_ignition(&input_path, &decoded_dir, &options, None).is_err());
+
+        let fresh_dir = temp_dir.path().join("fresh");
+        disassemble_ignition(&input_path, &fresh_dir, &options, None).unwrap();
+        assert!(!fresh_dir.exists());
+
+        disassemble_ignition(
+            &input_path,
+            &fresh_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        let options = AssembleOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        assemble_ignition(&target_file, &fresh_dir, &options, None).unwrap();
+        assert!(!target_file.exists());
+    }
+
+    #[test]
+    fn test_verify_gzip_roundtrip() {
+        use base64::Engine;
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let target_file = temp_dir.path().join("output.ign");
+
+        // Stored blocks never match what assembly's default level produces
+        let content = b"compressible compressible compressible";
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::none());
+        encoder.write_all(content).unwrap();
+        let original = encoder.finish().unwrap();
+
+        let config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": { "files": [{
+                "path": "/etc/packed",
+                "contents": {
+                    "source": format!(
+                        "data:;base64,{}",
+                        base64::engine::general_purpose::STANDARD.encode(&original)
+                    ),
+                    "compression": "gzip"
+                }
+            }] }
+        });
+        fs::write(&input_path, config.to_string()).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("manifest.json")).unwrap())
+                .unwrap();
+        let original_digest = manifest["gzip_digests"]["etc/packed"].as_str().unwrap();
+        assert_eq!(
+            original_digest,
+            crate::verification::HashAlgorithm::Sha256.hash(&original)
+        );
+
+        let options = AssembleOptions {
+            verify_gzip_roundtrip: true,
+            ..Default::default()
+        };
+        assemble_ignition(&target_file, &decoded_dir, &options, None).unwrap();
+
+        // Same content, different bytes
+        let regzipped = crate::gzip_deterministic(content).unwrap();
+        let warning =
+            crate::verify_gzip_roundtrip("/etc/packed", content, &regzipped, Some(original_digest))
+                .unwrap();
+        assert!(warning.unwrap().contains("differs byte-wise"));
+        let identical = crate::verification::HashAlgorithm::Sha256.hash(&regzipped);
+        assert_eq!(
+            crate::verify_gzip_roundtrip("/etc/packed", content, &regzipped, Some(&identical))
+                .unwrap(),
+            None
+        );
+        assert!(crate::verify_gzip_roundtrip("/etc/packed", b"other", &regzipped, None).is_err());
+    }
+
+    #[test]
+    fn test_emit_sbom() {
+        use sha2::Digest;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let sbom_path = temp_dir.path().join("sbom.json");
+
+        let config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": { "files": [
+                { "path": "/etc/test", "contents": { "source": "data:;base64,dGVzdCBjb250ZW50" } },
+                {
+                    "path": "/etc/motd",
+                    "append": [
+                        { "source": "data:,hello" },
+                        { "source": "https://example.com/motd" }
+                    ]
+                }
+            ] }
+        });
+        fs::write(&input_path, config.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            emit_sbom: Some(sbom_path.clone()),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let sbom: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&sbom_path).unwrap()).unwrap();
+        let digest = |content: &[u8]| crate::verification::to_hex(&sha2::Sha256::digest(content));
+        assert_eq!(
+            sbom["files"],
+            serde_json::json!([
+                {
+                    "path": "/etc/test",
+                    "kind": "inline",
+                    "media_type": "text/plain;charset=US-ASCII",
+                    "size": 12,
+                    "sha256": digest(b"test content"),
+                },
+                {
+                    "path": "/etc/motd",
+                    "entry": "append[0]",
+                    "kind": "inline",
+                    "media_type": "text/plain;charset=US-ASCII",
+                    "size": 5,
+                    "sha256": digest(b"hello"),
+                },
+                {
+                    "path": "/etc/motd",
+                    "entry": "append[1]",
+                    "kind": "remote",
+                    "url": "https://example.com/motd",
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_disassemble_library_report() {
+        let temp_dir = TempDir::new().unwrap();
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": { "files": [
+                { "path": "/etc/test", "contents": { "source": "data:,test" } },
+                { "path": "/etc/motd", "append": [{ "source": "data:,hi" }] }
+            ] }
+        });
+        let report = crate::disassemble(
+            &config.to_string(),
+            &decoded_dir,
+            DisassembleOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(report.file_count, 2);
+        assert_eq!(
+            report.extracted_paths,
+            [decoded_dir.join("etc/test"), decoded_dir.join("etc/motd/0")]
+        );
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/test")).unwrap(),
+            "test"
+        );
+    }
+
+    #[test]
+    fn test_reports_written_paths_and_warnings() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let target_file = temp_dir.path().join("output.ign");
+
+        let config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": { "files": [
+                { "path": "/etc/test", "bogus": true, "contents": { "source": "data:,test" } }
+            ] }
+        });
+        fs::write(&input_path, config.to_string()).unwrap();
+
+        let report = disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(report.file_count, 1);
+        assert_eq!(report.extracted_paths, [decoded_dir.join("etc/test")]);
+        assert_eq!(
+            report.written_paths,
+            [
+                decoded_dir.join("etc/test"),
+                decoded_dir.join("manifest.json"),
+                decoded_dir.join("decoded.ign"),
+            ]
+        );
+        assert_eq!(report.warnings.len(), 1);
+        assert!(
+            report.warnings[0].contains("bogus"),
+            "{:?}",
+            report.warnings
+        );
+        assert_eq!(report.skipped, None);
+
+        let report = assemble_ignition(
+            &target_file,
+            &decoded_dir,
+            &AssembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(report.file_count, 1);
+        assert_eq!(report.written_paths, vec![target_file.clone()]);
+        assert!(report.warnings.is_empty());
+
+        // A config outside the version range is skipped with its reason
+        let options = AssembleOptions {
+            action: Action::Replace,
+            version_range: Some("<3.4".parse().unwrap()),
+            ..Default::default()
+        };
+        let report = assemble_ignition(&target_file, &decoded_dir, &options, None).unwrap();
+        assert!(report.skipped.unwrap().contains("outside <3.4"));
+        assert!(report.written_paths.is_empty());
+    }
+
+    #[test]
+    fn test_default_keeps_ignition_version() {
+        let mut config = serde_json::json!({
+            "ignition": { "version": "", "config": { "merge": [] } },
+            "storage": { "files": [] }
+        });
+        crate::remove_config_defaults(&mut config, &[]);
+        assert_eq!(config["ignition"]["version"], "");
+
+        // Kept keys under `ignition` come back whole
+        let mut config = serde_json::json!({
+            "ignition": {
+                "version": "3.4.0",
+                "config": { "merge": [] },
+                "timeouts": { "httpTotal": 0 },
+                "proxy": {}
+            }
+        });
+        crate::remove_config_defaults(&mut config, &["config".into(), "timeouts".into()]);
+        assert_eq!(
+            config,
+            serde_json::json!({
+                "ignition": {
+                    "version": "3.4.0",
+                    "config": { "merge": [] },
+                    "timeouts": { "httpTotal": 0 }
+                }
+            })
+        );
+
+        let mut config = serde_json::json!({ "ignition": { "version": "3.4.0", "proxy": {} } });
+        crate::remove_config_defaults(&mut config, &["ignition".into()]);
+        assert_eq!(
+            config,
+            serde_json::json!({ "ignition": { "version": "3.4.0", "proxy": {} } })
+        );
+
+        let temp_dir = TempDir::new().unwrap();
+        let ignition_dir = temp_dir.path().join("ignition");
+        fs::create_dir(&ignition_dir).unwrap();
+        fs::write(
+            ignition_dir.join("decoded.ign"),
+            r#"{ "ignition": { "version": "3.4.0" } }"#,
+        )
+        .unwrap();
+
+        for compact in [false, true] {
+            let target_file = temp_dir.path().join(format!("output-{}.ign", compact));
+            let options = AssembleOptions {
+                default: true,
+                compact,
+                ..Default::default()
+            };
+            assemble_ignition(&target_file, &ignition_dir, &options, None).unwrap();
+            let output: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(&target_file).unwrap()).unwrap();
+            assert_eq!(output["ignition"]["version"], "3.4.0");
+        }
+    }
+
+    #[test]
+    fn test_disassemble_input_list() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.ign");
+        let second = temp_dir.path().join("second.ign");
+        let list_path = temp_dir.path().join("inputs.txt");
+
+        for (path, content) in [(&first, "one"), (&second, "two")] {
+            let config = serde_json::json!({
+                "ignition": { "version": "3.4.0" },
+                "storage": { "files": [
+                    { "path": "/etc/test", "contents": { "source": format!("data:,{}", content) } }
+                ] }
+            });
+            fs::write(path, config.to_string()).unwrap();
+        }
+        fs::write(
+            &list_path,
+            format!("{}\n\n{}\n", first.display(), second.display()),
+        )
+        .unwrap();
+
+        let reports =
+            crate::disassemble_input_list(&list_path, &DisassembleOptions::default(), None)
+                .unwrap();
+
+        let first_dir = temp_dir.path().join("first.decoded");
+        let second_dir = temp_dir.path().join("second.decoded");
+        let dirs: Vec<_> = reports.iter().map(|(dir, _)| dir.clone()).collect();
+        assert_eq!(dirs, [first_dir.clone(), second_dir.clone()]);
+        assert!(reports.iter().all(|(_, report)| report.file_count == 1));
+        assert_eq!(
+            fs::read_to_string(first_dir.join("etc/test")).unwrap(),
+            "one"
+        );
+        assert_eq!(
+            fs::read_to_string(second_dir.join("etc/test")).unwrap(),
+            "two"
+        );
+    }
+
+    #[test]
+    fn test_extract_units() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "systemd": {
+                "units": [
+                    {
+                        "name": "foo.service",
+                        "enabled": true,
+                        "contents": "[Service]\nExecStart=/usr/bin/foo\n",
+                        "dropins": [{ "name": "10-x.conf", "contents": "[Service]\nNice=5\n" }]
+                    },
+                    { "name": "masked.service", "mask": true }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            extract_units: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("units/foo.service")).unwrap(),
+            "[Service]\nExecStart=/usr/bin/foo\n"
+        );
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("units/foo.service.d/10-x.conf")).unwrap(),
+            "[Service]\nNice=5\n"
+        );
+
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        let unit = &decoded["systemd"]["units"][0];
+        assert_eq!(
+            unit["contents"],
+            "data:text/plain;base64-placeholder,units/foo.service"
+        );
+        assert_eq!(
+            unit["dropins"][0]["contents"],
+            "data:text/plain;base64-placeholder,units/foo.service.d/10-x.conf"
+        );
+        assert_eq!(unit["enabled"], true);
+        // The masked unit has nothing to extract and is kept as is
+        let masked = &decoded["systemd"]["units"][1];
+        assert_eq!(masked["mask"], true);
+        assert!(masked["contents"].is_null());
+        assert!(!decoded_dir.join("units/masked.service").exists());
+    }
+
+    #[test]
+    fn test_extract_units_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let unit_text = "[Unit]\nDescription=Backup\n\n[Service]\nType=oneshot\nExecStart=/usr/bin/rsync \\\n    --archive --delete \\\n    /var/data/ /mnt/backup/\n\n[Install]\nWantedBy=multi-user.target\n";
+        let dropin_text = "[Service]\nEnvironment=RSYNC_RSH=ssh\n";
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "systemd": {
+                "units": [{
+                    "name": "backup.service",
+                    "enabled": true,
+                    "contents": unit_text,
+                    "dropins": [{ "name": "env.conf", "contents": dropin_text }]
+                }]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            extract_units: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        assert_eq!(
+            fs::read(decoded_dir.join("units/backup.service")).unwrap(),
+            unit_text.as_bytes()
+        );
+
+        let options = AssembleOptions {
+            default: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_path).unwrap()).unwrap();
+        let unit = &output["systemd"]["units"][0];
+        assert_eq!(
+            unit["contents"].as_str().unwrap().as_bytes(),
+            unit_text.as_bytes()
+        );
+        assert_eq!(unit["enabled"], true);
+        assert_eq!(output, test_ignition);
+    }
+
+    #[test]
+    fn test_roundtrip_failure_diff() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = temp_dir.path().join("first");
+        let second = temp_dir.path().join("second");
+        let diff_path = temp_dir.path().join("roundtrip.diff");
+        for dir in [&first, &second] {
+            fs::create_dir_all(dir.join("etc")).unwrap();
+            fs::write(dir.join("etc/same.conf"), "unchanged\n").unwrap();
+        }
+        fs::write(first.join("etc/app.conf"), "a\nb\nc\n").unwrap();
+        // Induce a mismatch as if the round trip had altered the content
+        fs::write(second.join("etc/app.conf"), "a\nB\nc\n").unwrap();
+
+        let err = crate::fuzz::compare_trees(&first, &second, Some(&diff_path)).unwrap_err();
+        assert!(err.to_string().contains("etc/app.conf"));
+
+        let diff = fs::read_to_string(&diff_path).unwrap();
+        assert_eq!(
+            diff,
+            "--- first/etc/app.conf\n+++ second/etc/app.conf\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+
+        // Identical trees pass and leave no diff behind
+        fs::remove_file(&diff_path).unwrap();
+        crate::fuzz::compare_trees(&first, &first, Some(&diff_path)).unwrap();
+        assert!(!diff_path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_links_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [{
+                    "path": "/etc/app.d/app.conf",
+                    "contents": { "source": "data:,setting%3D1" }
+                }],
+                "links": [
+                    { "path": "/etc/localtime", "target": "/usr/share/zoneinfo/UTC" },
+                    { "path": "/etc/app.conf", "target": "/etc/app.d/app.conf", "hard": true },
+                    // Collides with the directory holding the extracted file
+                    { "path": "/etc/app.d", "target": "/opt/app" }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            extract_links: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        assert_eq!(
+            fs::read_link(decoded_dir.join("etc/localtime")).unwrap(),
+            std::path::Path::new("/usr/share/zoneinfo/UTC")
+        );
+        assert_eq!(
+            fs::read_link(decoded_dir.join("etc/app.conf")).unwrap(),
+            std::path::Path::new("/etc/app.d/app.conf")
+        );
+        assert!(decoded_dir.join("etc/app.d/app.conf").is_file());
+        let links = fs::read_to_string(decoded_dir.join("links.json")).unwrap();
+        assert!(links.contains(r#""hard": true"#));
+
+        // Only the colliding link is left in decoded.ign
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        let kept = decoded["storage"]["links"].as_array().unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0]["path"], "/etc/app.d");
+
+        let options = AssembleOptions {
+            default: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_path).unwrap()).unwrap();
+        let mut assembled = output["storage"]["links"].as_array().unwrap().clone();
+        let mut expected = test_ignition["storage"]["links"]
+            .as_array()
+            .unwrap()
+            .clone();
+        let by_path = |a: &serde_json::Value, b: &serde_json::Value| {
+            a["path"].as_str().cmp(&b["path"].as_str())
+        };
+        assembled.sort_by(by_path);
+        expected.sort_by(by_path);
+        assert_eq!(assembled, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_links_keeps_nested_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+        let outside = temp_dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "links": [
+                    { "path": "/a", "target": outside.to_str().unwrap() },
+                    { "path": "/a/pwned", "target": "anything" },
+                    { "path": "/etc/localtime", "target": "/usr/share/zoneinfo/UTC" }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            extract_links: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        // The nested link is not created through the first one
+        assert!(!outside.join("pwned").is_symlink());
+        assert!(decoded_dir.join("a").is_symlink());
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        let kept = decoded["storage"]["links"].as_array().unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0]["path"], "/a/pwned");
+
+        // Every link survives the round trip
+        assemble_ignition(&output_path, &decoded_dir, &Default::default(), None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_path).unwrap()).unwrap();
+        let mut assembled: Vec<_> = output["storage"]["links"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| l["path"].as_str().unwrap().to_string())
+            .collect();
+        assembled.sort();
+        assert_eq!(assembled, ["/a", "/a/pwned", "/etc/localtime"]);
+
+        // A symlink already in the tree is not written through either
+        let config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": { "links": [ { "path": "/a/planted", "target": "anything" } ] }
+        });
+        fs::write(&input_path, config.to_string()).unwrap();
+        let options = DisassembleOptions {
+            extract_links: true,
+            action: Action::Add,
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("Refusing to write through symlink"),
+            "{:#}",
+            err
+        );
+        assert!(!outside.join("planted").is_symlink());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_add_refuses_writes_through_extracted_links() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let outside = temp_dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        fs::write(outside.join("motd"), "outside").unwrap();
+
+        let links = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "links": [
+                    { "path": "/etc/conf.d", "target": outside.to_str().unwrap() },
+                    { "path": "/etc/motd", "target": outside.join("motd").to_str().unwrap() }
+                ]
+            }
+        });
+        fs::write(&input_path, links.to_string()).unwrap();
+        let options = DisassembleOptions {
+            extract_links: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        // Files below or at an extracted link are refused on a later run
+        let options = DisassembleOptions {
+            action: Action::Add,
+            ..Default::default()
+        };
+        for (path, message) in [
+            ("/etc/conf.d/app.conf", "Refusing to write through symlink"),
+            ("/etc/motd", "Refusing to write over existing symlink"),
+        ] {
+            let config = serde_json::json!({
+                "ignition": { "version": "3.4.0" },
+                "storage": {
+                    "files": [ { "path": path, "contents": { "source": "data:,planted" } } ]
+                }
+            });
+            fs::write(&input_path, config.to_string()).unwrap();
+            let err = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap_err();
+            assert!(format!("{:#}", err).contains(message), "{:#}", err);
+        }
+        assert!(!outside.join("app.conf").exists());
+        assert_eq!(fs::read_to_string(outside.join("motd")).unwrap(), "outside");
+    }
+
+    #[test]
+    fn test_output_path_collision() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        // The contents would be written to etc/foo, the append entry to etc/foo/0
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [{
+                    "path": "/etc/foo",
+                    "contents": { "source": "data:,base" },
+                    "append": [{ "source": "data:,more" }]
+                }]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let err =
+            disassemble_ignition(&input_path, &decoded_dir, &Default::default(), None).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains("append[0] of /etc/foo (etc/foo/0)"),
+            "{}",
+            message
+        );
+        assert!(
+            message.contains("source of /etc/foo (etc/foo)"),
+            "{}",
+            message
+        );
+
+        // Two files at the same path collide on the very same output file
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    { "path": "/etc/bar", "contents": { "source": "data:,one" } },
+                    { "path": "/etc/bar", "contents": { "source": "data:,two" } }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+        let options = DisassembleOptions {
+            action: crate::Action::Replace,
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap_err();
+        assert!(format!("{:#}", err).contains("collides with source of /etc/bar"));
+    }
+
+    #[test]
+    fn test_media_type_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let sources = [
+            "data:text/plain;charset=utf-8;base64,aGVsbG8=",
+            "data:Text/Plain;Charset=UTF-8;base64,aGVsbG8=",
+            "data:application/json;charset=utf-8;base64,e30=",
+            "data:text/plain; charset=\"utf-8\";base64,aGVsbG8=",
+        ];
+        let files: Vec<serde_json::Value> = sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| {
+                serde_json::json!({
+                    "path": format!("/etc/file{}", i),
+                    "contents": { "source": source }
+                })
+            })
+            .collect();
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": { "files": files }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        disassemble_ignition(&input_path, &decoded_dir, &Default::default(), None).unwrap();
+        let decoded = fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap();
+        assert!(decoded.contains("data:Text/Plain;Charset=UTF-8;base64-placeholder,etc/file1"));
+
+        let options = AssembleOptions {
+            default: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_path).unwrap()).unwrap();
+        for (i, source) in sources.iter().enumerate() {
+            assert_eq!(output["storage"]["files"][i]["contents"]["source"], *source);
+        }
+    }
+
+    #[test]
+    fn test_manifest_artifacts() {
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"packed content").unwrap();
+        let gzipped = {
+            use base64::Engine;
+            base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap())
+        };
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    {
+                        "path": "/etc/zz",
+                        "contents": { "source": "data:,base" },
+                        "append": []
+                    },
+                    { "path": "/etc/motd", "append": [{ "source": "data:,hi" }] },
+                    {
+                        "path": "/etc/packed",
+                        "contents": {
+                            "source": format!("data:;base64,{}", gzipped),
+                            "compression": "gzip"
+                        }
+                    }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+        disassemble_ignition(&input_path, &decoded_dir, &Default::default(), None).unwrap();
+
+        let manifest = crate::manifest::Manifest::read(&decoded_dir)
+            .unwrap()
+            .unwrap();
+        let summary: Vec<(&str, &str, &str, Option<&str>, usize)> = manifest
+            .artifacts
+            .iter()
+            .map(|a| {
+                (
+                    a.ignition_path.as_str(),
+                    a.field.as_str(),
+                    a.file.as_str(),
+                    a.compression.as_deref(),
+                    a.size,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("/etc/motd", "append[0]", "etc/motd/0", None, 2),
+                ("/etc/packed", "contents", "etc/packed", Some("gzip"), 14),
+                ("/etc/zz", "contents", "etc/zz", None, 4),
+            ]
+        );
+        assert_eq!(
+            manifest.artifacts[0].media_type,
+            "text/plain;charset=US-ASCII"
+        );
+
+        // A file added by hand is reported, a listed one removed is missing
+        fs::write(decoded_dir.join("etc/stray"), "x").unwrap();
+        let sidecars = ["decoded.ign", "manifest.json"];
+        let warnings = manifest.validate_tree(&decoded_dir, &sidecars).unwrap();
+        assert_eq!(warnings, vec!["etc/stray is not listed in manifest.json"]);
+        fs::remove_file(decoded_dir.join("etc/zz")).unwrap();
+        let warnings = manifest.validate_tree(&decoded_dir, &sidecars).unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                "etc/stray is not listed in manifest.json",
+                "etc/zz is listed in manifest.json but missing",
+            ]
+        );
+
+        // Validation only warns
+        fs::write(decoded_dir.join("etc/zz"), "base").unwrap();
+        let options = AssembleOptions {
+            validate_manifest: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+    }
+
+    #[test]
+    fn test_disassemble_compact() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [{ "path": "/etc/motd", "contents": { "source": "data:,hello" } }]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            compact: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let decoded = fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap();
+        assert!(!decoded.contains('\n'));
+        assert!(decoded.contains(
+            r#""source":"data:text/plain;charset=US-ASCII;base64-placeholder,etc/motd""#
+        ));
+        // Only decoded.ign is affected
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/motd")).unwrap(),
+            "hello"
+        );
+    }
+
+    #[test]
+    fn test_indent() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [{ "path": "/etc/motd", "contents": { "source": "data:,hello" } }]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            indent: "4".parse().unwrap(),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        let decoded = fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap();
+        assert!(decoded.contains("\n    \"ignition\": {\n"));
+        assert!(decoded.contains("\n        \"version\": \"3.4.0\""));
+
+        let options = AssembleOptions {
+            indent: "tab".parse().unwrap(),
+            default: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output = fs::read_to_string(&output_path).unwrap();
+        assert!(output.contains("\n\t\"ignition\": {\n\t\t\"version\": \"3.4.0\""));
+
+        // --compact wins over --indent
+        let options = AssembleOptions {
+            indent: "4".parse().unwrap(),
+            compact: true,
+            action: crate::Action::Replace,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        assert!(!fs::read_to_string(&output_path).unwrap().contains('\n'));
+
+        assert!("four".parse::<crate::Indent>().is_err());
+    }
+
+    #[test]
+    fn test_diff_ignition() {
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let left_path = temp_dir.path().join("left.ign");
+        let right_path = temp_dir.path().join("right.ign");
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"same").unwrap();
+        let gzipped = {
+            use base64::Engine;
+            base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap())
+        };
+        let left = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    { "path": "/etc/same", "contents": { "source": "data:,same" } },
+                    { "path": "/etc/changed", "contents": { "source": "data:,old" } },
+                    { "path": "/etc/removed", "contents": { "source": "data:,x" } }
+                ]
+            },
+            "passwd": { "users": [{ "name": "core" }] }
+        });
+        // /etc/same is gzipped and base64 encoded, and keys are reordered
+        let right = serde_json::json!({
+            "passwd": { "users": [{ "name": "core" }, { "name": "admin" }] },
+            "storage": {
+                "files": [
+                    { "path": "/etc/changed", "contents": { "source": "data:,much%20newer" } },
+                    {
+                        "contents": {
+                            "compression": "gzip",
+                            "source": format!("data:;base64,{}", gzipped)
+                        },
+                        "path": "/etc/same"
+                    }
+                ]
+            },
+            "systemd": { "units": [{ "name": "new.service", "enabled": true }] },
+            "ignition": { "version": "3.4.0" }
+        });
+        fs::write(&left_path, left.to_string()).unwrap();
+        fs::write(&right_path, right.to_string()).unwrap();
+
+        let differences = crate::diff_ignition(&left_path, &right_path).unwrap();
+        assert_eq!(
+            differences,
+            vec![
+                "file /etc/changed changed: 3 -> 10 bytes",
+                "file /etc/removed removed",
+                "unit new.service added",
+                "user admin added",
+            ]
+        );
+        assert!(crate::diff_ignition(&left_path, &left_path)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "passwd": { "users": [{ "name": "core", "groups": ["wheel"] }] },
+            "storage": {
+                "files": [{ "path": "/etc/motd", "contents": { "source": "data:text/plain;charset=US-ASCII;base64,aGVsbG8=" } }]
+            },
+            "systemd": { "units": [{ "name": "a.service", "enabled": true }] }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+        let options = DisassembleOptions {
+            canonical_order: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        let decoded = fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap();
+        assert!(
+            decoded.find("\"name\": \"a.service\"").unwrap() < decoded.find("\"enabled\"").unwrap()
+        );
+
+        let options = AssembleOptions {
+            default: true,
+            canonical_order: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output = fs::read_to_string(&output_path).unwrap();
+        let position = |key: &str| output.find(&format!("\"{}\"", key)).unwrap();
+        assert!(position("ignition") < position("passwd"));
+        assert!(position("passwd") < position("storage"));
+        assert!(position("storage") < position("systemd"));
+        // Entries lead with the key naming them
+        assert!(position("name") < position("groups"));
+        assert!(position("path") < position("contents"));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&output).unwrap(),
+            test_ignition
+        );
+
+        // Without the option keys stay alphabetical
+        let options = AssembleOptions {
+            default: true,
+            action: crate::Action::Replace,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output = fs::read_to_string(&output_path).unwrap();
+        assert!(output.find("\"contents\"").unwrap() < output.find("\"path\"").unwrap());
+    }
+
+    #[test]
+    fn test_validate_assembled() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    { "path": "/etc/motd", "contents": { "source": "data:,hello" } },
+                    { "path": "/etc/issue", "contents": { "source": "data:,hi" } }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        let options = AssembleOptions {
+            validate: true,
+            action: crate::Action::Replace,
+            ..Default::default()
+        };
+
+        // A sidecar mode of the wrong type only fails the re-parse
+        let metadata_path = decoded_dir.join("metadata.json");
+        fs::write(&metadata_path, r#"{"/etc/issue": {"mode": "rw"}}"#).unwrap();
+        let err = assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("invalid at /storage/files/1/mode"),
+            "{:#}",
+            err
+        );
+        assert!(!output_path.exists());
+
+        // The typed parse accepts any integer mode
+        fs::write(&metadata_path, r#"{"/etc/motd": {"mode": 99999}}"#).unwrap();
+        let err = assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("invalid at /storage/files/0/mode: mode 99999"),
+            "{:#}",
+            err
+        );
+        assert!(!output_path.exists());
+
+        fs::write(&metadata_path, r#"{"/etc/motd": {"mode": 420}}"#).unwrap();
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        assert!(output_path.exists());
+
+        // A rejected config leaves the previous target untouched
+        let previous = fs::read_to_string(&output_path).unwrap();
+        fs::write(&metadata_path, r#"{"/etc/motd": {"mode": 99999}}"#).unwrap();
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap_err();
+        assert_eq!(fs::read_to_string(&output_path).unwrap(), previous);
+        let leftovers: Vec<_> = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+    }
+
+    #[test]
+    fn test_assemble_recomputes_verification_hashes() {
+        use crate::verification::HashAlgorithm;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let hello = HashAlgorithm::Sha512.hash(b"hello");
+        let tail = HashAlgorithm::Sha256.hash(b"tail");
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [{
+                    "path": "/etc/motd",
+                    "contents": {
+                        "source": "data:;base64,aGVsbG8=",
+                        "verification": { "hash": hello }
+                    }
+                }, {
+                    "path": "/etc/tail",
+                    "append": [{
+                        "source": "data:;base64,dGFpbA==",
+                        "verification": { "hash": tail }
+                    }]
+                }, {
+                    "path": "/etc/remote",
+                    "contents": {
+                        "source": "https://example.com/remote",
+                        "verification": { "hash": "sha256-0000" }
+                    }
+                }]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+        disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        fs::write(decoded_dir.join("etc/motd"), "edited").unwrap();
+        fs::write(decoded_dir.join("etc/tail/0"), "new tail").unwrap();
+        assemble_ignition(
+            &output_path,
+            &decoded_dir,
+            &AssembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        let files = &output["storage"]["files"];
+        assert_eq!(
+            files[0]["contents"]["verification"]["hash"],
+            HashAlgorithm::Sha512.hash(b"edited")
+        );
+        assert_eq!(
+            files[1]["append"][0]["verification"]["hash"],
+            HashAlgorithm::Sha256.hash(b"new tail")
+        );
+        // Remote content can't be rehashed
+        assert_eq!(files[2]["contents"]["verification"]["hash"], "sha256-0000");
+        crate::verification::verify_inline_hashes(&output).unwrap();
+    }
+
+    #[test]
+    fn test_strict_rejects_warnings() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        // `contens` is an unused key the parser only warns about
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [{ "path": "/etc/motd", "contens": { "source": "data:,hello" } }]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let strict = DisassembleOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &decoded_dir, &strict, None).unwrap_err();
+        assert!(err.to_string().contains("contens"), "{}", err);
+        assert!(!decoded_dir.exists());
+
+        // Without --strict the warning is only reported
+        let report = disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(report.warnings.len(), 1);
+
+        // An existing target survives a strict assembly that fails
+        let decoded_path = decoded_dir.join("decoded.ign");
+        let mut decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&decoded_path).unwrap()).unwrap();
+        decoded["storage"]["files"][0]["contens"] = serde_json::json!({ "source": "data:,hi" });
+        fs::write(&decoded_path, decoded.to_string()).unwrap();
+        fs::write(&output_path, "existing").unwrap();
+        let options = AssembleOptions {
+            strict: true,
+            action: crate::Action::Replace,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap_err();
+        assert_eq!(fs::read_to_string(&output_path).unwrap(), "existing");
+
+        let options = AssembleOptions {
+            action: crate::Action::Replace,
+            ..Default::default()
+        };
+        let report = assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        assert_eq!(report.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_inline_remotes() {
+        use crate::remotes::inline_remotes;
+        use crate::verification::HashAlgorithm;
+
+        let mut config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [{
+                    "path": "/etc/motd",
+                    "contents": {
+                        "source": "https://example.com/motd",
+                        "verification": { "hash": HashAlgorithm::Sha256.hash(b"hello") }
+                    },
+                    "append": [{
+                        "source": "http://example.com/tail",
+                        "httpHeaders": [{ "name": "Authorization", "value": "token" }]
+                    }]
+                }, {
+                    "path": "/etc/local",
+                    "contents": { "source": "data:,local" }
+                }, {
+                    "path": "/etc/bucket",
+                    "contents": { "source": "s3://bucket/key" }
+                }]
+            }
+        });
+        let mut fetched = Vec::new();
+        let mut fetch = |url: &str| -> anyhow::Result<Vec<u8>> {
+            fetched.push(url.to_string());
+            Ok(url
+                .rsplit('/')
+                .next()
+                .unwrap()
+                .replace("motd", "hello")
+                .into_bytes())
+        };
+        let inlined = inline_remotes(&mut config, &mut fetch).unwrap();
+        assert_eq!(inlined, 2);
+        // Keys are walked in order, so `append` comes before `contents`
+        assert_eq!(
+            fetched,
+            vec!["http://example.com/tail", "https://example.com/motd"]
+        );
+
+        let files = &config["storage"]["files"];
+        assert_eq!(files[0]["contents"]["source"], "data:;base64,aGVsbG8=");
+        let append = &files[0]["append"][0];
+        assert_eq!(append["source"], "data:;base64,dGFpbA==");
+        assert_eq!(
+            append["verification"]["hash"],
+            HashAlgorithm::Sha512.hash(b"tail")
+        );
+        assert!(append.get("httpHeaders").is_none());
+        assert_eq!(files[1]["contents"]["source"], "data:,local");
+        assert_eq!(files[2]["contents"]["source"], "s3://bucket/key");
+        crate::verification::verify_inline_hashes(&config).unwrap();
+
+        // Content that doesn't match the recorded hash is refused
+        let mut config = serde_json::json!({
+            "storage": { "files": [{
+                "path": "/etc/motd",
+                "contents": {
+                    "source": "https://example.com/motd",
+                    "verification": { "hash": HashAlgorithm::Sha512.hash(b"hello") }
+                }
+            }] }
+        });
+        let err = inline_remotes(&mut config, &mut |_: &str| Ok(b"tampered".to_vec()))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("does not match its verification hash"),
+            "{}",
+            err
+        );
+
+        // Fetch failures abort
+        let mut config = serde_json::json!({
+            "storage": { "files": [{
+                "path": "/etc/motd",
+                "contents": { "source": "https://example.com/motd" }
+            }] }
+        });
+        let err = inline_remotes(&mut config, &mut |url: &str| {
+            anyhow::bail!("{} returned 404", url)
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("404"));
+    }
+
+    #[test]
+    fn test_disassemble_writes_references() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = serde_json::json!({
+            "ignition": {
+                "version": "3.4.0",
+                "config": { "merge": [{ "source": "https://example.com/base.ign" }] }
+            },
+            "storage": {
+                "files": [{
+                    "path": "/etc/motd",
+                    "contents": {
+                        "source": "https://example.com/motd",
+                        "verification": { "hash": "sha256-0000" },
+                        "httpHeaders": [{ "name": "Authorization", "value": "token" }]
+                    }
+                }, {
+                    "path": "/etc/tail",
+                    "append": [
+                        { "source": "data:,local" },
+                        { "source": "tftp://boot/tail" }
+                    ]
+                }]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+        let report = disassemble_ignition(
+            &input_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(report.remote_count, 3);
+        assert_eq!(report.file_count, 1);
+
+        let references: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("references.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            references,
+            serde_json::json!([
+                { "path": "", "source": "https://example.com/base.ign" },
+                {
+                    "path": "/etc/motd",
+                    "source": "https://example.com/motd",
+                    "verification": { "hash": "sha256-0000" },
+                    "httpHeaders": [{ "name": "Authorization", "value": "token" }]
+                },
+                { "path": "/etc/tail", "entry": "append[1]", "source": "tftp://boot/tail" }
+            ])
+        );
+
+        // The sources stay in decoded.ign
+        let decoded = fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap();
+        assert!(decoded.contains("https://example.com/motd"));
+        assert!(decoded.contains("tftp://boot/tail"));
+    }
+
+    #[test]
+    fn test_merge_decoded_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let decode = |name: &str, config: serde_json::Value| {
+            let input_path = temp_dir.path().join(format!("{}.ign", name));
+            let decoded_dir = temp_dir.path().join(name);
+            fs::write(&input_path, config.to_string()).unwrap();
+            disassemble_ignition(
+                &input_path,
+                &decoded_dir,
+                &DisassembleOptions::default(),
+                None,
+            )
+            .unwrap();
+            decoded_dir
+        };
+
+        let base = decode(
+            "base",
+            serde_json::json!({
+                "ignition": { "version": "3.4.0" },
+                "storage": { "files": [
+                    { "path": "/etc/motd", "mode": 420, "contents": { "source": "data:,base" } },
+                    { "path": "/etc/tail", "append": [{ "source": "data:,one" }] }
+                ] }
+            }),
+        );
+        let overlay = decode(
+            "overlay",
+            serde_json::json!({
+                "ignition": { "version": "3.4.0" },
+                "storage": { "files": [
+                    { "path": "/etc/motd", "contents": { "source": "data:,overlay" } },
+                    { "path": "/etc/tail", "append": [{ "source": "data:,two" }] },
+                    { "path": "/etc/extra", "contents": { "source": "data:,extra" } }
+                ] }
+            }),
+        );
+
+        let merged_dir = temp_dir.path().join("merged");
+        let report =
+            crate::merge_decoded_dirs(&[base.clone(), overlay.clone()], &merged_dir, false)
+                .unwrap();
+        assert_eq!(report.file_count, 4);
+        assert_eq!(
+            fs::read_to_string(merged_dir.join("etc/tail/0.1")).unwrap(),
+            "two"
+        );
+
+        // Writing over an existing merge is refused
+        crate::merge_decoded_dirs(&[base, overlay], &merged_dir, false).unwrap_err();
+
+        let output_path = temp_dir.path().join("merged.ign");
+        assemble_ignition(&output_path, &merged_dir, &AssembleOptions::default(), None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        let content = |resource: &serde_json::Value| {
+            String::from_utf8(
+                crate::verification::inline_content(resource)
+                    .unwrap()
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+        let files = output["storage"]["files"].as_array().unwrap();
+        assert_eq!(files.len(), 3);
+        // The later input wins on /etc/motd, keeping fields it doesn't set
+        assert_eq!(files[0]["path"], "/etc/motd");
+        assert_eq!(files[0]["mode"], 420);
+        assert_eq!(content(&files[0]["contents"]), "overlay");
+        // Appends are concatenated
+        assert_eq!(content(&files[1]["append"][0]), "one");
+        assert_eq!(content(&files[1]["append"][1]), "two");
+        assert_eq!(files[2]["path"], "/etc/extra");
+        assert_eq!(content(&files[2]["contents"]), "extra");
+    }
+
+    #[test]
+    fn test_convert_ignition() {
+        let temp_dir = TempDir::new().unwrap();
+        let legacy_path = temp_dir.path().join("legacy.ign");
+        fs::write(
+            &legacy_path,
+            serde_json::json!({
+                "ignition": { "version": "3.0.0" },
+                "storage": {
+                    "files": [{ "path": "/etc/motd", "contents": { "source": "data:,hello" } }]
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let upgraded_path = temp_dir.path().join("upgraded.ign");
+        let dropped = crate::convert_ignition(&legacy_path, &upgraded_path, "3.4").unwrap();
+        assert!(dropped.is_empty());
+        let upgraded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&upgraded_path).unwrap()).unwrap();
+        assert_eq!(upgraded["ignition"]["version"], "3.4.0");
+        assert_eq!(
+            upgraded["storage"]["files"][0]["contents"]["source"],
+            "data:,hello"
+        );
+        // The output is never overwritten
+        crate::convert_ignition(&legacy_path, &upgraded_path, "3.4").unwrap_err();
+
+        // A downgrade that keeps every field is fine
+        let downgraded_path = temp_dir.path().join("downgraded.ign");
+        crate::convert_ignition(&upgraded_path, &downgraded_path, "3.0.0").unwrap();
+
+        // kernelArguments only exists since 3.3
+        let modern_path = temp_dir.path().join("modern.ign");
+        fs::write(
+            &modern_path,
+            serde_json::json!({
+                "ignition": { "version": "3.4.0" },
+                "kernelArguments": { "shouldExist": ["quiet"] }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let lossy_path = temp_dir.path().join("lossy.ign");
+        let err = crate::convert_ignition(&modern_path, &lossy_path, "3.2.0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("would lose"), "{}", err);
+        assert!(err.contains("kernelArguments"), "{}", err);
+        assert!(!lossy_path.exists());
+
+        crate::convert_ignition(&modern_path, &lossy_path, "3.9.0").unwrap_err();
+    }
+
+    #[test]
+    fn test_disassemble_dedup() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        // The same bytes, encoded differently, at three places
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": { "files": [
+                { "path": "/etc/a", "contents": { "source": "data:,same" } },
+                { "path": "/etc/b", "contents": { "source": "data:;base64,c2FtZQ==" } },
+                { "path": "/etc/c", "contents": { "source": "data:,other" } },
+                { "path": "/etc/d", "append": [{ "source": "data:,same" }] }
+            ] }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+        let options = DisassembleOptions {
+            dedup: true,
+            ..Default::default()
+        };
+        let report = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        assert_eq!(report.file_count, 4);
+
+        let blob = crate::ContentDigest::Sha256.blob_path(b"same");
+        assert_eq!(fs::read_to_string(decoded_dir.join(&blob)).unwrap(), "same");
+        assert!(!decoded_dir.join("etc/a").exists());
+        assert!(!decoded_dir.join("etc/b").exists());
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/c")).unwrap(),
+            "other"
+        );
+
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        let files = &decoded["storage"]["files"];
+        for source in [
+            &files[0]["contents"]["source"],
+            &files[1]["contents"]["source"],
+            &files[3]["append"][0]["source"],
+        ] {
+            assert!(source.as_str().unwrap().ends_with(&blob), "{}", source);
+        }
+
+        // Every placeholder reads the shared blob back
+        assemble_ignition(
+            &output_path,
+            &decoded_dir,
+            &AssembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        let files = &output["storage"]["files"];
+        for resource in [
+            &files[0]["contents"],
+            &files[1]["contents"],
+            &files[3]["append"][0],
+        ] {
+            assert_eq!(
+                crate::verification::inline_content(resource)
+                    .unwrap()
+                    .unwrap(),
+                b"same"
+            );
+        }
+    }
+
+    #[test]
+    fn test_media_type_extension() {
+        use crate::media_type_extension;
+
+        assert_eq!(media_type_extension("text/plain;charset=US-ASCII"), "data");
+        assert_eq!(media_type_extension("application/x-pem-file"), "pem");
+        assert_eq!(media_type_extension("application/gzip"), "gz");
+        assert_eq!(media_type_extension("application/octet-stream"), "bin");
+        assert_eq!(media_type_extension("Application/JSON"), "json");
+        // Unknown types fall back to their subtype
+        assert_eq!(media_type_extension("application/wasm"), "wasm");
+        assert_eq!(media_type_extension("application/vnd.example+json"), "json");
+        assert_eq!(media_type_extension("application/x-rpm"), "rpm");
+        // ... unless it doesn't look like an extension
+        assert_eq!(media_type_extension("application/vnd.ms-excel"), "data");
+        assert_eq!(media_type_extension(""), "data");
+    }
+
+    #[test]
+    fn test_generated_file_names_follow_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let decode = |files: serde_json::Value| {
+            let input_path = temp_dir.path().join("test.ign");
+            let decoded_dir = temp_dir.path().join("decoded");
+            let _ = fs::remove_dir_all(&decoded_dir);
+            let config = serde_json::json!({
+                "ignition": { "version": "3.4.0" },
+                "storage": { "files": files }
+            });
+            fs::write(&input_path, config.to_string()).unwrap();
+            disassemble_ignition(
+                &input_path,
+                &decoded_dir,
+                &DisassembleOptions::default(),
+                None,
+            )
+            .unwrap();
+            fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap()
+        };
+
+        let name = crate::generated_file_name("application/wasm", b"wasm", 0);
+        assert!(name.starts_with("extracted_") && name.ends_with(".wasm"));
+        assert_eq!(name.len(), "extracted_.wasm".len() + 12);
+
+        let first = decode(serde_json::json!([
+            { "path": "", "contents": { "source": "data:application/json,%7B%7D" } }
+        ]));
+        // A source added before it doesn't rename it
+        let second = decode(serde_json::json!([
+            { "path": "", "contents": { "source": "data:,other" } },
+            { "path": "", "contents": { "source": "data:application/json,%7B%7D" } }
+        ]));
+        let json_name = crate::generated_file_name("application/json", b"{}", 0);
+        assert!(first.contains(&json_name));
+        assert!(second.contains(&json_name));
+
+        // The same content twice gets a counter
+        let twice = decode(serde_json::json!([
+            { "path": "", "contents": { "source": "data:application/json,%7B%7D" } },
+            { "path": "", "contents": { "source": "data:application/json,%7B%7D" } }
+        ]));
+        assert!(twice.contains(&json_name));
+        assert!(twice.contains(&crate::generated_file_name("application/json", b"{}", 1)));
+    }
+
+    #[test]
+    fn test_parallel_extraction_matches_sequential() {
+        use base64::Engine;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let mut files: Vec<serde_json::Value> = (0..50)
+            .map(|i| {
+                let content = format!("file {}\n", i % 20).repeat(i + 1);
+                serde_json::json!({
+                    "path": format!("/etc/many/{}", i),
+                    "contents": {
+                        "source": format!(
+                            "data:;base64,{}",
+                            base64::engine::general_purpose::STANDARD.encode(content)
+                        )
+                    }
+                })
+            })
+            .collect();
+        files.push(serde_json::json!({
+            "path": "/etc/tail",
+            "append": [{ "source": "data:,same" }, { "source": "data:,same" }]
+        }));
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": { "files": files }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let sequential_dir = temp_dir.path().join("sequential");
+        let parallel_dir = temp_dir.path().join("parallel");
+        let sequential =
+            disassemble_ignition(&input_path, &sequential_dir, &Default::default(), None).unwrap();
+        let options = DisassembleOptions {
+            parallel: true,
+            ..Default::default()
+        };
+        let parallel = disassemble_ignition(&input_path, &parallel_dir, &options, None).unwrap();
+
+        assert_eq!(parallel.file_count, 52);
+        assert_eq!(parallel.file_count, sequential.file_count);
+        crate::fuzz::compare_trees(&sequential_dir, &parallel_dir, None).unwrap();
+
+        // Shared blobs are written once
+        let blobs_dir = temp_dir.path().join("blobs");
+        let options = DisassembleOptions {
+            parallel: true,
+            dedup: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &blobs_dir, &options, None).unwrap();
+        let sequential_blobs = temp_dir.path().join("sequential-blobs");
+        let options = DisassembleOptions {
+            dedup: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &sequential_blobs, &options, None).unwrap();
+        crate::fuzz::compare_trees(&sequential_blobs, &blobs_dir, None).unwrap();
+    }
+
+    #[test]
+    fn test_include_exclude_leave_unselected_sources_inline() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    { "path": "/etc/a.conf", "contents": { "source": "data:,a" } },
+                    { "path": "/etc/pki/key", "contents": { "source": "data:,key" } },
+                    { "path": "/etc/b", "append": [ { "source": "data:,b" } ] },
+                    { "path": "/var/blob", "contents": { "source": "data:,blob" } }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            include: vec!["/etc/**".parse().unwrap()],
+            exclude: vec!["/etc/pki/*".parse().unwrap()],
+            ..Default::default()
+        };
+        let report = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        assert_eq!(report.file_count, 2);
+
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/a.conf")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/b/0")).unwrap(),
+            "b"
+        );
+        assert!(!decoded_dir.join("etc/pki").exists());
+        assert!(!decoded_dir.join("var").exists());
+
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        let files = &decoded["storage"]["files"];
+        assert_eq!(files[1]["contents"]["source"], "data:,key");
+        assert_eq!(files[3]["contents"]["source"], "data:,blob");
+
+        // Sources left inline are embedded again as they were
+        let output = temp_dir.path().join("output.ign");
+        assemble_ignition(&output, &decoded_dir, &Default::default(), None).unwrap();
+        let assembled: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output).unwrap()).unwrap();
+        assert_eq!(
+            assembled["storage"]["files"][1]["contents"]["source"],
+            "data:,key"
+        );
+    }
+
+    #[test]
+    fn test_base64url_sources_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let content = [0xfb, 0xff, 0xbf];
+        let hash = crate::verification::HashAlgorithm::Sha256.hash(&content);
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    {
+                        "path": "/etc/url",
+                        "contents": {
+                            "source": "data:application/octet-stream;base64,-_-_",
+                            "verification": { "hash": hash }
+                        }
+                    },
+                    {
+                        "path": "/etc/std",
+                        "contents": { "source": "data:application/octet-stream;base64,+/+/" }
+                    }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+        disassemble_ignition(&input_path, &decoded_dir, &Default::default(), None).unwrap();
+
+        assert_eq!(fs::read(decoded_dir.join("etc/url")).unwrap(), content);
+        assert_eq!(fs::read(decoded_dir.join("etc/std")).unwrap(), content);
+        let decoded = fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap();
+        assert!(
+            decoded.contains("data:application/octet-stream;base64url;base64-placeholder,etc/url")
+        );
+
+        let assemble = |options: AssembleOptions, name: &str| {
+            let target = temp_dir.path().join(name);
+            assemble_ignition(&target, &decoded_dir, &options, None).unwrap();
+            let config: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(target).unwrap()).unwrap();
+            let files = &config["storage"]["files"];
+            (
+                files[0]["contents"]["source"].as_str().unwrap().to_string(),
+                files[1]["contents"]["source"].as_str().unwrap().to_string(),
+            )
+        };
+
+        // Each source keeps its alphabet, streamed or not
+        let expected = (
+            "data:application/octet-stream;base64,-_-_".to_string(),
+            "data:application/octet-stream;base64,+/+/".to_string(),
+        );
+        assert_eq!(assemble(Default::default(), "kept.ign"), expected);
+        let chunked = AssembleOptions {
+            chunked_write: true,
+            ..Default::default()
+        };
+        assert_eq!(assemble(chunked, "chunked.ign"), expected);
+
+        let forced = AssembleOptions {
+            base64url: true,
+            ..Default::default()
+        };
+        let (_, std_source) = assemble(forced, "forced.ign");
+        assert_eq!(std_source, "data:application/octet-stream;base64,-_-_");
+    }
+
+    #[test]
+    fn test_extraction_errors_name_json_pointer() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    { "path": "/etc/good", "contents": { "source": "data:,good" } },
+                    {
+                        "path": "/etc/broken",
+                        "append": [
+                            { "source": "data:,fine" },
+                            { "source": "data:;base64,not*base64" }
+                        ]
+                    }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let err =
+            disassemble_ignition(&input_path, &decoded_dir, &Default::default(), None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at /storage/files/1/append/1/source",
+            "{:#}",
+            err
+        );
+        assert!(format!("{:#}", err).contains("Failed to decode base64 body for /etc/broken"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_mode_and_mode_from_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    { "path": "/usr/bin/tool", "mode": 0o4750, "contents": { "source": "data:,x" } },
+                    { "path": "/etc/secret", "contents": { "source": "data:,s" } }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            apply_mode: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        let mode = |path: &str| {
+            fs::metadata(decoded_dir.join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o7777
+        };
+        assert_eq!(mode("usr/bin/tool"), 0o750);
+
+        fs::set_permissions(
+            decoded_dir.join("etc/secret"),
+            fs::Permissions::from_mode(0o600),
+        )
+        .unwrap();
+        let options = AssembleOptions {
+            mode_from_file: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        let files = &output["storage"]["files"];
+        // The declared mode wins over the file, special bits included
+        assert_eq!(files[0]["mode"], 0o4750);
+        assert_eq!(files[1]["mode"], 0o600);
+    }
+
+    #[test]
+    fn test_extract_ssh_keys_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let passwd = serde_json::json!({
+            "users": [
+                {
+                    "name": "core",
+                    "sshAuthorizedKeys": [
+                        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIB core@laptop",
+                        "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQ core@desktop"
+                    ]
+                },
+                {
+                    "name": "admin",
+                    "groups": ["wheel"],
+                    "sshAuthorizedKeys": [
+                        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIC admin@one",
+                        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAID admin@two",
+                        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIE admin@three"
+                    ]
+                },
+                { "name": "nokeys" }
+            ]
+        });
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "passwd": passwd
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            extract_ssh_keys: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("passwd/admin/authorized_keys")).unwrap(),
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIC admin@one\n\
+             ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAID admin@two\n\
+             ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIE admin@three\n"
+        );
+        assert!(decoded_dir.join("passwd/core/authorized_keys").is_file());
+        assert!(!decoded_dir.join("passwd/nokeys").exists());
+        let decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("decoded.ign")).unwrap())
+                .unwrap();
+        assert_eq!(
+            decoded["passwd"]["users"][0]["sshAuthorizedKeys"],
+            serde_json::json!(["data:text/plain;base64-placeholder,passwd/core/authorized_keys"])
+        );
+
+        let options = AssembleOptions {
+            default: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output_path).unwrap()).unwrap();
+        assert_eq!(output["passwd"], passwd);
+    }
+
+    #[test]
+    fn test_info_summarizes_config() {
+        let config = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    { "path": "/etc/a", "contents": { "source": "data:,abc" } },
+                    {
+                        "path": "/etc/b",
+                        "contents": {
+                            "compression": "gzip",
+                            "source": "data:;base64,H4sIAAAAAAAA/8tIzcnJBwCGphA2BQAAAA=="
+                        },
+                        "append": [ { "source": "https://example.com/tail" } ]
+                    }
+                ],
+                "links": [ { "path": "/etc/l", "target": "/etc/a" } ],
+                "directories": [ { "path": "/var/d" } ]
+            },
+            "systemd": { "units": [ { "name": "a.service" }, { "name": "b.service" } ] },
+            "passwd": { "users": [ { "name": "core" } ], "groups": [ { "name": "ops" } ] }
+        });
+
+        let info = crate::info::collect(&config).unwrap();
+        assert_eq!(
+            serde_json::to_value(&info).unwrap(),
+            serde_json::json!({
+                "version": "3.4.0",
+                "files": 2,
+                "links": 1,
+                "directories": 1,
+                "units": 2,
+                "users": 1,
+                "groups": 1,
+                "decoded_bytes": 8,
+                "compression": { "gzip": 1, "none": 1 },
+                "remote_sources": 1,
+                "has_remote_sources": true
+            })
+        );
+        assert!(info
+            .to_string()
+            .contains("Compression     gzip: 1, none: 1\n"));
+    }
+
+    #[test]
+    fn test_version_string_survives_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        for version in ["3.0.0", "3.1.0", "3.2.0", "3.3.0", "3.4.0", "3.5.0"] {
+            let input_path = temp_dir.path().join(format!("{}.ign", version));
+            let decoded_dir = temp_dir.path().join(format!("{}.d", version));
+            let output_path = temp_dir.path().join(format!("{}.out.ign", version));
+            fs::write(
+                &input_path,
+                format!(r#"{{ "ignition": {{ "version": "{}" }} }}"#, version),
+            )
+            .unwrap();
+
+            disassemble_ignition(&input_path, &decoded_dir, &Default::default(), None).unwrap();
+            assemble_ignition(&output_path, &decoded_dir, &Default::default(), None).unwrap();
+
+            for written in [decoded_dir.join("decoded.ign"), output_path] {
+                let config: serde_json::Value =
+                    serde_json::from_str(&fs::read_to_string(written).unwrap()).unwrap();
+                assert_eq!(config["ignition"]["version"], version);
+            }
+        }
+
+        // The typed parser only accepts the exact spelling of a spec version,
+        // so nothing else can reach serialization to be normalized
+        for version in ["3.4", "3.4.00", "v3.4.0", "3.4.0+build"] {
+            let input_path = temp_dir.path().join("noncanonical.ign");
+            fs::write(
+                &input_path,
+                format!(r#"{{ "ignition": {{ "version": "{}" }} }}"#, version),
+            )
+            .unwrap();
+            let decoded_dir = temp_dir.path().join("noncanonical.d");
+            assert!(
+                disassemble_ignition(&input_path, &decoded_dir, &Default::default(), None).is_err(),
+                "{}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn test_action_merge_merges_decoded_ign() {
+        let temp_dir = TempDir::new().unwrap();
+        let first_path = temp_dir.path().join("first.ign");
+        let second_path = temp_dir.path().join("second.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let target_file = temp_dir.path().join("output.ign");
+
+        fs::write(
+            &first_path,
+            r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/first", "contents": { "source": "data:,first" } },
+      { "path": "/etc/shared", "mode": 384, "contents": { "source": "data:,old" } }
+    ]
+  }
+}"#,
+        )
+        .unwrap();
+        fs::write(
+            &second_path,
+            r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/shared", "contents": { "source": "data:,new" } },
+      { "path": "/etc/second", "contents": { "source": "data:,second" } }
+    ]
+  }
+}"#,
+        )
+        .unwrap();
+        disassemble_ignition(
+            &first_path,
+            &decoded_dir,
+            &DisassembleOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        // An entry added by hand survives the merge
+        let decoded_path = decoded_dir.join("decoded.ign");
+        let mut decoded: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&decoded_path).unwrap()).unwrap();
+        decoded["storage"]["directories"] = serde_json::json!([{ "path": "/var/manual" }]);
+        fs::write(&decoded_path, decoded.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            action: Action::Merge,
+            ..Default::default()
+        };
+        disassemble_ignition(&second_path, &decoded_dir, &options, None).unwrap();
+
+        // The shared path merges field by field: the new content wins and
+        // the mode the second config leaves unset is kept
+        assert_eq!(
+            fs::read_to_string(decoded_dir.join("etc/shared")).unwrap(),
+            "new"
+        );
+        assemble_ignition(
+            &target_file,
+            &decoded_dir,
+            &AssembleOptions::default(),
+            None,
+        )
+        .unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&target_file).unwrap()).unwrap();
+        let files = output["storage"]["files"].as_array().unwrap();
+        let paths: Vec<_> = files.iter().map(|f| f["path"].as_str().unwrap()).collect();
+        assert_eq!(paths, ["/etc/first", "/etc/shared", "/etc/second"]);
+        assert_eq!(files[1]["mode"], 384);
+        assert_eq!(
+            files[1]["contents"]["source"],
+            "data:text/plain;charset=US-ASCII;base64,bmV3"
+        );
+        assert_eq!(output["storage"]["directories"][0]["path"], "/var/manual");
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            manifest["file_order"],
+            serde_json::json!(["/etc/first", "/etc/shared", "/etc/second"])
+        );
+        assert_eq!(manifest["artifacts"].as_array().unwrap().len(), 3);
+
+        // Assembly has no decoded.ign to merge into
+        let options = AssembleOptions {
+            action: Action::Merge,
+            ..Default::default()
+        };
+        let err = assemble_ignition(&target_file, &decoded_dir, &options, None).unwrap_err();
+        assert!(err.to_string().contains("only applies to disassembly"));
+    }
+
+    #[test]
+    fn test_ign_name_picks_decoded_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let target_file = temp_dir.path().join("output.ign");
+
+        fs::write(
+            &input_path,
+            r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } }
+    ]
+  }
+}"#,
+        )
+        .unwrap();
+        let options = DisassembleOptions {
+            ign_name: Some("custom.ign".to_string()),
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        assert!(decoded_dir.join("custom.ign").is_file());
+        assert!(!decoded_dir.join("decoded.ign").exists());
+
+        // A template sorting before the named config is not picked up
+        fs::write(
+            decoded_dir.join("aaa-template.ign"),
+            r#"{"ignition": {"version": "3.4.0"}}"#,
+        )
+        .unwrap();
+        let options = AssembleOptions {
+            ign_name: Some("custom.ign".to_string()),
+            ..Default::default()
+        };
+        assemble_ignition(&target_file, &decoded_dir, &options, None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&target_file).unwrap()).unwrap();
+        assert_eq!(output["storage"]["files"][0]["path"], "/etc/test");
+
+        // Without the named file, the first .ign by name is used
+        let options = AssembleOptions {
+            action: Action::Replace,
+            ..Default::default()
+        };
+        assemble_ignition(&target_file, &decoded_dir, &options, None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&target_file).unwrap()).unwrap();
+        assert!(output.get("storage").is_none());
+
+        let options = DisassembleOptions {
+            ign_name: Some("../escape.ign".to_string()),
+            action: Action::Replace,
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap_err();
+        assert!(err.to_string().contains("must be a plain file name"));
+        assert!(decoded_dir.join("custom.ign").is_file());
+
+        // Errors name the config actually in use
+        fs::write(decoded_dir.join("custom.ign"), "not json").unwrap();
+        let options = DisassembleOptions {
+            ign_name: Some("custom.ign".to_string()),
+            action: Action::Merge,
+            ..Default::default()
+        };
+        let err = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap_err();
+        assert!(format!("{:#}", err).contains("Failed to parse custom.ign"));
+    }
+
+    #[test]
+    fn test_split_units_dir_groups_by_install_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "systemd": {
+                "units": [
+                    {
+                        "name": "app.service",
+                        "enabled": true,
+                        "contents": "[Service]\nExecStart=/usr/bin/app\n\n[Install]\nWantedBy=multi-user.target default.target\n",
+                        "dropins": [{ "name": "10-env.conf", "contents": "[Service]\nNice=5\n" }]
+                    },
+                    {
+                        "name": "backup.timer",
+                        "contents": "[Timer]\nOnCalendar=daily\n\n[Install]\nWantedBy=timers.target\n"
+                    },
+                    {
+                        "name": "storage.mount",
+                        "contents": "[Mount]\nWhat=/dev/sdb\nWhere=/storage\n\n[Install]\nRequiredBy=local-fs.target\n"
+                    },
+                    {
+                        "name": "oneshot.service",
+                        "contents": "[Service]\nType=oneshot\nExecStart=/usr/bin/true\n"
+                    }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            extract_units: true,
+            split_units_dir: true,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        let expected = [
+            ("app.service", "units/multi-user.target.wants/app.service"),
+            (
+                "app.service.d/10-env.conf",
+                "units/multi-user.target.wants/app.service.d/10-env.conf",
+            ),
+            ("backup.timer", "units/timers.target.wants/backup.timer"),
+            ("oneshot.service", "units/oneshot.service"),
+            (
+                "storage.mount",
+                "units/local-fs.target.requires/storage.mount",
+            ),
+        ];
+        for (_, file) in expected {
+            assert!(decoded_dir.join(file).is_file(), "{}", file);
+        }
+
+        // The manifest records where each unit went
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("manifest.json")).unwrap())
+                .unwrap();
+        let recorded: Vec<(&str, &str)> = manifest["artifacts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| {
+                (
+                    a["ignition_path"].as_str().unwrap(),
+                    a["file"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(recorded, expected);
+
+        let options = AssembleOptions {
+            default: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output_path).unwrap()).unwrap();
+        assert_eq!(output, test_ignition);
+    }
+
+    #[test]
+    fn test_strict_accepts_clean_older_spec_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+        let output_path = temp_dir.path().join("output.ign");
+
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.0.0" },
+            "storage": {
+                "files": [{ "path": "/etc/motd", "contents": { "source": "data:,hello" } }]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        let options = DisassembleOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let report = disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+        assert!(report.warnings.is_empty());
+
+        let options = AssembleOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assemble_ignition(&output_path, &decoded_dir, &options, None).unwrap();
+        let output = fs::read_to_string(&output_path).unwrap();
+        assert!(output.contains("\"3.0.0\""));
+    }
+
+    #[test]
+    fn test_keep_going_rolls_back_only_what_the_section_added() {
+        use base64::Engine;
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("test.ign");
+        let decoded_dir = temp_dir.path().join("decoded");
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"zipped").unwrap();
+        let zipped = base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap());
+        let test_ignition = serde_json::json!({
+            "ignition": { "version": "3.4.0" },
+            "storage": {
+                "files": [
+                    { "path": "/etc/mine", "contents": { "source": "data:,from%20config" } },
+                    { "path": "/etc/new", "contents": { "source": "data:,new" } },
+                    {
+                        "path": "/etc/zipped",
+                        "contents": {
+                            "source": format!("data:;base64,{}", zipped),
+                            "compression": "gzip"
+                        }
+                    },
+                    { "path": "/etc/remote", "contents": { "source": "https://example.com/remote" } },
+                    { "path": "/etc/broken", "contents": { "source": "data:no-comma" } }
+                ]
+            }
+        });
+        fs::write(&input_path, test_ignition.to_string()).unwrap();
+
+        // A file of the user's that the failing section overwrites
+        fs::create_dir_all(decoded_dir.join("etc")).unwrap();
+        fs::write(decoded_dir.join("etc/mine"), "user content").unwrap();
+
+        let options = DisassembleOptions {
+            keep_going: true,
+            action: Action::Add,
+            ..Default::default()
+        };
+        disassemble_ignition(&input_path, &decoded_dir, &options, None).unwrap();
+
+        assert!(decoded_dir.join("etc/mine").is_file());
+        assert!(!decoded_dir.join("etc/new").exists());
+        assert!(!decoded_dir.join("etc/zipped").exists());
+
+        // No sidecar lists what the skipped section extracted or references
+        assert!(!decoded_dir.join("references.json").exists());
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(decoded_dir.join("manifest.json")).unwrap())
+                .unwrap();
+        assert!(manifest.get("gzip_digests").is_none(), "{}", manifest);
+        assert!(manifest.get("artifacts").is_none(), "{}", manifest);
+    }
+
+    #[test]
+    fn test_parallel_skips_excluded_sources_and_records_only_written_files() {
+        use crate::progress::{FileEvent, ProgressSink};
+
+        #[derive(Default)]
+        struct Capture(Vec<std::path::PathBuf>);
+
+        impl ProgressSink for Capture {
+            fn on_file_extracted(&mut self, event: &FileEvent) {
+                self.0.push(event.file.clone());
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("decoded");
+        let config = serde_json::json!({
+            "storage": {
+                "files": [
+                    { "path": "/etc/kept", "contents": { "source": "data:,kept" } },
+                    { "path": "/etc/skipped", "contents": { "source": "data:,skipped" } }
+                ]
+            }
+        });
+        let options = DisassembleOptions {
+            parallel: true,
+            exclude: vec!["/etc/skipped".parse().unwrap()],
+            ..Default::default()
+        };
+
+        // Excluded sources are not decoded ahead of the walk
+        let mut capture = Capture::default();
+        let mut extractor = crate::Extractor::new(&output_dir, &options, false, Some(&mut capture));
+        extractor.predecode(&config).unwrap();
+        assert_eq!(
+            extractor.predecoded.keys().collect::<Vec<_>>(),
+            ["data:,kept"]
+        );
+
+        // Nothing is recorded before the write pass, and a failed write
+        // is never recorded
+        extractor
+            .write_file("etc/a", b"a".to_vec(), None, "text/plain", "/etc/a", "a")
+            .unwrap();
+        extractor
+            .write_file("etc/b", b"b".to_vec(), None, "text/plain", "/etc/b", "b")
+            .unwrap();
+        assert!(extractor.written_paths.is_empty());
+        fs::create_dir_all(output_dir.join("etc/b")).unwrap();
+        assert!(extractor.flush_writes().is_err());
+        assert_eq!(extractor.written_paths, [output_dir.join("etc/a")]);
+        drop(extractor);
+        assert_eq!(capture.0, [output_dir.join("etc/a")]);
+    }
 }
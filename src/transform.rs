@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Environment variable carrying the ignition path of the file being transformed
+pub const PATH_ENV: &str = "IGNITION_PATH";
+
+/// Environment variable carrying the transform direction (`decode` or `encode`)
+pub const DIRECTION_ENV: &str = "IGNITION_TRANSFORM";
+
+/// Which way content is flowing through a transform
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Content extracted from a config during disassembly
+    Decode,
+    /// Content about to be embedded into a config during assembly
+    Encode,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Decode => "decode",
+            Direction::Encode => "encode",
+        }
+    }
+}
+
+/// Pipe `content` through `program` and return what it writes to stdout.
+///
+/// The program receives the ignition path and direction through the
+/// `IGNITION_PATH` and `IGNITION_TRANSFORM` environment variables so a single
+/// script can handle both sides of a round trip. A nonzero exit is an error.
+pub fn run_transform(
+    program: &Path,
+    content: &[u8],
+    ignition_path: &str,
+    direction: Direction,
+) -> Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .env(PATH_ENV, ignition_path)
+        .env(DIRECTION_ENV, direction.as_str())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("Failed to run transform command: {}", program.display()))?;
+
+    // Feed stdin from a separate thread so a program that writes before it
+    // finishes reading can't deadlock against us
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("Transform command has no stdin")?;
+    let input = content.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run transform command: {}", program.display()))?;
+    let written = writer
+        .join()
+        .map_err(|_| anyhow::anyhow!("Transform input writer panicked"))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Transform command {} failed for {} ({})",
+            program.display(),
+            ignition_path,
+            output.status
+        );
+    }
+
+    // A program may legitimately exit without reading all of its input
+    match written {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => {
+            return Err(e).with_context(|| {
+                format!("Failed to write to transform command for {}", ignition_path)
+            });
+        }
+        _ => (),
+    }
+
+    Ok(output.stdout)
+}
@@ -0,0 +1,67 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// A file or directory of an extracted tree
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: NodeKind,
+    /// Size in bytes, for files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Entries sorted by name, for directories
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<TreeNode>>,
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// Describe the tree rooted at `path` without following symlinks
+pub fn scan(path: &Path) -> Result<TreeNode> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let metadata =
+        fs::symlink_metadata(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    if metadata.is_dir() {
+        let mut children = fs::read_dir(path)?
+            .map(|entry| scan(&entry?.path()))
+            .collect::<Result<Vec<_>>>()?;
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(TreeNode {
+            name,
+            kind: NodeKind::Directory,
+            size: None,
+            children: Some(children),
+        })
+    } else {
+        let kind = if metadata.file_type().is_symlink() {
+            NodeKind::Symlink
+        } else {
+            NodeKind::File
+        };
+        Ok(TreeNode {
+            name,
+            kind,
+            size: Some(metadata.len()),
+            children: None,
+        })
+    }
+}
+
+/// Write the tree rooted at `root` as pretty JSON to `output`
+pub fn write_tree_json(root: &Path, output: &Path) -> Result<()> {
+    let tree = scan(root)?;
+    fs::write(output, serde_json::to_string_pretty(&tree)?)
+        .with_context(|| format!("Failed to write tree JSON: {}", output.display()))
+}
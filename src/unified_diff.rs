@@ -0,0 +1,121 @@
+/// Lines of unchanged context around each hunk, as `diff -u` prints
+const CONTEXT: usize = 3;
+
+/// One step of the line edit script turning the old text into the new one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Keep,
+    Delete,
+    Insert,
+}
+
+/// Line-based unified diff of `old` and `new` in the format of `diff -u`,
+/// with `old_label` and `new_label` in the header. Empty when the texts are
+/// equal.
+pub fn unified_diff(old_label: &str, new_label: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = edit_script(&old_lines, &new_lines);
+
+    // Position in both texts before each edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for edit in &edits {
+        positions.push((old_pos, new_pos));
+        match edit {
+            Edit::Keep => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Edit::Delete => old_pos += 1,
+            Edit::Insert => new_pos += 1,
+        }
+    }
+    positions.push((old_pos, new_pos));
+
+    let changes: Vec<usize> = (0..edits.len())
+        .filter(|&i| edits[i] != Edit::Keep)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_label, new_label);
+    let mut start = 0;
+    while start < changes.len() {
+        // Merge changes whose context would overlap into one hunk
+        let mut end = start;
+        while end + 1 < changes.len() && changes[end + 1] - changes[end] <= 2 * CONTEXT + 1 {
+            end += 1;
+        }
+        let first = changes[start].saturating_sub(CONTEXT);
+        let last = (changes[end] + CONTEXT + 1).min(edits.len());
+
+        let (old_start, new_start) = positions[first];
+        let (old_end, new_end) = positions[last];
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_end - old_start),
+            hunk_range(new_start, new_end - new_start)
+        ));
+        for (i, edit) in edits.iter().enumerate().take(last).skip(first) {
+            let (old_pos, new_pos) = positions[i];
+            let (marker, line) = match edit {
+                Edit::Keep => (' ', old_lines[old_pos]),
+                Edit::Delete => ('-', old_lines[old_pos]),
+                Edit::Insert => ('+', new_lines[new_pos]),
+            };
+            output.push(marker);
+            output.push_str(line);
+            if !line.ends_with('\n') {
+                output.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        start = end + 1;
+    }
+    output
+}
+
+/// `start,count` of a hunk header; an empty range names the line before it
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// Shortest edit script between two line lists via their longest common
+/// subsequence
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // lcs[i][j]: length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            edits.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            edits.push(Edit::Delete);
+            i += 1;
+        } else {
+            edits.push(Edit::Insert);
+            j += 1;
+        }
+    }
+    edits.extend(std::iter::repeat_n(Edit::Delete, old.len() - i));
+    edits.extend(std::iter::repeat_n(Edit::Insert, new.len() - j));
+    edits
+}
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// Sidecar recording how unit contents were encoded before `--decode-unit-contents`
+pub const UNIT_ENCODINGS_FILE: &str = "unit-encodings.json";
+
+/// Directory of the decoded tree holding extracted unit and dropin contents
+pub const UNITS_DIR: &str = "units";
+
+/// Start of the placeholder that replaces extracted unit and dropin contents
+const PLACEHOLDER_PREFIX: &str = "data:text/plain;base64-placeholder,";
+
+/// Non-standard encoding found in a unit's `contents`
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnitEncoding {
+    /// Base64 of the unit text
+    Base64,
+    /// Base64 of the gzip-compressed unit text
+    GzipBase64,
+}
+
+/// Decode unit and dropin `contents` that hold base64 (optionally gzipped)
+/// text instead of plain unit text, returning the encoding of each decoded
+/// entry keyed by `<unit>` or `<unit>.d/<dropin>`.
+pub fn decode_unit_contents(config: &mut Value) -> BTreeMap<String, UnitEncoding> {
+    let mut encodings = BTreeMap::new();
+
+    for_each_contents(config, |key, contents| {
+        if let Some((text, encoding)) = detect_encoded(contents) {
+            encodings.insert(key.to_string(), encoding);
+            Some(text)
+        } else {
+            None
+        }
+    });
+
+    encodings
+}
+
+/// Re-apply the encodings recorded by [`decode_unit_contents`]
+pub fn reencode_unit_contents(
+    config: &mut Value,
+    encodings: &BTreeMap<String, UnitEncoding>,
+) -> Result<()> {
+    let mut result = Ok(());
+
+    for_each_contents(config, |key, contents| {
+        let encoding = encodings.get(key)?;
+        match encode(contents, *encoding) {
+            Ok(encoded) => Some(encoded),
+            Err(e) => {
+                result = Err(e).with_context(|| format!("Failed to re-encode unit {}", key));
+                None
+            }
+        }
+    });
+
+    result
+}
+
+/// Replace every unit and dropin `contents` with a placeholder naming its
+/// file in the decoded tree (see [`unit_file`]), returning the key, file and
+/// text of each. Units without contents, such as masked ones, are left
+/// alone.
+///
+/// With `split_dirs`, a unit whose `[Install]` section names a target goes
+/// to a subdirectory for it, e.g. `units/multi-user.target.wants/`, and its
+/// dropins follow it.
+pub fn take_unit_contents(config: &mut Value, split_dirs: bool) -> Vec<(String, String, String)> {
+    let mut taken = Vec::new();
+    let mut groups = BTreeMap::new();
+    for_each_contents(config, |key, contents| {
+        let group = match key.split_once(".d/") {
+            // Dropins are visited after their unit
+            Some((unit, _)) => groups.get(unit).cloned(),
+            None if split_dirs => {
+                let group = install_group(contents);
+                if let Some(group) = &group {
+                    groups.insert(key.to_string(), group.clone());
+                }
+                group
+            }
+            None => None,
+        };
+        let file = match group {
+            Some(group) => unit_file(&format!("{}/{}", group, key)),
+            None => unit_file(key),
+        };
+        let placeholder = format!("{}{}", PLACEHOLDER_PREFIX, file);
+        taken.push((key.to_string(), file, contents.to_string()));
+        Some(placeholder)
+    });
+    taken
+}
+
+/// File of the decoded tree holding the contents of `key`: `units/<unit>`
+/// or `units/<unit>.d/<dropin>`
+pub fn unit_file(key: &str) -> String {
+    format!("{}/{}", UNITS_DIR, key)
+}
+
+/// Directory named after the first target of the `WantedBy=` or
+/// `RequiredBy=` lines in the `[Install]` section of `text`, e.g.
+/// `multi-user.target.wants`
+fn install_group(text: &str) -> Option<String> {
+    let mut in_install = false;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_install = line == "[Install]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_install) else {
+            continue;
+        };
+        let suffix = match key.trim() {
+            "WantedBy" => "wants",
+            "RequiredBy" => "requires",
+            _ => continue,
+        };
+        let target = value.split_whitespace().next();
+        if let Some(target) = target.filter(|t| !t.contains('/') && !t.starts_with('.')) {
+            return Some(format!("{}.{}", target, suffix));
+        }
+    }
+    None
+}
+
+/// Call `func` with the key and text of every unit and dropin `contents`,
+/// replacing the text when it returns a new value
+fn for_each_contents<F>(config: &mut Value, mut func: F)
+where
+    F: FnMut(&str, &str) -> Option<String>,
+{
+    let units = config
+        .pointer_mut("/systemd/units")
+        .and_then(|u| u.as_array_mut());
+
+    for unit in units.into_iter().flatten() {
+        let name = unit
+            .get("name")
+            .and_then(|n| n.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        if let Some(contents) = unit.get_mut("contents") {
+            if let Some(new) = contents.as_str().and_then(|c| func(&name, c)) {
+                *contents = Value::String(new);
+            }
+        }
+
+        let dropins = unit.get_mut("dropins").and_then(|d| d.as_array_mut());
+        for dropin in dropins.into_iter().flatten() {
+            let dropin_name = dropin
+                .get("name")
+                .and_then(|n| n.as_str())
+                .unwrap_or_default();
+            let key = format!("{}.d/{}", name, dropin_name);
+            if let Some(contents) = dropin.get_mut("contents") {
+                if let Some(new) = contents.as_str().and_then(|c| func(&key, c)) {
+                    *contents = Value::String(new);
+                }
+            }
+        }
+    }
+}
+
+/// Recognize base64 or base64-wrapped gzip unit contents.
+///
+/// Plain unit text always contains whitespace or brackets and so never
+/// decodes as base64; the decoded text must also look like a unit (contain a
+/// `[Section]` header) to guard against short strings that happen to be valid
+/// base64.
+fn detect_encoded(contents: &str) -> Option<(String, UnitEncoding)> {
+    let compact: String = contents
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    if compact.is_empty() {
+        return None;
+    }
+
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(compact)
+        .ok()?;
+
+    let (bytes, encoding) = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut inflated = Vec::new();
+        flate2::read::GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut inflated)
+            .ok()?;
+        (inflated, UnitEncoding::GzipBase64)
+    } else {
+        (bytes, UnitEncoding::Base64)
+    };
+
+    let text = String::from_utf8(bytes).ok()?;
+    let looks_like_unit = text.lines().any(|line| line.trim_start().starts_with('['));
+    looks_like_unit.then_some((text, encoding))
+}
+
+fn encode(text: &str, encoding: UnitEncoding) -> Result<String> {
+    let bytes = match encoding {
+        UnitEncoding::Base64 => text.as_bytes().to_vec(),
+        UnitEncoding::GzipBase64 => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(text.as_bytes())?;
+            encoder.finish()?
+        }
+    };
+    Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+}
@@ -0,0 +1,106 @@
+use ignition_config::{Config, Error};
+use serde_json::Value;
+
+use crate::checks::MAX_MODE;
+
+/// Re-parse an assembled config with the typed parser of its spec version
+/// and check the modes it can't range-check, failing with the JSON pointer
+/// of the first offending field. Unused-key warnings are not errors.
+pub fn validate_assembled(json: &str) -> anyhow::Result<()> {
+    match Config::parse_str(json) {
+        Ok(_) => {}
+        Err(Error::Serialization(e)) => {
+            let pointer = pointer_at(json, byte_offset(json, e.line(), e.column()));
+            anyhow::bail!("Assembled config is invalid at {}: {}", pointer, e);
+        }
+        Err(e) => anyhow::bail!("Assembled config is invalid at /ignition/version: {}", e),
+    }
+
+    let config: Value = serde_json::from_str(json)?;
+    for section in ["files", "directories"] {
+        let entries = config
+            .pointer(&format!("/storage/{}", section))
+            .and_then(|v| v.as_array());
+        for (index, entry) in entries.into_iter().flatten().enumerate() {
+            let Some(mode) = entry.get("mode").filter(|m| !m.is_null()) else {
+                continue;
+            };
+            if !mode.as_i64().is_some_and(|m| (0..=MAX_MODE).contains(&m)) {
+                anyhow::bail!(
+                    "Assembled config is invalid at /storage/{}/{}/mode: mode {} is outside 0..=0o7777",
+                    section,
+                    index,
+                    mode
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Byte offset of the 1-based `line` and `column` serde_json reports
+fn byte_offset(json: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = json
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    (line_start + column).min(json.len())
+}
+
+/// One open container while scanning JSON text
+enum Frame {
+    /// Object and the key of the member being read, if past its name
+    Object(Option<String>),
+    /// Array and the index of the element being read
+    Array(usize),
+}
+
+/// JSON pointer of the value being read at byte `offset` of `json`
+fn pointer_at(json: &str, offset: usize) -> String {
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut chars = json.char_indices().take_while(|&(i, _)| i < offset);
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '{' => stack.push(Frame::Object(None)),
+            '[' => stack.push(Frame::Array(0)),
+            '}' | ']' => {
+                stack.pop();
+            }
+            ',' => match stack.last_mut() {
+                Some(Frame::Object(key)) => *key = None,
+                Some(Frame::Array(index)) => *index += 1,
+                None => {}
+            },
+            '"' => {
+                let mut escaped = false;
+                let mut end = None;
+                for (i, c) in chars.by_ref() {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => {
+                            end = Some(i);
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                if let (Some(end), Some(Frame::Object(key @ None))) = (end, stack.last_mut()) {
+                    *key = serde_json::from_str(&json[start..=end]).ok();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    stack
+        .iter()
+        .filter_map(|frame| match frame {
+            Frame::Object(key) => key
+                .as_ref()
+                .map(|k| format!("/{}", k.replace('~', "~0").replace('/', "~1"))),
+            Frame::Array(index) => Some(format!("/{}", index)),
+        })
+        .collect()
+}
@@ -0,0 +1,59 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How much the commands print besides their results and errors
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Errors only
+    Quiet,
+    /// Status lines and warnings
+    #[default]
+    Normal,
+    /// Also every file and config written, with its size and media type
+    Verbose,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// Set the level for the rest of the process
+pub fn set_verbosity(level: Verbosity) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// The level set by [`set_verbosity`], `Normal` until then
+pub fn verbosity() -> Verbosity {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        2 => Verbosity::Verbose,
+        _ => Verbosity::Normal,
+    }
+}
+
+/// Print a status line unless quiet, on stderr when stdout carries the
+/// output config
+pub fn status(to_stderr: bool, message: &str) {
+    if verbosity() >= Verbosity::Normal {
+        print_line(to_stderr, message);
+    }
+}
+
+/// Print a line only when verbose
+pub fn detail(to_stderr: bool, message: &str) {
+    if verbosity() >= Verbosity::Verbose {
+        print_line(to_stderr, message);
+    }
+}
+
+/// Print a warning to stderr unless quiet
+pub fn warn(message: &str) {
+    if verbosity() >= Verbosity::Normal {
+        eprintln!("Warning: {}", message);
+    }
+}
+
+fn print_line(to_stderr: bool, message: &str) {
+    if to_stderr {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
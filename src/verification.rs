@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use sha2::{Digest, Sha256, Sha512};
+use std::io::Read;
+
+use crate::streaming;
+
+/// Hash algorithms Ignition accepts in `verification.hash`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    fn prefix(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Format the digest of `content` as an Ignition `<type>-<hex>` hash
+    pub fn hash(self, content: &[u8]) -> String {
+        let digest = match self {
+            HashAlgorithm::Sha256 => Sha256::digest(content).to_vec(),
+            HashAlgorithm::Sha512 => Sha512::digest(content).to_vec(),
+        };
+        format!("{}-{}", self.prefix(), to_hex(&digest))
+    }
+
+    /// Like [`HashAlgorithm::hash`], reading the content from `reader` in
+    /// chunks
+    pub fn hash_reader(self, reader: impl Read) -> std::io::Result<String> {
+        let digest = match self {
+            HashAlgorithm::Sha256 => digest_reader::<Sha256>(reader)?,
+            HashAlgorithm::Sha512 => digest_reader::<Sha512>(reader)?,
+        };
+        Ok(format!("{}-{}", self.prefix(), to_hex(&digest)))
+    }
+}
+
+fn digest_reader<D: Digest>(mut reader: impl Read) -> std::io::Result<Vec<u8>> {
+    let mut hasher = D::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.finalize().to_vec());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+/// Split an Ignition `<type>-<hex>` hash into its algorithm and digest
+pub fn parse_hash(hash: &str) -> Result<(HashAlgorithm, &str)> {
+    let (prefix, digest) = hash
+        .split_once('-')
+        .ok_or_else(|| anyhow::anyhow!("Malformed verification hash: {}", hash))?;
+    let algorithm = match prefix {
+        "sha256" => HashAlgorithm::Sha256,
+        "sha512" => HashAlgorithm::Sha512,
+        _ => anyhow::bail!("Unsupported verification hash algorithm: {}", prefix),
+    };
+    Ok((algorithm, digest))
+}
+
+/// The `verification.hash` of a resource object, if it has one
+pub fn existing_hash(resource: &Value) -> Option<&str> {
+    resource
+        .pointer("/verification/hash")
+        .and_then(|h| h.as_str())
+}
+
+/// Replace an existing `verification.hash` on `resource` with the hash of
+/// `content`, keeping the algorithm it already used. Resources without a
+/// hash are left alone. Returns whether the hash changed.
+pub fn recompute_hash(resource: &mut Value, content: &[u8]) -> Result<bool> {
+    let Some(old_hash) = existing_hash(resource) else {
+        return Ok(false);
+    };
+    let (algorithm, _) = parse_hash(old_hash)?;
+    let new_hash = algorithm.hash(content);
+    if new_hash == old_hash {
+        return Ok(false);
+    }
+    resource["verification"]["hash"] = Value::String(new_hash);
+    Ok(true)
+}
+
+/// Check every inline `storage.files` contents and append source that carries
+/// a `verification.hash` against its decoded (and decompressed) content,
+/// reporting all mismatches at once. Remote sources are not fetched.
+pub fn verify_inline_hashes(config: &Value) -> Result<()> {
+    let mismatches = inline_hash_mismatches(config);
+    if !mismatches.is_empty() {
+        anyhow::bail!(
+            "Verification hash mismatches:\n  {}",
+            mismatches.join("\n  ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Every inline `storage.files` contents and append source whose decoded
+/// (and decompressed) content doesn't match its `verification.hash`, as
+/// `<location> (<path>): expected <hash>, got <hash>` lines. Sources without
+/// a hash and remote sources are skipped.
+pub fn inline_hash_mismatches(config: &Value) -> Vec<String> {
+    let files = config
+        .pointer("/storage/files")
+        .and_then(|f| f.as_array())
+        .into_iter()
+        .flatten();
+
+    let mut mismatches = Vec::new();
+    for (index, file) in files.enumerate() {
+        let path = file.get("path").and_then(|p| p.as_str()).unwrap_or("");
+        let mut resources = Vec::new();
+        if let Some(contents) = file.get("contents") {
+            resources.push((format!("storage.files[{}].contents", index), contents));
+        }
+        let appends = file.get("append").and_then(|a| a.as_array());
+        for (append_index, append) in appends.into_iter().flatten().enumerate() {
+            resources.push((
+                format!("storage.files[{}].append[{}]", index, append_index),
+                append,
+            ));
+        }
+
+        for (location, resource) in resources {
+            let Some(expected) = existing_hash(resource) else {
+                continue;
+            };
+            let actual = inline_content(resource).and_then(|content| match content {
+                Some(content) => {
+                    let (algorithm, _) = parse_hash(expected)?;
+                    Ok(Some(algorithm.hash(&content)))
+                }
+                None => Ok(None),
+            });
+            match actual {
+                Ok(Some(actual)) if actual != expected => mismatches.push(format!(
+                    "{} ({}): expected {}, got {}",
+                    location, path, expected, actual
+                )),
+                Ok(_) => {}
+                Err(e) => mismatches.push(format!("{} ({}): {:#}", location, path, e)),
+            }
+        }
+    }
+
+    mismatches
+}
+
+/// Decode the inline data URL of a resource and undo its `compression`,
+/// returning `None` for remote or missing sources
+pub fn inline_content(resource: &Value) -> Result<Option<Vec<u8>>> {
+    let Some(source) = resource.get("source").and_then(|s| s.as_str()) else {
+        return Ok(None);
+    };
+    if !source.starts_with("data:") {
+        return Ok(None);
+    }
+
+    let content = decode_data_url(source)?;
+
+    match resource.get("compression").and_then(|c| c.as_str()) {
+        None | Some("") => Ok(Some(content)),
+        Some("gzip") => {
+            let mut inflated = Vec::new();
+            flate2::read::GzDecoder::new(content.as_slice())
+                .read_to_end(&mut inflated)
+                .context("Failed to decompress gzip content")?;
+            Ok(Some(inflated))
+        }
+        Some(other) => anyhow::bail!("Unsupported compression '{}'", other),
+    }
+}
+
+/// Decode the content of a `data:` URL, whose base64 may use the standard
+/// or the base64url alphabet
+pub fn decode_data_url(source: &str) -> Result<Vec<u8>> {
+    if let Some(payload) = streaming::base64url_payload(source) {
+        use base64::Engine;
+        return streaming::base64_engine(true)
+            .decode(payload)
+            .context("Failed to decode base64url data URL");
+    }
+    let url = data_url::DataUrl::process(source)
+        .map_err(|e| anyhow::anyhow!("Failed to parse data URL: {:?}", e))?;
+    let (content, _) = url
+        .decode_to_vec()
+        .map_err(|e| anyhow::anyhow!("Failed to decode data URL: {:?}", e))?;
+    Ok(content)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A spec version as `(major, minor, patch)`
+pub type Version = (u64, u64, u64);
+
+/// Comparison used by one bound of a [`VersionRange`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Ge,
+    Gt,
+    Le,
+    Lt,
+    Eq,
+}
+
+/// A set of comparisons such as `>=3.3,<3.5` that a spec version must all satisfy
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionRange {
+    expr: String,
+    bounds: Vec<(Op, Version)>,
+}
+
+impl VersionRange {
+    /// Whether `version` (e.g. `3.4.0`) satisfies every bound
+    pub fn contains(&self, version: &str) -> Result<bool> {
+        let version = parse_version(version)?;
+        Ok(self.bounds.iter().all(|(op, bound)| match op {
+            Op::Ge => version >= *bound,
+            Op::Gt => version > *bound,
+            Op::Le => version <= *bound,
+            Op::Lt => version < *bound,
+            Op::Eq => version == *bound,
+        }))
+    }
+}
+
+impl FromStr for VersionRange {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        let mut bounds = Vec::new();
+        for part in expr.split(',').map(str::trim) {
+            let (op, version) = [
+                (">=", Op::Ge),
+                ("<=", Op::Le),
+                ("==", Op::Eq),
+                (">", Op::Gt),
+                ("<", Op::Lt),
+                ("=", Op::Eq),
+            ]
+            .iter()
+            .find_map(|(prefix, op)| part.strip_prefix(prefix).map(|rest| (*op, rest)))
+            .unwrap_or((Op::Eq, part));
+            let version = parse_version(version.trim())
+                .with_context(|| format!("Invalid version range '{}'", expr))?;
+            bounds.push((op, version));
+        }
+        Ok(Self {
+            expr: expr.to_string(),
+            bounds,
+        })
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+/// Whether two versions are equal, treating missing components as zero
+pub fn same_version(a: &str, b: &str) -> Result<bool> {
+    Ok(parse_version(a)? == parse_version(b)?)
+}
+
+/// Parse `3`, `3.4` or `3.4.0`, treating missing components as zero
+pub fn parse_version(version: &str) -> Result<Version> {
+    let mut parts = [0u64; 3];
+    let components: Vec<&str> = version.split('.').collect();
+    if components.len() > parts.len() {
+        anyhow::bail!("Invalid version '{}'", version);
+    }
+    for (part, component) in parts.iter_mut().zip(components) {
+        *part = component
+            .parse()
+            .with_context(|| format!("Invalid version '{}'", version))?;
+    }
+    Ok((parts[0], parts[1], parts[2]))
+}
+
+/// Read `ignition.version` from raw config text without a full typed parse
+pub fn config_version(content: &str) -> Result<String> {
+    let json: serde_json::Value =
+        serde_json::from_str(content).with_context(|| "Failed to parse config as JSON")?;
+    json.pointer("/ignition/version")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .context("Config has no ignition.version")
+}
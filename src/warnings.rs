@@ -0,0 +1,109 @@
+use ignition_config::Warning;
+use std::fmt;
+
+use crate::verbosity::{verbosity, Verbosity};
+
+/// Category of a warning raised while loading an Ignition config
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// The config contains a key not defined by its spec version
+    UnknownField,
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WarningKind::UnknownField => "unknown-field",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A categorized warning about an Ignition config
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigWarning {
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Categorize the warnings returned by `Config::parse_str`
+pub fn classify_warnings(parse_warnings: Vec<Warning>) -> Vec<ConfigWarning> {
+    parse_warnings
+        .into_iter()
+        .map(|w| ConfigWarning {
+            kind: kind_of(&w),
+            message: w.to_string(),
+        })
+        .collect()
+}
+
+fn kind_of(warning: &Warning) -> WarningKind {
+    match warning {
+        Warning::UnusedKey(_) => WarningKind::UnknownField,
+        // `Warning` is non-exhaustive, and unused keys are all it reports so far
+        _ => WarningKind::UnknownField,
+    }
+}
+
+/// Print every warning and fail if any of them belongs to a kind in `abort_on`.
+///
+/// With a `collapse_threshold`, messages repeated at least that many times
+/// are printed once with their count.
+pub fn report_warnings(
+    warnings: &[ConfigWarning],
+    abort_on: &[WarningKind],
+    collapse_threshold: Option<usize>,
+) -> anyhow::Result<()> {
+    if verbosity() >= Verbosity::Normal {
+        for line in format_warnings(warnings, collapse_threshold) {
+            eprintln!("{}", line);
+        }
+    }
+
+    if let Some(fatal) = warnings.iter().find(|w| abort_on.contains(&w.kind)) {
+        anyhow::bail!("Aborting on {} warning: {}", fatal.kind, fatal.message);
+    }
+
+    Ok(())
+}
+
+/// Render warnings as output lines, collapsing identical messages that occur
+/// at least `collapse_threshold` times into one line at their first position
+pub fn format_warnings(
+    warnings: &[ConfigWarning],
+    collapse_threshold: Option<usize>,
+) -> Vec<String> {
+    let Some(threshold) = collapse_threshold else {
+        return warnings.iter().map(|w| format!("Warning: {}", w)).collect();
+    };
+
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for warning in warnings {
+        match counts
+            .iter_mut()
+            .find(|(message, _)| *message == warning.message)
+        {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&warning.message, 1)),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (message, count) in counts {
+        if count >= threshold.max(2) {
+            lines.push(format!(
+                "Warning: {} identical warnings: {}",
+                count, message
+            ));
+        } else {
+            lines.extend(std::iter::repeat_n(format!("Warning: {}", message), count));
+        }
+    }
+    lines
+}
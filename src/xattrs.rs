@@ -0,0 +1,27 @@
+use anyhow::Result;
+use std::path::Path;
+
+/// Extended attribute holding the media type of an extracted file
+pub const MIME_TYPE_ATTR: &str = "user.mime_type";
+
+/// Extended attribute holding the absolute path a file has in the config
+pub const IGNITION_PATH_ATTR: &str = "user.ignition_path";
+
+/// Tag an extracted file with its media type and ignition path
+#[cfg(target_os = "linux")]
+pub fn set(path: &Path, media_type: &str, ignition_path: &str) -> Result<()> {
+    use anyhow::Context;
+    for (name, value) in [
+        (MIME_TYPE_ATTR, media_type),
+        (IGNITION_PATH_ATTR, ignition_path),
+    ] {
+        xattr::set(path, name, value.as_bytes())
+            .with_context(|| format!("Failed to set {} on {}", name, path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set(_path: &Path, _media_type: &str, _ignition_path: &str) -> Result<()> {
+    anyhow::bail!("Setting extended attributes is only supported on Linux")
+}
@@ -0,0 +1,266 @@
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn coder() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_fcos-ignition-coder"))
+}
+
+#[test]
+fn test_assemble_compact_to_stdout() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("test.ign");
+    let decoded_dir = temp_dir.path().join("decoded");
+
+    let test_ignition = r#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } }
+    ]
+  }
+}"#;
+    fs::write(&input_path, test_ignition).unwrap();
+
+    let output = coder()
+        .arg("disassemble")
+        .arg(&input_path)
+        .arg(&decoded_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = coder()
+        .args(["assemble", "-"])
+        .arg(&decoded_dir)
+        .args(["--compact", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // stdout holds exactly one line of JSON, without a trailing newline
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains('\n'));
+    let config: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(config["ignition"]["version"], "3.4.0");
+    assert_eq!(
+        config["storage"]["files"][0]["contents"]["source"],
+        "data:text/plain;charset=US-ASCII;base64,dGVzdCBjb250ZW50"
+    );
+
+    // Status messages are on stderr
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Embedded /etc/test"));
+    assert!(stderr.contains("Encoding complete"));
+    assert!(!temp_dir.path().join("-").exists());
+
+    let output = coder()
+        .args(["assemble", "--stdout", "--compact", "--newline", "ignored"])
+        .arg(&decoded_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.matches('\n').count(), 1);
+    assert!(stdout.ends_with("}\n"));
+
+    // --preserve-unknown is also accepted under its longer name
+    let output = coder()
+        .args([
+            "assemble",
+            "--stdout",
+            "--assemble-preserve-unknown",
+            "ignored",
+        ])
+        .arg(&decoded_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_disassemble_from_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new().unwrap();
+    let decoded_dir = temp_dir.path().join("decoded");
+
+    let mut child = coder()
+        .args(["disassemble", "-"])
+        .arg(&decoded_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(
+            br#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/piped", "contents": { "source": "data:,from%20stdin" } }
+    ]
+  }
+}"#,
+        )
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    assert_eq!(
+        fs::read_to_string(decoded_dir.join("etc/piped")).unwrap(),
+        "from stdin"
+    );
+    assert!(decoded_dir.join("decoded.ign").is_file());
+
+    // Status messages go to stderr
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Decoding complete! Extracted 1 file(s)"));
+
+    // So do the files a dry run would write
+    let mut child = coder()
+        .args(["disassemble", "--dry-run", "-"])
+        .arg(temp_dir.path().join("dry"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(br#"{"ignition": {"version": "3.4.0"}}"#)
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Would write"), "{}", stderr);
+
+    // Listing remotes reads stdin too
+    let mut child = coder()
+        .args(["remotes", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(
+            br#"{
+  "ignition": { "version": "3.4.0" },
+  "storage": {
+    "files": [
+      { "path": "/etc/remote", "contents": { "source": "https://example.com/remote" } }
+    ]
+  }
+}"#,
+        )
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("https://example.com/remote"), "{}", stdout);
+}
+
+#[test]
+fn test_quiet_and_verbose_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("test.ign");
+    fs::write(
+        &input_path,
+        r#"{
+  "ignition": { "version": "3.4.0" },
+  "extra": true,
+  "storage": {
+    "files": [
+      { "path": "/etc/test", "contents": { "source": "data:,test%20content" } }
+    ]
+  }
+}"#,
+    )
+    .unwrap();
+
+    // Nothing but errors under --quiet, not even the unused key warning
+    let output = coder()
+        .args(["--quiet", "disassemble"])
+        .arg(&input_path)
+        .arg(temp_dir.path().join("quiet"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+
+    let output = coder()
+        .args(["disassemble", "--verbose"])
+        .arg(&input_path)
+        .arg(temp_dir.path().join("verbose"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("(12 bytes, text/plain;charset=US-ASCII)"));
+    assert!(stdout.contains("decoded.ign ("));
+
+    // Errors still reach stderr
+    let output = coder()
+        .args(["disassemble", "-q"])
+        .arg(&input_path)
+        .arg(temp_dir.path().join("quiet"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("already exists"));
+
+    let output = coder()
+        .args(["--quiet", "--verbose", "list"])
+        .arg(&input_path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_hash_mismatch_reported_once() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("test.ign");
+    let config = serde_json::json!({
+        "ignition": { "version": "3.4.0" },
+        "storage": {
+            "files": [{
+                "path": "/etc/bad",
+                "contents": {
+                    "source": "data:,tampered",
+                    "verification": { "hash": format!("sha512-{}", "0".repeat(128)) }
+                }
+            }]
+        }
+    });
+    fs::write(&input_path, config.to_string()).unwrap();
+
+    let output = coder()
+        .arg("disassemble")
+        .arg(&input_path)
+        .arg(temp_dir.path().join("decoded"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stdout.matches("/etc/bad").count(), 1, "{}", stdout);
+    assert!(!stderr.contains("/etc/bad"), "{}", stderr);
+}